@@ -0,0 +1,741 @@
+/**
+ * AI request pipeline for Wurdump
+ *
+ * Builds prompts, keeps requests within the model's context window and talks
+ * to the local Ollama server. Content that does not fit the window is split
+ * into chunks, processed one by one (map) and then combined (reduce).
+ */
+
+use serde::Serialize;
+use std::time::Duration;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+use crate::ai_queue::{AIJobPriority, AIJobQueue};
+use crate::chunking::{estimate_tokens, group_within_budget, split_into_chunks};
+use crate::metrics::{self, AICallMetrics};
+use crate::output_validation::{self, OutputFormat, ValidationOutcome};
+use crate::providers::{self, ProviderConfig};
+use crate::redaction::redact_for_log;
+use crate::AITransformation;
+
+/// Model used until the user selects another one
+pub const DEFAULT_MODEL: &str = "gpt-oss:20b";
+
+/// Base URL of the local Ollama server
+pub const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+
+/// Default system prompt for clipboard transformations
+pub const SYSTEM_PROMPT: &str = "You are an AI assistant that helps transform clipboard content. Be helpful, accurate, and preserve important information. When provided with additional context, use it to give better, more relevant responses.";
+
+/// Instruction used when the user gives no prompt
+pub const DEFAULT_REQUEST: &str = "Please improve and format this content, taking into account any provided context.";
+
+/// Placeholder left in template instructions so each chunk can be inserted separately.
+/// Only templates use it; a typed prompt containing it is sent as written.
+pub const CONTENT_PLACEHOLDER: &str = "{{content}}";
+
+/// Tokens reserved for chat formatting around the messages
+const PROMPT_OVERHEAD_TOKENS: usize = 64;
+
+// Static HTTP client for connection pooling with improved configuration
+pub static HTTP_CLIENT: once_cell::sync::Lazy<reqwest::Client> = once_cell::sync::Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(120)) // Increased timeout for large models
+        .connect_timeout(std::time::Duration::from_secs(10)) // Quick connection detection
+        .pool_idle_timeout(std::time::Duration::from_secs(30)) // Keep connections alive
+        .pool_max_idle_per_host(4) // Maintain connection pool
+        .tcp_keepalive(std::time::Duration::from_secs(60)) // Keep TCP connections alive
+        .http1_title_case_headers() // Better compatibility with Ollama
+        .build()
+        .expect("Failed to create HTTP client")
+});
+
+/// Smallest remainder of the context budget worth filling with a truncated item
+const MIN_CONTEXT_ITEM_TOKENS: usize = 32;
+
+/**
+ * A context item shown to the model before the main content
+ */
+#[derive(Debug, Clone)]
+pub struct ContextItem {
+    /// Clipboard item the context came from, if it was referenced by id
+    pub item_id: Option<String>,
+    pub content_type: String,
+    pub code_language: Option<String>,
+    pub content: String,
+    /// Whether the content was cut to fit the context budget
+    pub truncated: bool,
+}
+
+impl ContextItem {
+    /**
+     * Label shown in the prompt, e.g. "code, rust"
+     */
+    pub fn label(&self) -> String {
+        let mut label = match &self.code_language {
+            Some(language) => format!("{}, {}", self.content_type, language),
+            None => self.content_type.clone(),
+        };
+        if self.truncated {
+            label.push_str(", truncated");
+        }
+        label
+    }
+}
+
+/**
+ * Keep context items within a total token budget
+ *
+ * Items are taken in order. The first item that does not fit is truncated
+ * to the remaining budget (if a useful amount is left) and the rest dropped.
+ */
+pub fn fit_context_to_budget(items: Vec<ContextItem>, budget: usize) -> Vec<ContextItem> {
+    let mut fitted = Vec::new();
+    let mut remaining = budget;
+
+    for mut item in items {
+        let tokens = estimate_tokens(&item.content);
+        if tokens <= remaining {
+            remaining -= tokens;
+            fitted.push(item);
+            continue;
+        }
+
+        if remaining >= MIN_CONTEXT_ITEM_TOKENS {
+            if let Some(head) = split_into_chunks(&item.content, remaining).into_iter().next() {
+                log::info!("✂️ Truncated context item to {} of {} tokens", remaining, tokens);
+                item.content = head;
+                item.truncated = true;
+                fitted.push(item);
+            }
+        }
+        log::info!("📏 Context budget of {} tokens reached, dropping remaining context items", budget);
+        break;
+    }
+
+    fitted
+}
+
+/**
+ * A previous message of a conversation
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatMessage {
+    /// "user" or "assistant"
+    pub role: String,
+    pub content: String,
+}
+
+/**
+ * Keep the most recent conversation turns that fit in a token budget
+ *
+ * The kept history always starts with a user message so the model never
+ * sees an answer without the instruction that produced it.
+ */
+pub fn fit_history_to_budget(history: Vec<ChatMessage>, budget: usize) -> Vec<ChatMessage> {
+    let mut kept: Vec<ChatMessage> = Vec::new();
+    let mut used = 0;
+
+    for message in history.into_iter().rev() {
+        let tokens = history_tokens(std::slice::from_ref(&message));
+        if used + tokens > budget {
+            break;
+        }
+        used += tokens;
+        kept.push(message);
+    }
+
+    kept.reverse();
+    while kept.first().map(|message| message.role != "user").unwrap_or(false) {
+        kept.remove(0);
+    }
+    kept
+}
+
+/**
+ * Tokens taken up by conversation history, including per-message formatting
+ */
+pub fn history_tokens(history: &[ChatMessage]) -> usize {
+    history.iter().map(|message| estimate_tokens(&message.content) + 4).sum()
+}
+
+/**
+ * A fully resolved AI request
+ */
+#[derive(Debug, Clone)]
+pub struct AIRequest {
+    /// Clipboard content to transform
+    pub content: String,
+    /// What the model should do; may contain CONTENT_PLACEHOLDER
+    pub instruction: String,
+    /// Whether the instruction is a user prompt or template (prefixed with "Request:")
+    pub has_custom_instruction: bool,
+    /// Whether the instruction was rendered from a prompt template, so
+    /// CONTENT_PLACEHOLDER marks where the content goes
+    pub instruction_from_template: bool,
+    /// Additional context items shown before the content
+    pub context_items: Vec<ContextItem>,
+    pub system_prompt: String,
+    pub model: String,
+    pub temperature: f64,
+    /// Maximum tokens the model may generate
+    pub max_tokens: u32,
+    /// Total tokens the model can attend to (prompt plus response)
+    pub context_window: u32,
+    /// Format the answer is validated against
+    pub output_format: OutputFormat,
+    /// Earlier turns of a conversation, sent before the new request
+    pub history: Vec<ChatMessage>,
+    /// Providers tried in order until one answers
+    pub providers: Vec<ProviderConfig>,
+    /// Time each provider gets before the next one is tried
+    pub timeout: Duration,
+}
+
+impl AIRequest {
+    /**
+     * Build the user message for the given content (or part of it)
+     *
+     * Parameters:
+     * - content: The content, or one chunk of it
+     * - part: Chunk position as (index, total) when the content was split
+     */
+    pub fn build_user_prompt(&self, content: &str, part: Option<(usize, usize)>) -> String {
+        let mut user_prompt = String::new();
+
+        // Add context items if provided
+        if !self.context_items.is_empty() {
+            user_prompt.push_str("Additional Context:\n");
+            for (i, item) in self.context_items.iter().enumerate() {
+                user_prompt.push_str(&format!("Context {} ({}):\n{}\n\n", i + 1, item.label(), item.content));
+            }
+            user_prompt.push_str("---\n\n");
+        }
+
+        let content_in_instruction = self.instruction_from_template && self.instruction.contains(CONTENT_PLACEHOLDER);
+
+        // Add main content (unless the template already placed it) and request
+        if !content_in_instruction {
+            match part {
+                Some((index, total)) => user_prompt.push_str(&format!(
+                    "Main Content (part {} of {}):\n```\n{}\n```\n\n",
+                    index + 1,
+                    total,
+                    content
+                )),
+                None => user_prompt.push_str(&format!("Main Content:\n```\n{}\n```\n\n", content)),
+            }
+        }
+
+        let instruction = if content_in_instruction {
+            self.instruction.replace(CONTENT_PLACEHOLDER, content)
+        } else {
+            self.instruction.clone()
+        };
+        if self.has_custom_instruction {
+            user_prompt.push_str(&format!("Request: {}", instruction));
+        } else {
+            user_prompt.push_str(&instruction);
+        }
+
+        if let Some((index, total)) = part {
+            user_prompt.push_str(&format!(
+                "\n\nThis is part {} of {} of a longer text. Apply the request to this part only; the parts will be combined afterwards.",
+                index + 1,
+                total
+            ));
+        }
+
+        user_prompt
+    }
+
+    /**
+     * Build the chat completion body for a user message
+     */
+    pub fn request_body(&self, user_prompt: &str) -> Value {
+        let mut messages = vec![serde_json::json!({"role": "system", "content": self.system_prompt})];
+        messages.extend(self.history.iter().map(|message| serde_json::json!(message)));
+        messages.push(serde_json::json!({"role": "user", "content": user_prompt}));
+
+        serde_json::json!({
+            "model": self.model,
+            "messages": messages,
+            "temperature": self.temperature,
+            "max_tokens": self.max_tokens,
+            "stream": false // Ensure we get complete response
+        })
+    }
+
+    /**
+     * Compute the cache key for this request
+     *
+     * The unsplit request body carries everything that affects the output:
+     * model, system prompt, content, context, instruction, temperature and
+     * token limit. Hashing it means any change to those produces a new key.
+     */
+    pub fn cache_key(&self) -> String {
+        let body = self.request_body(&self.build_user_prompt(&self.content, None));
+        let digest = Sha256::digest(body.to_string().as_bytes());
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /**
+     * Tokens available for content in a single request
+     *
+     * The context window has to hold the system prompt, conversation history,
+     * context items, instruction, the content itself and the generated response.
+     */
+    pub fn content_budget(&self) -> usize {
+        let fixed = estimate_tokens(&self.system_prompt)
+            + estimate_tokens(&self.build_user_prompt("", Some((0, 1))))
+            + history_tokens(&self.history)
+            + PROMPT_OVERHEAD_TOKENS;
+        (self.context_window as usize)
+            .saturating_sub(self.max_tokens as usize)
+            .saturating_sub(fixed)
+    }
+}
+
+/**
+ * Run a request at background priority on the shared AI queue
+ *
+ * Background jobs are not deduplicated or registered for user cancellation,
+ * and only start once no interactive request is waiting. Returns the model's
+ * answer text.
+ */
+pub async fn run_background_request(queue: &AIJobQueue, job_id: String, request: &AIRequest) -> Result<String, String> {
+    let (_cancel_tx, mut cancel_rx) = broadcast::channel(1);
+
+    let (result, _) = queue.run(
+        job_id,
+        None,
+        AIJobPriority::Background,
+        std::sync::Arc::new(|_| {}),
+        || async {
+            run_ai_request(request, &mut cancel_rx).await.map(|transformation| vec![transformation])
+        },
+    ).await;
+
+    result?
+        .into_iter()
+        .next()
+        .map(|transformation| transformation.result)
+        .ok_or_else(|| "Model returned no result".to_string())
+}
+
+/**
+ * Run an AI request and validate the answer against the expected format
+ *
+ * Structured answers (JSON, YAML, code) are stripped of fences and chatter
+ * and parsed. If parsing fails, the model is asked once to fix its answer
+ * using the parser error. The confidence reflects how validation went.
+ *
+ * Returns Err("Request cancelled by user") as soon as a cancellation signal arrives.
+ */
+pub async fn run_ai_request(
+    request: &AIRequest,
+    cancel_rx: &mut broadcast::Receiver<()>,
+) -> Result<AITransformation, String> {
+    let mut transformation = generate(request, cancel_rx).await?;
+    let format = &request.output_format;
+
+    if *format == OutputFormat::Text {
+        transformation.confidence = ValidationOutcome::Unchecked.confidence();
+        return Ok(transformation);
+    }
+
+    let cleaned = output_validation::clean_output(&transformation.result, format);
+    let outcome = match output_validation::validate_output(&cleaned, format) {
+        Ok(()) if cleaned == transformation.result.trim() => ValidationOutcome::Valid,
+        Ok(()) => ValidationOutcome::Cleaned,
+        Err(error) => {
+            log::info!("🔧 AI output is not valid {} ({}), asking the model to repair it", format.name(), error);
+            let repair_prompt = output_validation::repair_prompt(&cleaned, format, &error);
+            let repaired = send_request(request, &repair_prompt, cancel_rx).await?;
+            let repaired_result = output_validation::clean_output(&repaired.result, format);
+
+            match output_validation::validate_output(&repaired_result, format) {
+                Ok(()) => {
+                    transformation.result = repaired_result;
+                    ValidationOutcome::Repaired
+                }
+                Err(error) => {
+                    log::warn!("⚠️ AI output is still not valid {} after repair: {}", format.name(), error);
+                    transformation.result = cleaned.clone();
+                    transformation.description = format!("The output may not be valid {}: {}", format.name(), error);
+                    ValidationOutcome::Invalid
+                }
+            }
+        }
+    };
+
+    if matches!(outcome, ValidationOutcome::Valid | ValidationOutcome::Cleaned) {
+        transformation.result = cleaned;
+    }
+    transformation.confidence = outcome.confidence();
+    Ok(transformation)
+}
+
+/**
+ * Generate an answer, splitting the content when it exceeds the context window
+ */
+async fn generate(
+    request: &AIRequest,
+    cancel_rx: &mut broadcast::Receiver<()>,
+) -> Result<AITransformation, String> {
+    let content_tokens = estimate_tokens(&request.content);
+    let budget = request.content_budget();
+
+    if budget == 0 {
+        return Err(format!(
+            "The context window ({} tokens) is too small for the prompt and a {}-token response",
+            request.context_window, request.max_tokens
+        ));
+    }
+
+    if content_tokens <= budget {
+        let user_prompt = request.build_user_prompt(&request.content, None);
+        log_prompt(&request.system_prompt, &user_prompt);
+        return send_request(request, &user_prompt, cancel_rx).await;
+    }
+
+    // MAP: process each chunk independently
+    let chunks = split_into_chunks(&request.content, budget);
+    log::info!(
+        "✂️  Content has {} tokens (budget {}), processing in {} chunks",
+        content_tokens,
+        budget,
+        chunks.len()
+    );
+
+    let mut partials = Vec::with_capacity(chunks.len());
+    for (index, chunk) in chunks.iter().enumerate() {
+        let user_prompt = request.build_user_prompt(chunk, Some((index, chunks.len())));
+        log::debug!("🧩 Processing chunk {}/{}", index + 1, chunks.len());
+        let transformation = send_request(request, &user_prompt, cancel_rx).await?;
+        partials.push(transformation.result);
+    }
+
+    // REDUCE: combine partial results until a single answer remains
+    let instruction = if request.instruction_from_template {
+        request.instruction.replace(CONTENT_PLACEHOLDER, "the provided text")
+    } else {
+        request.instruction.clone()
+    };
+    let reduce_budget = (request.context_window as usize)
+        .saturating_sub(request.max_tokens as usize)
+        .saturating_sub(
+            estimate_tokens(&request.system_prompt)
+                + estimate_tokens(&instruction)
+                + history_tokens(&request.history)
+                + PROMPT_OVERHEAD_TOKENS * 2,
+        );
+
+    let mut answered_by = (None, None);
+    while partials.len() > 1 {
+        let groups = group_within_budget(std::mem::take(&mut partials), reduce_budget);
+        log::debug!("🧩 Combining partial results in {} group(s)", groups.len());
+
+        for group in groups {
+            let mut user_prompt = format!(
+                "The following are partial results produced by applying this request to consecutive parts of a longer text:\n\nRequest: {}\n\n",
+                instruction
+            );
+            for (i, partial) in group.iter().enumerate() {
+                user_prompt.push_str(&format!("Partial result {}:\n{}\n\n", i + 1, partial));
+            }
+            user_prompt.push_str("Combine them into a single coherent result. Do not mention the parts.");
+
+            let transformation = send_request(request, &user_prompt, cancel_rx).await?;
+            answered_by = (transformation.provider, transformation.model);
+            partials.push(transformation.result);
+        }
+    }
+
+    let result = partials.pop().unwrap_or_default();
+    Ok(AITransformation {
+        id: format!("ai-{}", Uuid::new_v4()),
+        title: "AI Enhanced Content".to_string(),
+        description: format!("AI-processed content in {} parts", chunks.len()),
+        result,
+        confidence: ValidationOutcome::Unchecked.confidence(),
+        is_applied: false,
+        transformation_type: "enhancement".to_string(),
+        is_cached: false,
+        provider: answered_by.0,
+        model: answered_by.1,
+        context_sources: Vec::new(),
+        language: None,
+    })
+}
+
+/**
+ * Log the prompt being sent, truncated to keep logs readable
+ */
+fn log_prompt(system_prompt: &str, user_prompt: &str) {
+    // Logs only ever see redacted text, even when redaction of requests is turned off
+    let user_prompt = redact_for_log(user_prompt);
+    log::info!("📝 Final prompt being sent to AI:");
+    log::info!("System: {}", redact_for_log(system_prompt));
+    log::info!("User prompt (first 500 chars): {}",
+              &user_prompt.chars().take(500).collect::<String>());
+    if user_prompt.len() > 500 {
+        log::info!("... (prompt continues for {} more chars)", user_prompt.len() - 500);
+    }
+}
+
+/**
+ * Send a user message to the request's providers in order
+ *
+ * Moves on to the next provider when one times out or cannot be reached;
+ * any other error ends the request. The answer records which provider and
+ * model produced it.
+ */
+async fn send_request(
+    request: &AIRequest,
+    user_prompt: &str,
+    cancel_rx: &mut broadcast::Receiver<()>,
+) -> Result<AITransformation, String> {
+    let request_body = request.request_body(user_prompt);
+    let mut last_error = "No AI provider configured".to_string();
+
+    for (index, provider) in request.providers.iter().enumerate() {
+        if index > 0 {
+            log::info!("↪️  Falling back to {} ({})", provider.name, provider.model);
+        }
+
+        match request_with_retries(provider, &request_body, request.timeout, cancel_rx).await {
+            Ok(transformation) => return Ok(transformation),
+            Err(e) if providers::is_failover_error(&e) => {
+                log::warn!("⚠️  {} ({}) did not answer: {}", provider.name, provider.model, e);
+                last_error = e;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(last_error)
+}
+
+/**
+ * Send a request with retries, exponential backoff and cancellation support
+ *
+ * All attempts share the provider's time budget.
+ */
+async fn request_with_retries(
+    provider: &ProviderConfig,
+    request_body: &Value,
+    timeout: Duration,
+    cancel_rx: &mut broadcast::Receiver<()>,
+) -> Result<AITransformation, String> {
+    // Retry logic with exponential backoff
+    let max_retries = 3;
+    let mut last_error = String::new();
+    let deadline = tokio::time::Instant::now() + timeout;
+
+    for attempt in 1..=max_retries {
+        log::debug!("🔄 AI request attempt {}/{}", attempt, max_retries);
+
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        if remaining.is_zero() {
+            last_error = format!("AI request to {} timed out ({}s)", provider.name, timeout.as_secs());
+            break;
+        }
+
+        // Make the request with cancellation support
+        let result = tokio::select! {
+            response_result = make_ai_request(provider, request_body, remaining) => {
+                response_result
+            }
+            _ = cancel_rx.recv() => {
+                return Err("Request cancelled by user".to_string());
+            }
+        };
+
+        match result {
+            Ok(transformation) => {
+                log::info!("✅ AI request completed successfully on attempt {}", attempt);
+                return Ok(transformation);
+            }
+            Err(e) => {
+                last_error = e;
+                log::warn!("⚠️  AI request attempt {} failed: {}", attempt, last_error);
+
+                // Don't retry for certain errors
+                if last_error.contains("cancelled") ||
+                   last_error.contains("Invalid response format") ||
+                   providers::is_failover_error(&last_error) {
+                    break;
+                }
+
+                // Exponential backoff before retry
+                if attempt < max_retries {
+                    let delay = std::time::Duration::from_millis(1000 * (2_u64.pow(attempt - 1)));
+                    log::debug!("⏳ Waiting {}ms before retry", delay.as_millis());
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    log::error!("❌ AI processing failed after {} attempts: {}", max_retries, last_error);
+    Err(format!("AI processing failed after {} attempts: {}", max_retries, last_error))
+}
+
+/**
+ * Helper function to make AI requests with improved error handling
+ *
+ * Records usage and timing of the call, whether it succeeds or not.
+ */
+async fn make_ai_request(provider: &ProviderConfig, request_body: &Value, timeout: Duration) -> Result<AITransformation, String> {
+    let started_at = std::time::Instant::now();
+    let result = providers::chat_completion(provider, request_body, timeout).await;
+    let latency_ms = started_at.elapsed().as_millis() as u64;
+
+    // Fall back to local estimates when the provider does not report usage
+    let estimated_prompt_tokens = || {
+        request_body["messages"]
+            .as_array()
+            .map(|messages| {
+                messages
+                    .iter()
+                    .filter_map(|message| message["content"].as_str())
+                    .map(estimate_tokens)
+                    .sum::<usize>() as u32
+            })
+            .unwrap_or(0)
+    };
+
+    let call = match &result {
+        Ok(completion) => {
+            let prompt_tokens = completion.prompt_tokens.unwrap_or_else(estimated_prompt_tokens);
+            let completion_tokens = completion
+                .completion_tokens
+                .unwrap_or_else(|| estimate_tokens(&completion.content) as u32);
+            AICallMetrics {
+                id: Uuid::new_v4().to_string(),
+                provider: provider.name.clone(),
+                model: provider.model.clone(),
+                prompt_tokens,
+                completion_tokens,
+                usage_estimated: completion.prompt_tokens.is_none() || completion.completion_tokens.is_none(),
+                time_to_first_token_ms: completion.time_to_first_token.map(|ttft| ttft.as_millis() as u64),
+                latency_ms,
+                cost: provider.cost(prompt_tokens, completion_tokens),
+                success: true,
+                error: None,
+                created_at: chrono::Utc::now(),
+            }
+        }
+        Err(e) => AICallMetrics {
+            id: Uuid::new_v4().to_string(),
+            provider: provider.name.clone(),
+            model: provider.model.clone(),
+            prompt_tokens: estimated_prompt_tokens(),
+            completion_tokens: 0,
+            usage_estimated: true,
+            time_to_first_token_ms: None,
+            latency_ms,
+            cost: None,
+            success: false,
+            error: Some(e.clone()),
+            created_at: chrono::Utc::now(),
+        },
+    };
+    metrics::record(call);
+
+    let completion = result?;
+    let transformation = AITransformation {
+        id: format!("ai-{}", Uuid::new_v4()),
+        title: "AI Enhanced Content".to_string(),
+        description: "AI-processed content with context".to_string(),
+        result: completion.content,
+        confidence: ValidationOutcome::Unchecked.confidence(),
+        is_applied: false,
+        transformation_type: "enhancement".to_string(),
+        is_cached: false,
+        provider: Some(provider.name.clone()),
+        model: Some(provider.model.clone()),
+        context_sources: Vec::new(),
+        language: None,
+    };
+
+    Ok(transformation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(instruction: &str, from_template: bool) -> AIRequest {
+        AIRequest {
+            content: "let x = 1;".to_string(),
+            instruction: instruction.to_string(),
+            has_custom_instruction: true,
+            instruction_from_template: from_template,
+            context_items: Vec::new(),
+            system_prompt: SYSTEM_PROMPT.to_string(),
+            model: DEFAULT_MODEL.to_string(),
+            temperature: 0.7,
+            max_tokens: 100,
+            context_window: 8192,
+            output_format: OutputFormat::Text,
+            history: Vec::new(),
+            providers: vec![ProviderConfig::local_ollama(DEFAULT_MODEL)],
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    #[test]
+    fn template_places_content_at_placeholder() {
+        let prompt = request("Explain:\n{{content}}\nBriefly.", true).build_user_prompt("let x = 1;", None);
+        assert_eq!(prompt, "Request: Explain:\nlet x = 1;\nBriefly.");
+    }
+
+    #[test]
+    fn typed_prompt_with_placeholder_keeps_main_content() {
+        let prompt = request("What does {{content}} do in Handlebars?", false).build_user_prompt("let x = 1;", None);
+        assert!(prompt.starts_with("Main Content:\n```\nlet x = 1;\n```\n\n"));
+        assert!(prompt.ends_with("Request: What does {{content}} do in Handlebars?"));
+    }
+
+    #[test]
+    fn chunked_prompts_name_the_part() {
+        let prompt = request("Summarize", false).build_user_prompt("part", Some((1, 3)));
+        assert!(prompt.starts_with("Main Content (part 2 of 3):"));
+        assert!(prompt.contains("This is part 2 of 3 of a longer text."));
+    }
+
+    #[test]
+    fn context_items_come_before_content() {
+        let mut request = request("Fix it", false);
+        request.context_items.push(ContextItem {
+            item_id: None,
+            content_type: "code".to_string(),
+            code_language: Some("rust".to_string()),
+            content: "fn helper() {}".to_string(),
+            truncated: true,
+        });
+        let prompt = request.build_user_prompt("let x = 1;", None);
+        assert!(prompt.starts_with("Additional Context:\nContext 1 (code, rust, truncated):\nfn helper() {}\n\n---\n\n"));
+    }
+
+    #[test]
+    fn context_budget_truncates_then_drops() {
+        let item = |content: &str| ContextItem {
+            item_id: None,
+            content_type: "text".to_string(),
+            code_language: None,
+            content: content.to_string(),
+            truncated: false,
+        };
+        let long = "word ".repeat(400);
+        let fitted = fit_context_to_budget(vec![item("short note"), item(&long), item("dropped")], 200);
+        assert_eq!(fitted.len(), 2);
+        assert!(!fitted[0].truncated);
+        assert!(fitted[1].truncated);
+        assert!(estimate_tokens(&fitted[1].content) <= 200);
+    }
+}
@@ -0,0 +1,511 @@
+/**
+ * AI job scheduler for Wurdump
+ *
+ * A single local model can only serve so many requests at once, so every
+ * generation request goes through this queue. It enforces a configurable concurrency limit,
+ * runs interactive jobs before background ones (FIFO within each priority),
+ * reports queue positions to waiting jobs, and lets identical requests that
+ * are already in flight share one model call.
+ */
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, oneshot};
+use crate::AITransformation;
+
+/// Result shared between deduplicated jobs
+pub type AIJobResult = Result<Vec<AITransformation>, String>;
+
+/// Callback receiving a job's queue position (0 = running, 1 = next in line, ...)
+pub type PositionCallback = Arc<dyn Fn(usize) + Send + Sync>;
+
+/// Position updates collected under the queue lock and delivered after it is released
+type PositionUpdates = Vec<(PositionCallback, usize)>;
+
+/**
+ * Scheduling priority of an AI job
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AIJobPriority {
+    /// Requested by the user and awaited in the UI
+    #[default]
+    Interactive,
+    /// Enrichment and other work nobody is waiting on
+    Background,
+}
+
+/**
+ * Queue position update emitted to the frontend
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuePositionEvent {
+    #[serde(rename = "jobId")]
+    pub job_id: String,
+    /// 0 when the job is running, otherwise its place in line
+    pub position: usize,
+    pub priority: AIJobPriority,
+}
+
+struct Waiter {
+    /// Unique per queued job, unlike the caller-supplied job id
+    seq: u64,
+    priority: AIJobPriority,
+    ready: oneshot::Sender<()>,
+    on_position: PositionCallback,
+    /// Last position reported, to avoid repeating unchanged positions
+    position: usize,
+}
+
+struct QueueState {
+    max_concurrent: usize,
+    running: usize,
+    waiting: VecDeque<Waiter>,
+    next_seq: u64,
+    in_flight: HashMap<String, broadcast::Sender<AIJobResult>>,
+}
+
+impl QueueState {
+    /**
+     * Start waiting jobs while there is capacity, then refresh positions
+     *
+     * Returns the position updates to report; callers pass them to notify
+     * once the lock is released, since the callbacks emit events.
+     */
+    #[must_use]
+    fn dispatch(&mut self) -> PositionUpdates {
+        let mut updates = PositionUpdates::new();
+
+        while self.running < self.max_concurrent {
+            let Some(waiter) = self.waiting.pop_front() else {
+                break;
+            };
+            // A closed channel means the job was abandoned while waiting
+            if waiter.ready.send(()).is_ok() {
+                self.running += 1;
+                updates.push((waiter.on_position, 0));
+            }
+        }
+
+        for (index, waiter) in self.waiting.iter_mut().enumerate() {
+            if waiter.position != index + 1 {
+                waiter.position = index + 1;
+                updates.push((Arc::clone(&waiter.on_position), waiter.position));
+            }
+        }
+
+        updates
+    }
+
+    /**
+     * Insert a waiter behind every job of the same or higher priority
+     */
+    fn enqueue(&mut self, waiter: Waiter) {
+        let index = match waiter.priority {
+            AIJobPriority::Interactive => self
+                .waiting
+                .iter()
+                .position(|queued| queued.priority == AIJobPriority::Background)
+                .unwrap_or(self.waiting.len()),
+            AIJobPriority::Background => self.waiting.len(),
+        };
+        self.waiting.insert(index, waiter);
+    }
+}
+
+/**
+ * Deliver position updates returned by dispatch
+ */
+fn notify(updates: PositionUpdates) {
+    for (on_position, position) in updates {
+        on_position(position);
+    }
+}
+
+/**
+ * Bounded, prioritized queue for AI jobs
+ */
+pub struct AIJobQueue {
+    state: Arc<Mutex<QueueState>>,
+}
+
+impl Default for AIJobQueue {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl AIJobQueue {
+    /**
+     * Create a queue that runs at most `max_concurrent` jobs at a time
+     */
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(QueueState {
+                max_concurrent: max_concurrent.max(1),
+                running: 0,
+                waiting: VecDeque::new(),
+                next_seq: 0,
+                in_flight: HashMap::new(),
+            })),
+        }
+    }
+
+    /**
+     * Change the concurrency limit; waiting jobs start immediately if it grew
+     */
+    pub fn set_max_concurrent(&self, max_concurrent: usize) {
+        let updates = {
+            let mut state = self.state.lock().unwrap();
+            state.max_concurrent = max_concurrent.max(1);
+            state.dispatch()
+        };
+        notify(updates);
+    }
+
+    /**
+     * Run a job once a slot is free
+     *
+     * If a job with the same `dedup_key` is already queued or running, this
+     * call waits for that job's result instead of running its own. Returns
+     * the result and whether it was shared from another job.
+     *
+     * Dropping the returned future (e.g. on cancellation) removes the job
+     * from the queue and releases its slot.
+     */
+    pub async fn run<F, Fut>(
+        &self,
+        job_id: String,
+        dedup_key: Option<String>,
+        priority: AIJobPriority,
+        on_position: PositionCallback,
+        job: F,
+    ) -> (AIJobResult, bool)
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = AIJobResult>,
+    {
+        // Join an identical job that is already in flight
+        let (leader, follower) = match &dedup_key {
+            Some(key) => {
+                let mut state = self.state.lock().unwrap();
+                match state.in_flight.get(key) {
+                    Some(sender) => (None, Some(sender.subscribe())),
+                    None => {
+                        let (sender, _) = broadcast::channel(1);
+                        state.in_flight.insert(key.clone(), sender.clone());
+                        let guard = InFlightGuard {
+                            state: Arc::clone(&self.state),
+                            key: key.clone(),
+                            sender,
+                        };
+                        (Some(guard), None)
+                    }
+                }
+            }
+            None => (None, None),
+        };
+
+        if let Some(mut receiver) = follower {
+            log::info!("🔁 AI job {} joined an identical in-flight request", job_id);
+            let result = receiver
+                .recv()
+                .await
+                .unwrap_or_else(|_| Err("The identical request this job was waiting on was cancelled".to_string()));
+            return (result, true);
+        }
+
+        let _slot = self.acquire(priority, on_position).await;
+        let result = job().await;
+
+        if let Some(leader) = leader {
+            leader.finish(&result);
+        }
+
+        (result, false)
+    }
+
+    /**
+     * Wait for a free slot
+     */
+    async fn acquire(&self, priority: AIJobPriority, on_position: PositionCallback) -> SlotGuard {
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let (seq, updates) = {
+            let mut state = self.state.lock().unwrap();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.enqueue(Waiter {
+                seq,
+                priority,
+                ready: ready_tx,
+                on_position,
+                position: 0,
+            });
+            (seq, state.dispatch())
+        };
+        notify(updates);
+
+        let mut waiting = WaitGuard {
+            state: Arc::clone(&self.state),
+            seq,
+            ready: ready_rx,
+            granted: false,
+        };
+
+        // The sender only drops without sending if the queue itself is gone
+        let _ = (&mut waiting.ready).await;
+        waiting.granted = true;
+
+        SlotGuard {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+/**
+ * Removes a job from the waiting list if it is abandoned before starting
+ */
+struct WaitGuard {
+    state: Arc<Mutex<QueueState>>,
+    seq: u64,
+    ready: oneshot::Receiver<()>,
+    granted: bool,
+}
+
+impl Drop for WaitGuard {
+    fn drop(&mut self) {
+        if self.granted {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if self.ready.try_recv().is_ok() {
+            // A slot was handed over just before the job was abandoned
+            state.running = state.running.saturating_sub(1);
+        } else {
+            state.waiting.retain(|waiter| waiter.seq != self.seq);
+        }
+        let updates = state.dispatch();
+        drop(state);
+        notify(updates);
+    }
+}
+
+/**
+ * Holds a running slot and frees it when the job ends
+ */
+struct SlotGuard {
+    state: Arc<Mutex<QueueState>>,
+}
+
+impl Drop for SlotGuard {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.running = state.running.saturating_sub(1);
+        let updates = state.dispatch();
+        drop(state);
+        notify(updates);
+    }
+}
+
+/**
+ * Registration of a job that identical requests can join
+ *
+ * Dropping it without finishing (cancellation) closes the channel so that
+ * joined jobs stop waiting.
+ */
+struct InFlightGuard {
+    state: Arc<Mutex<QueueState>>,
+    key: String,
+    sender: broadcast::Sender<AIJobResult>,
+}
+
+impl InFlightGuard {
+    fn finish(self, result: &AIJobResult) {
+        // Unregister first so later requests start a fresh job
+        self.unregister();
+        let _ = self.sender.send(result.clone());
+    }
+
+    fn unregister(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state
+            .in_flight
+            .get(&self.key)
+            .map(|sender| sender.same_channel(&self.sender))
+            .unwrap_or(false)
+        {
+            state.in_flight.remove(&self.key);
+        }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::task::JoinHandle;
+
+    type Log = Arc<Mutex<Vec<&'static str>>>;
+
+    fn no_position() -> PositionCallback {
+        Arc::new(|_| {})
+    }
+
+    fn waiting_len(queue: &AIJobQueue) -> usize {
+        queue.state.lock().unwrap().waiting.len()
+    }
+
+    async fn wait_for_running(queue: &AIJobQueue, running: usize) {
+        while queue.state.lock().unwrap().running != running {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    async fn wait_for_waiting(queue: &AIJobQueue, len: usize) {
+        while waiting_len(queue) != len {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    /// Runs a job that holds its slot until `release` fires
+    fn spawn_blocker(queue: &Arc<AIJobQueue>, dedup_key: Option<&str>, release: oneshot::Receiver<()>, log: &Log) -> JoinHandle<(AIJobResult, bool)> {
+        let queue = Arc::clone(queue);
+        let dedup_key = dedup_key.map(str::to_string);
+        let log = Arc::clone(log);
+        tokio::spawn(async move {
+            queue
+                .run("blocker".to_string(), dedup_key, AIJobPriority::Interactive, no_position(), move || async move {
+                    let _ = release.await;
+                    log.lock().unwrap().push("blocker");
+                    Ok(Vec::new())
+                })
+                .await
+        })
+    }
+
+    fn spawn_job(queue: &Arc<AIJobQueue>, name: &'static str, dedup_key: Option<&str>, priority: AIJobPriority, log: &Log) -> JoinHandle<(AIJobResult, bool)> {
+        let queue = Arc::clone(queue);
+        let dedup_key = dedup_key.map(str::to_string);
+        let log = Arc::clone(log);
+        tokio::spawn(async move {
+            queue
+                .run(name.to_string(), dedup_key, priority, no_position(), move || async move {
+                    log.lock().unwrap().push(name);
+                    Ok(Vec::new())
+                })
+                .await
+        })
+    }
+
+    #[tokio::test]
+    async fn interactive_jobs_run_before_background_jobs() {
+        let queue = Arc::new(AIJobQueue::new(1));
+        let log = Log::default();
+        let (release, release_rx) = oneshot::channel();
+        let blocker = spawn_blocker(&queue, None, release_rx, &log);
+        wait_for_running(&queue, 1).await;
+
+        let background = spawn_job(&queue, "background", None, AIJobPriority::Background, &log);
+        wait_for_waiting(&queue, 1).await;
+        let first = spawn_job(&queue, "first", None, AIJobPriority::Interactive, &log);
+        wait_for_waiting(&queue, 2).await;
+        let second = spawn_job(&queue, "second", None, AIJobPriority::Interactive, &log);
+        wait_for_waiting(&queue, 3).await;
+
+        release.send(()).unwrap();
+        for handle in [blocker, background, first, second] {
+            assert!(handle.await.unwrap().0.is_ok());
+        }
+        assert_eq!(*log.lock().unwrap(), vec!["blocker", "first", "second", "background"]);
+    }
+
+    #[tokio::test]
+    async fn identical_jobs_share_one_run() {
+        let queue = Arc::new(AIJobQueue::new(2));
+        let log = Log::default();
+        let (release, release_rx) = oneshot::channel();
+        let leader = spawn_blocker(&queue, Some("same"), release_rx, &log);
+        while !queue.state.lock().unwrap().in_flight.contains_key("same") {
+            tokio::task::yield_now().await;
+        }
+        let follower = spawn_job(&queue, "follower", Some("same"), AIJobPriority::Interactive, &log);
+        while queue.state.lock().unwrap().in_flight["same"].receiver_count() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        release.send(()).unwrap();
+        let (leader_result, leader_shared) = leader.await.unwrap();
+        let (follower_result, follower_shared) = follower.await.unwrap();
+        assert!(leader_result.is_ok() && follower_result.is_ok());
+        assert!(!leader_shared);
+        assert!(follower_shared);
+        assert_eq!(*log.lock().unwrap(), vec!["blocker"]);
+        assert!(queue.state.lock().unwrap().in_flight.is_empty());
+    }
+
+    #[tokio::test]
+    async fn abandoned_job_only_removes_itself() {
+        let queue = Arc::new(AIJobQueue::new(1));
+        let log = Log::default();
+        let (release, release_rx) = oneshot::channel();
+        let blocker = spawn_blocker(&queue, None, release_rx, &log);
+        wait_for_running(&queue, 1).await;
+
+        // Both jobs use the same id; cancelling one must not drop the other
+        let cancelled = spawn_job(&queue, "duplicate", None, AIJobPriority::Interactive, &log);
+        wait_for_waiting(&queue, 1).await;
+        let kept = spawn_job(&queue, "duplicate", None, AIJobPriority::Interactive, &log);
+        wait_for_waiting(&queue, 2).await;
+
+        cancelled.abort();
+        assert!(cancelled.await.unwrap_err().is_cancelled());
+        assert_eq!(waiting_len(&queue), 1);
+
+        release.send(()).unwrap();
+        blocker.await.unwrap().0.unwrap();
+        kept.await.unwrap().0.unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["blocker", "duplicate"]);
+        assert_eq!(queue.state.lock().unwrap().running, 0);
+    }
+
+    #[tokio::test]
+    async fn positions_are_reported_outside_the_lock() {
+        let queue = Arc::new(AIJobQueue::new(1));
+        let log = Log::default();
+        let (release, release_rx) = oneshot::channel();
+        let blocker = spawn_blocker(&queue, None, release_rx, &log);
+        wait_for_running(&queue, 1).await;
+
+        // Report whether the queue could be locked from inside the callback
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let on_position: PositionCallback = {
+            let state = Arc::clone(&queue.state);
+            let reports = Arc::clone(&reports);
+            Arc::new(move |position| {
+                let unlocked = state.try_lock().is_ok();
+                reports.lock().unwrap().push((position, unlocked));
+            })
+        };
+        let waiter = {
+            let queue = Arc::clone(&queue);
+            tokio::spawn(async move {
+                queue
+                    .run("watched".to_string(), None, AIJobPriority::Interactive, on_position, || async { Ok(Vec::new()) })
+                    .await
+            })
+        };
+        wait_for_waiting(&queue, 1).await;
+
+        release.send(()).unwrap();
+        blocker.await.unwrap().0.unwrap();
+        waiter.await.unwrap().0.unwrap();
+        assert_eq!(*reports.lock().unwrap(), vec![(1, true), (0, true)]);
+    }
+}
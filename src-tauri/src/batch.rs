@@ -0,0 +1,118 @@
+/**
+ * Batch AI processing for Wurdump
+ *
+ * Applies one prompt to many clipboard history items, selected by id or by
+ * a filter. Items go through the regular AI pipeline one at a time, so each
+ * result is cached and recorded against its item, and progress is reported
+ * as 'ai-batch-progress' events. A failed item does not stop the batch.
+ */
+
+use serde::{Deserialize, Serialize};
+use crate::{AIProcessOptions, AITransformation, ClipboardFilter, ClipboardItem};
+
+/// Upper bound on the number of items in one batch; longer id lists are rejected, filters are cut off
+pub const MAX_BATCH_ITEMS: u32 = 50;
+
+/// Characters of an item shown in headings of the combined output
+const HEADING_PREVIEW_CHARS: usize = 60;
+
+/**
+ * A prompt to apply to several clipboard items
+ */
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BatchAIRequest {
+    /// Items to process; takes precedence over the filter
+    #[serde(rename = "itemIds")]
+    pub item_ids: Vec<String>,
+    /// Selects the items when no ids are given
+    pub filter: Option<ClipboardFilter>,
+    /// Instruction applied to every item; a template can be chosen in the options instead
+    pub prompt: Option<String>,
+    /// Options applied to every item's request
+    pub options: AIProcessOptions,
+    /// Also merge the per-item results into one output
+    pub combine: bool,
+    /// Ask the model to merge the results with this instruction instead of concatenating them
+    #[serde(rename = "combinePrompt")]
+    pub combine_prompt: Option<String>,
+    /// Caller-chosen id reported in progress events
+    #[serde(rename = "batchId")]
+    pub batch_id: Option<String>,
+}
+
+/**
+ * Outcome of processing one item in a batch
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchItemResult {
+    #[serde(rename = "itemId")]
+    pub item_id: String,
+    pub transformations: Vec<AITransformation>,
+    /// Why the item failed, if it did
+    pub error: Option<String>,
+}
+
+/**
+ * Outcome of a whole batch
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchAIResult {
+    #[serde(rename = "batchId")]
+    pub batch_id: String,
+    pub results: Vec<BatchItemResult>,
+    pub succeeded: usize,
+    pub failed: usize,
+    /// Merged output, when requested and at least one item succeeded
+    pub combined: Option<AITransformation>,
+}
+
+/**
+ * Payload of 'ai-batch-progress' events, sent after each item
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchProgressEvent {
+    #[serde(rename = "batchId")]
+    pub batch_id: String,
+    #[serde(rename = "itemId")]
+    pub item_id: String,
+    /// Items finished so far, including this one
+    pub completed: usize,
+    pub total: usize,
+    pub error: Option<String>,
+}
+
+/**
+ * Join the successful results into one document, one section per item
+ */
+pub fn combine_results(items: &[ClipboardItem], results: &[BatchItemResult]) -> String {
+    results
+        .iter()
+        .filter_map(|result| {
+            let transformation = result.transformations.first()?;
+            let heading = items
+                .iter()
+                .find(|item| item.id == result.item_id)
+                .map(item_heading)
+                .unwrap_or_else(|| result.item_id.clone());
+            Some(format!("## {}\n\n{}", heading, transformation.result.trim()))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/**
+ * Short heading for an item: its title if enriched, else the start of its preview
+ */
+fn item_heading(item: &ClipboardItem) -> String {
+    if let Some(title) = &item.title {
+        return title.clone();
+    }
+
+    let first_line = item.preview.lines().next().unwrap_or_default().trim();
+    if first_line.chars().count() > HEADING_PREVIEW_CHARS {
+        format!("{}…", first_line.chars().take(HEADING_PREVIEW_CHARS).collect::<String>())
+    } else {
+        first_line.to_string()
+    }
+}
@@ -0,0 +1,235 @@
+/**
+ * Token budgeting and chunking for Wurdump AI requests
+ *
+ * Counts tokens with the o200k tokenizer (the base of the gpt-oss harmony
+ * tokenizer) and splits content that does not fit the model's context window
+ * into chunks along paragraph and fenced code block boundaries.
+ */
+
+use tiktoken_rs::o200k_base_singleton;
+
+/**
+ * Count the tokens in a piece of text
+ */
+pub fn estimate_tokens(text: &str) -> usize {
+    o200k_base_singleton().encode_ordinary(text).len()
+}
+
+/**
+ * Split content into chunks of at most `max_tokens` tokens
+ *
+ * Paragraphs and fenced code blocks are kept whole whenever they fit.
+ * Blocks larger than the budget fall back to line boundaries, and single
+ * lines larger than the budget (e.g. minified JSON) are cut by characters.
+ */
+pub fn split_into_chunks(content: &str, max_tokens: usize) -> Vec<String> {
+    let max_tokens = max_tokens.max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+
+    for block in split_blocks(content) {
+        for piece in split_oversized_block(&block, max_tokens) {
+            let piece_tokens = estimate_tokens(&piece);
+            if !current.is_empty() && current_tokens + piece_tokens + 1 > max_tokens {
+                chunks.push(std::mem::take(&mut current));
+                current_tokens = 0;
+            }
+            if !current.is_empty() {
+                current.push_str("\n\n");
+                current_tokens += 1;
+            }
+            current.push_str(&piece);
+            current_tokens += piece_tokens;
+        }
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/**
+ * Group texts so that each group's combined size stays within `max_tokens`
+ *
+ * Every group holds at least two texts (when available) so that repeated
+ * grouping always makes progress, even if that slightly exceeds the budget.
+ */
+pub fn group_within_budget(texts: Vec<String>, max_tokens: usize) -> Vec<Vec<String>> {
+    let mut groups: Vec<Vec<String>> = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut current_tokens = 0;
+
+    for text in texts {
+        let tokens = estimate_tokens(&text);
+        if current.len() >= 2 && current_tokens + tokens > max_tokens {
+            groups.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        current_tokens += tokens;
+        current.push(text);
+    }
+
+    if !current.is_empty() {
+        // A trailing single text would not shrink; merge it into the previous group
+        match groups.last_mut() {
+            Some(last) if current.len() == 1 => last.append(&mut current),
+            _ => groups.push(current),
+        }
+    }
+
+    groups
+}
+
+/**
+ * Split content into paragraphs and fenced code blocks
+ */
+fn split_blocks(content: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut in_fence = false;
+
+    for line in content.lines() {
+        let is_fence = line.trim_start().starts_with("```");
+
+        if in_fence {
+            current.push(line);
+            if is_fence {
+                in_fence = false;
+                blocks.push(current.join("\n"));
+                current.clear();
+            }
+            continue;
+        }
+
+        if is_fence {
+            if !current.is_empty() {
+                blocks.push(current.join("\n"));
+                current.clear();
+            }
+            in_fence = true;
+            current.push(line);
+            continue;
+        }
+
+        if line.trim().is_empty() {
+            if !current.is_empty() {
+                blocks.push(current.join("\n"));
+                current.clear();
+            }
+            continue;
+        }
+
+        current.push(line);
+    }
+
+    if !current.is_empty() {
+        blocks.push(current.join("\n"));
+    }
+
+    blocks
+}
+
+/**
+ * Break a block that exceeds the budget into line-aligned pieces
+ */
+fn split_oversized_block(block: &str, max_tokens: usize) -> Vec<String> {
+    if estimate_tokens(block) <= max_tokens {
+        return vec![block.to_string()];
+    }
+
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+
+    for line in block.lines() {
+        let line_tokens = estimate_tokens(line);
+
+        if line_tokens > max_tokens {
+            if !current.is_empty() {
+                pieces.push(std::mem::take(&mut current));
+                current_tokens = 0;
+            }
+            pieces.extend(split_long_line(line, line_tokens, max_tokens));
+            continue;
+        }
+
+        if !current.is_empty() && current_tokens + line_tokens + 1 > max_tokens {
+            pieces.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        if !current.is_empty() {
+            current.push('\n');
+            current_tokens += 1;
+        }
+        current.push_str(line);
+        current_tokens += line_tokens;
+    }
+
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+
+    pieces
+}
+
+/**
+ * Cut a single over-long line into character windows sized from its token density
+ */
+fn split_long_line(line: &str, line_tokens: usize, max_tokens: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let chars_per_token = chars.len() as f64 / line_tokens.max(1) as f64;
+    // Leave 10% headroom since token density varies along the line
+    let window = ((max_tokens as f64 * chars_per_token * 0.9) as usize).max(1);
+
+    chars.chunks(window).map(|chunk| chunk.iter().collect()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_content_is_one_chunk() {
+        assert_eq!(split_into_chunks("one\n\ntwo", 100), vec!["one\n\ntwo"]);
+        assert!(split_into_chunks("", 100).is_empty());
+    }
+
+    #[test]
+    fn chunks_stay_within_budget() {
+        let content = (0..50).map(|i| format!("Paragraph {} has a few words in it.", i)).collect::<Vec<_>>().join("\n\n");
+        let chunks = split_into_chunks(&content, 40);
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(estimate_tokens(chunk) <= 40, "{} tokens: {:?}", estimate_tokens(chunk), chunk);
+        }
+        assert_eq!(chunks.join("\n\n"), content);
+    }
+
+    #[test]
+    fn code_blocks_are_kept_whole() {
+        let content = "Intro text.\n\n```rust\nfn main() {\n\n    println!(\"hi\");\n}\n```\n\nOutro text.";
+        let chunks = split_into_chunks(content, 20);
+        assert!(chunks.iter().any(|chunk| chunk.contains("fn main() {\n\n    println!(\"hi\");\n}")));
+    }
+
+    #[test]
+    fn long_lines_are_cut() {
+        let line = "x".repeat(5000);
+        let chunks = split_into_chunks(&line, 50);
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), line);
+        assert!(chunks.iter().all(|chunk| estimate_tokens(chunk) <= 50));
+    }
+
+    #[test]
+    fn groups_always_shrink() {
+        let texts: Vec<String> = (0..5).map(|i| format!("text {} ", i).repeat(20)).collect();
+        let groups = group_within_budget(texts.clone(), 1);
+        assert!(groups.len() < texts.len());
+        assert!(groups.iter().all(|group| group.len() >= 2));
+        assert_eq!(groups.concat(), texts);
+    }
+}
@@ -0,0 +1,521 @@
+/**
+ * Command line interface for Wurdump
+ *
+ * Runs history queries and clipboard operations against the app's database
+ * without starting the GUI, so history can be used from shell scripts and
+ * editor integrations. Output is a table for people or JSON for scripts.
+ * Items can be referred to by a unique prefix of their id, as shown in
+ * tables.
+ */
+
+use std::io::Write;
+use std::path::PathBuf;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use tokio::sync::broadcast;
+use crate::ai::{self, AIRequest};
+use crate::database::ClipboardDatabase;
+use crate::output_validation::{self, OutputFormat};
+use crate::providers;
+use crate::redaction::Redactor;
+use crate::response_segments;
+use crate::{AITransformation, AITransformationRecord, ClipboardFilter, ClipboardItem};
+
+/// Must match the identifier in tauri.conf.json, which names the app data directory
+const APP_IDENTIFIER: &str = "com.jerrodkim.wurdump";
+
+/// Characters of an id shown in tables
+const SHORT_ID_CHARS: usize = 8;
+
+/// Characters of the preview shown in tables
+const PREVIEW_CHARS: usize = 60;
+
+#[derive(Debug, Parser)]
+#[command(name = "wurdump", version, about = "Query and manage Wurdump clipboard history without the GUI")]
+struct Cli {
+    /// Database file (defaults to the one the app uses)
+    #[arg(long, global = true, env = "WURDUMP_DB")]
+    db: Option<PathBuf>,
+    /// Print JSON instead of a table
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List recent items, newest first
+    List {
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: u32,
+        /// Only items of this content type (text, code, json, url, ...)
+        #[arg(long = "type")]
+        content_type: Option<String>,
+        /// Only favorites
+        #[arg(long)]
+        favorites: bool,
+    },
+    /// Find items whose content, title or tags contain the text
+    Search {
+        query: String,
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: u32,
+        #[arg(long = "type")]
+        content_type: Option<String>,
+        /// Only code in this language
+        #[arg(long)]
+        language: Option<String>,
+    },
+    /// Print an item's content (with --json, the whole item)
+    Show { id: String },
+    /// Copy an item back to the clipboard
+    Copy { id: String },
+    /// Delete items, or the whole history with --all --yes
+    Delete {
+        #[arg(required_unless_present = "all")]
+        ids: Vec<String>,
+        #[arg(long, conflicts_with = "ids")]
+        all: bool,
+        /// Confirm deleting the whole history
+        #[arg(long, requires = "all")]
+        yes: bool,
+    },
+    /// Write history to stdout or a file
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[arg(short = 'n', long, default_value_t = 1000)]
+        limit: u32,
+    },
+    /// Apply an AI prompt to an item and print the result
+    Transform {
+        id: String,
+        #[arg(short, long)]
+        prompt: String,
+        /// Also copy the result to the clipboard
+        #[arg(long)]
+        copy: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Json,
+    Markdown,
+}
+
+/**
+ * Whether the process was started to run a CLI command rather than the GUI
+ *
+ * Arguments that parse as a command, ask for help or the version, or name a
+ * subcommand after the global options count, so usage errors are reported
+ * by the CLI. Anything else, such as arguments the OS passes to the app on
+ * launch, starts the GUI.
+ */
+pub fn is_cli_invocation() -> bool {
+    is_cli_args(std::env::args_os())
+}
+
+fn is_cli_args<I, T>(args: I) -> bool
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    let args: Vec<std::ffi::OsString> = args.into_iter().map(Into::into).collect();
+
+    match Cli::try_parse_from(&args) {
+        Ok(_) => true,
+        Err(e) if matches!(e.kind(), clap::error::ErrorKind::DisplayHelp | clap::error::ErrorKind::DisplayVersion) => true,
+        Err(_) => first_command(&args).is_some_and(|first| {
+            first == "help" || Cli::command().get_subcommands().any(|command| command.get_name() == first)
+        }),
+    }
+}
+
+/**
+ * First argument that is not a global option or its value
+ */
+fn first_command(args: &[std::ffi::OsString]) -> Option<&str> {
+    let mut args = args.iter().skip(1).map(|arg| arg.to_str());
+    while let Some(arg) = args.next() {
+        match arg? {
+            "--db" => {
+                args.next();
+            }
+            arg if arg.starts_with('-') => {}
+            arg => return Some(arg),
+        }
+    }
+    None
+}
+
+/**
+ * Attach to the console of the shell that started the app
+ *
+ * Release builds on Windows use the GUI subsystem and start without a
+ * console, so output would otherwise go nowhere.
+ */
+#[cfg(windows)]
+fn attach_parent_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    // Fails when there is no parent console or one is already attached; both are fine
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+/**
+ * Run the CLI with the process arguments and return the exit code
+ */
+pub fn run() -> i32 {
+    #[cfg(windows)]
+    attach_parent_console();
+
+    // Logs go to stderr and stay quiet unless RUST_LOG asks for more
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let cli = Cli::parse();
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("error: failed to start runtime: {}", e);
+            return 1;
+        }
+    };
+
+    match runtime.block_on(execute(cli)) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+async fn execute(cli: Cli) -> Result<(), String> {
+    let db_path = cli.db.clone().unwrap_or_else(default_database_path);
+    // Opening a missing file would silently create an empty history
+    if !db_path.is_file() {
+        return Err(format!(
+            "No history database at {} (run Wurdump once to create it, or pass --db)",
+            db_path.display()
+        ));
+    }
+    let db = ClipboardDatabase::new(Some(db_path)).await.map_err(|e| e.to_string())?;
+
+    match cli.command {
+        Command::List { limit, content_type, favorites } => {
+            let filter = ClipboardFilter { content_type, favorites_only: favorites, ..Default::default() };
+            let items = db.search_clipboard_items(&filter, limit).await.map_err(|e| e.to_string())?;
+            print_items(&items, cli.json)
+        }
+        Command::Search { query, limit, content_type, language } => {
+            let filter = ClipboardFilter {
+                query: Some(query),
+                content_type,
+                code_language: language,
+                ..Default::default()
+            };
+            let items = db.search_clipboard_items(&filter, limit).await.map_err(|e| e.to_string())?;
+            print_items(&items, cli.json)
+        }
+        Command::Show { id } => {
+            let item = resolve_item(&db, &id).await?;
+            if cli.json {
+                print_json(&item)
+            } else {
+                print!("{}", item.content);
+                if !item.content.ends_with('\n') {
+                    println!();
+                }
+                Ok(())
+            }
+        }
+        Command::Copy { id } => {
+            let item = resolve_item(&db, &id).await?;
+            copy_to_clipboard(&item.content)?;
+            if !cli.json {
+                eprintln!("Copied {} ({} chars)", short_id(&item.id), item.content.chars().count());
+            }
+            Ok(())
+        }
+        Command::Delete { ids, all, yes } => {
+            if all {
+                if !yes {
+                    return Err("Deleting the whole history needs --yes".to_string());
+                }
+                db.clear_clipboard_history().await.map_err(|e| e.to_string())?;
+                eprintln!("Deleted all items");
+                return Ok(());
+            }
+
+            let mut deleted = Vec::new();
+            for id in &ids {
+                let item = resolve_item(&db, id).await?;
+                db.delete_clipboard_item(&item.id).await.map_err(|e| e.to_string())?;
+                deleted.push(item.id);
+            }
+            if cli.json {
+                print_json(&deleted)
+            } else {
+                eprintln!("Deleted {} item(s)", deleted.len());
+                Ok(())
+            }
+        }
+        Command::Export { format, output, limit } => {
+            let items = db.get_clipboard_history(limit, 0).await.map_err(|e| e.to_string())?;
+            let exported = match format {
+                ExportFormat::Json => serde_json::to_string_pretty(&items).map_err(|e| e.to_string())?,
+                ExportFormat::Markdown => export_markdown(&items),
+            };
+
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, exported)
+                        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                    eprintln!("Exported {} item(s) to {}", items.len(), path.display());
+                }
+                None => println!("{}", exported),
+            }
+            Ok(())
+        }
+        Command::Transform { id, prompt, copy } => {
+            let item = resolve_item(&db, &id).await?;
+            let transformations = transform(&db, &item, &prompt).await?;
+
+            if copy {
+                if let Some(transformation) = transformations.first() {
+                    copy_to_clipboard(&transformation.result)?;
+                }
+            }
+
+            if cli.json {
+                print_json(&transformations)
+            } else {
+                let result = transformations.first().map(|transformation| transformation.result.as_str()).unwrap_or_default();
+                println!("{}", result);
+                Ok(())
+            }
+        }
+    }
+}
+
+/**
+ * Database location used by the app: clipboard.db in its data directory
+ */
+fn default_database_path() -> PathBuf {
+    match dirs::data_dir() {
+        Some(data_dir) => data_dir.join(APP_IDENTIFIER).join("clipboard.db"),
+        None => std::env::temp_dir().join("wurdump").join("clipboard.db"),
+    }
+}
+
+/**
+ * Find an item by its id or a unique prefix of it
+ */
+async fn resolve_item(db: &ClipboardDatabase, id: &str) -> Result<ClipboardItem, String> {
+    if let Some(item) = db.get_clipboard_item(id).await.map_err(|e| e.to_string())? {
+        return Ok(item);
+    }
+
+    // Two matches are enough to tell that a prefix is ambiguous
+    let mut matches = db.find_items_by_id_prefix(id, 2).await.map_err(|e| e.to_string())?.into_iter();
+    match (matches.next(), matches.next()) {
+        (Some(item), None) => Ok(item),
+        (Some(_), Some(_)) => Err(format!("Id prefix {} matches several items", id)),
+        (None, _) => Err(format!("Item not found: {}", id)),
+    }
+}
+
+/**
+ * Run a prompt on an item through the provider chain and record the results
+ */
+async fn transform(db: &ClipboardDatabase, item: &ClipboardItem, prompt: &str) -> Result<Vec<AITransformation>, String> {
+    let settings = db.load_settings().await.map_err(|e| e.to_string())?.unwrap_or_default();
+    if !settings.ai_enabled {
+        return Err("AI is disabled in the settings".to_string());
+    }
+
+    let output_format = output_validation::expected_format(
+        prompt,
+        &item.content_type,
+        item.code_language.as_deref(),
+        &item.content,
+    );
+
+    let mut redactor = Redactor::new();
+    let (content, instruction) = if settings.ai_redaction_enabled {
+        (redactor.redact(&item.content), redactor.redact(prompt))
+    } else {
+        (item.content.clone(), prompt.to_string())
+    };
+
+    let providers = providers::provider_chain(&settings, None);
+    let request = AIRequest {
+        content,
+        instruction,
+        has_custom_instruction: true,
+        instruction_from_template: false,
+        context_items: Vec::new(),
+        system_prompt: ai::SYSTEM_PROMPT.to_string(),
+        model: providers[0].model.clone(),
+        temperature: settings.ai_temperature,
+        max_tokens: settings.ai_max_tokens,
+        context_window: settings.ai_context_window,
+        output_format: output_format.clone(),
+        history: Vec::new(),
+        providers,
+        timeout: providers::request_timeout(&settings),
+    };
+
+    let started_at = std::time::Instant::now();
+    let (_cancel_tx, mut cancel_rx) = broadcast::channel(1);
+    let transformation = ai::run_ai_request(&request, &mut cancel_rx).await?;
+    let latency_ms = started_at.elapsed().as_millis() as u64;
+
+    let mut transformations = if output_format == OutputFormat::Text {
+        response_segments::split_transformation(transformation)
+    } else {
+        vec![transformation]
+    };
+
+    for transformation in &mut transformations {
+        redactor.restore_transformation(transformation);
+
+        let record = AITransformationRecord {
+            id: transformation.id.clone(),
+            item_id: Some(item.id.clone()),
+            prompt: prompt.to_string(),
+            model: transformation.model.clone().unwrap_or_else(|| request.model.clone()),
+            transformation: transformation.clone(),
+            latency_ms,
+            template_id: None,
+            context_sources: Vec::new(),
+            created_at: chrono::Utc::now(),
+        };
+        if let Err(e) = db.store_ai_transformation(&record).await {
+            log::warn!("Failed to record AI transformation: {}", e);
+        }
+    }
+
+    Ok(transformations)
+}
+
+/**
+ * Put text on the system clipboard
+ *
+ * On Linux the text stays available only while another program, such as a
+ * clipboard manager or the running app, has picked it up.
+ */
+fn copy_to_clipboard(text: &str) -> Result<(), String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text.to_string()))
+        .map_err(|e| format!("Failed to set clipboard content: {}", e))
+}
+
+fn print_items(items: &[ClipboardItem], json: bool) -> Result<(), String> {
+    if json {
+        return print_json(&items);
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    let mut write = |line: String| writeln!(stdout, "{}", line).map_err(|e| e.to_string());
+
+    write(format!("{:<8}  {:<8}  {:<10}  {:<16}  {}", "ID", "TYPE", "LANGUAGE", "CAPTURED", "PREVIEW"))?;
+    for item in items {
+        write(format!(
+            "{:<8}  {:<8}  {:<10}  {:<16}  {}{}",
+            short_id(&item.id),
+            item.content_type,
+            item.code_language.as_deref().unwrap_or("-"),
+            item.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+            if item.is_favorite { "★ " } else { "" },
+            single_line(item.title.as_deref().unwrap_or(&item.content)),
+        ))?;
+    }
+    Ok(())
+}
+
+fn print_json<T: serde::Serialize + ?Sized>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+/**
+ * One section per item, newest first, with code in fenced blocks
+ */
+fn export_markdown(items: &[ClipboardItem]) -> String {
+    let mut markdown = String::from("# Wurdump clipboard history\n");
+    for item in items {
+        let heading = item.title.clone().unwrap_or_else(|| single_line(&item.content));
+        markdown.push_str(&format!(
+            "\n## {}\n\n*{} · {}*\n\n",
+            heading,
+            item.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+            item.content_type
+        ));
+
+        match (item.content_type.as_str(), &item.code_language) {
+            ("text" | "markdown" | "url" | "email", _) => markdown.push_str(item.content.trim_end()),
+            (_, language) => markdown.push_str(&format!(
+                "```{}\n{}\n```",
+                language.as_deref().unwrap_or_default(),
+                item.content.trim_end()
+            )),
+        }
+        markdown.push('\n');
+    }
+    markdown
+}
+
+fn short_id(id: &str) -> String {
+    id.chars().take(SHORT_ID_CHARS).collect()
+}
+
+fn single_line(text: &str) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() > PREVIEW_CHARS {
+        format!("{}…", collapsed.chars().take(PREVIEW_CHARS).collect::<String>())
+    } else {
+        collapsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subcommands_run_the_cli() {
+        assert!(is_cli_args(["wurdump", "list"]));
+        assert!(is_cli_args(["wurdump", "help"]));
+        assert!(is_cli_args(["wurdump", "--help"]));
+        assert!(is_cli_args(["wurdump", "--version"]));
+    }
+
+    #[test]
+    fn global_options_may_come_first() {
+        assert!(is_cli_args(["wurdump", "--json", "list"]));
+        assert!(is_cli_args(["wurdump", "--db", "history.db", "show", "abc"]));
+        assert!(is_cli_args(["wurdump", "--db=history.db", "--json", "search", "todo"]));
+    }
+
+    #[test]
+    fn usage_errors_are_reported_by_the_cli() {
+        assert!(is_cli_args(["wurdump", "list", "--limit", "many"]));
+        assert!(is_cli_args(["wurdump", "--json", "show"]));
+    }
+
+    #[test]
+    fn other_arguments_start_the_gui() {
+        assert!(!is_cli_args(["wurdump"]));
+        assert!(!is_cli_args(["wurdump", "--json"]));
+        assert!(!is_cli_args(["wurdump", "-psn_0_12345"]));
+        assert!(!is_cli_args(["wurdump", "wurdump://open"]));
+        assert!(!is_cli_args(["wurdump", "--db", "list"]));
+    }
+}
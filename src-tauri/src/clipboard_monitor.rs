@@ -4,7 +4,7 @@
  * This module provides automatic clipboard monitoring functionality that:
  * - Monitors system clipboard changes every 1 second
  * - Automatically saves new clipboard content to SQLite database
- * - Maintains only the latest 20 clipboard items (auto-cleanup)
+ * - Maintains only the latest max_history_items clipboard items (auto-cleanup)
  * - Detects content types (text, code, JSON, URL, email, etc.)
  * - Prevents duplicate entries within a 1-hour window
  * - Provides thread-safe access to clipboard history
@@ -17,7 +17,7 @@ use tauri::Manager;
 use anyhow::Result;
 use tauri::AppHandle;
 use tauri_plugin_clipboard_manager::ClipboardExt;
-use crate::{content_detection::ContentDetector, database::ClipboardDatabase};
+use crate::{content_detection::ContentDetector, database::ClipboardDatabase, enrichment, AppState};
 
 /**
  * Clipboard monitoring service
@@ -99,7 +99,7 @@ impl ClipboardMonitor {
      * 1. Checks clipboard content at the specified interval (default: 1000ms)
      * 2. Detects when clipboard content changes
      * 3. Automatically stores new content to the database
-     * 4. Maintains a maximum of 20 items (older items are auto-deleted)
+     * 4. Maintains a maximum of max_history_items items (older items are auto-deleted)
      * 5. Prevents duplicate storage within a 1-hour window
      * 
      * Parameters:
@@ -180,11 +180,17 @@ impl ClipboardMonitor {
                                         // The database will automatically:
                                         // 1. Detect content type (text, code, JSON, URL, etc.)
                                         // 2. Generate a preview
-                                        // 3. Maintain only the latest 20 items
-                                        if let Err(e) = db.store_clipboard_item(&current_content).await {
-                                            log::error!("❌ Failed to store clipboard item: {}", e);
-                                        } else {
-                                            log::debug!("✅ Stored new clipboard item in database");
+                                        // 3. Maintain only the latest max_history_items items
+                                        match db.store_clipboard_item(&current_content).await {
+                                            Ok(item) => {
+                                                log::debug!("✅ Stored new clipboard item in database");
+                                                app_handle.state::<AppState>().publish_capture(&item);
+                                                // Title and tag the item in the background if enabled
+                                                enrichment::enqueue_enrichment(app_handle.clone(), db.clone(), item);
+                                            }
+                                            Err(e) => {
+                                                log::error!("❌ Failed to store clipboard item: {}", e);
+                                            }
                                         }
                                     } else {
                                         log::debug!("⏭️  Clipboard content already exists in recent history, skipping");
@@ -309,7 +315,7 @@ impl ClipboardMonitor {
         );
         
         // TODO: Store in database
-        // TODO: Trigger AI processing if enabled
+        // AI enrichment of new items is triggered from the monitoring loop
         
         Ok(())
     }
@@ -3,13 +3,44 @@
  * These functions are called from the frontend TypeScript code
  */
 
-use tauri::{command, State, Manager};
+use std::collections::HashMap;
+use tauri::{command, Emitter, State, Manager};
 use tauri_plugin_clipboard_manager::ClipboardExt;
-use crate::{AppState, ClipboardItem, AITransformation, AppSettings};
+use crate::{AppState, ClipboardItem, AITransformation, AITransformationRecord, AIProcessOptions, AppSettings, SettingsUpdate, ClipboardFilter, ContextSource, ConversationMessage, PromptTemplate};
+use crate::ai::{self, AIRequest, ChatMessage, ContextItem};
+use crate::chunking::estimate_tokens;
+use crate::ai_queue::{PositionCallback, QueuePositionEvent};
+use crate::batch::{self, BatchAIRequest, BatchAIResult, BatchItemResult, BatchProgressEvent};
+use crate::ollama::{self, AIModelInfo, AIStatus};
+use crate::metrics::{self, ModelDayMetrics};
+use crate::providers::{self, ProviderKind};
+use crate::transforms::{BuiltinTransform, TRANSFORM_REGISTRY};
+use crate::database::ClipboardDatabase;
+use crate::digest;
+use crate::embedded;
+use crate::feedback::{self, FeedbackAction, PresetKind, PresetScore, TransformationFeedback};
+use crate::embeddings::{self, SemanticSearchResult};
+use crate::content_detection::ContentDetector;
+use crate::context_selection::{self, SelectedContext};
+use crate::output_validation::{self, OutputFormat};
+use crate::response_segments;
+use crate::redaction::{self, Redactor};
+use crate::prompt_templates::{self, PromptTemplateInput, TemplateVariables};
 use anyhow::Result;
 use tokio::sync::broadcast;
 use uuid::Uuid;
 
+/**
+ * Get the database from the clipboard monitor, if it has been initialized
+ */
+pub fn get_database(state: &State<'_, AppState>) -> Option<ClipboardDatabase> {
+    let monitor = {
+        let monitor_guard = state.clipboard_monitor.lock().unwrap();
+        monitor_guard.clone()
+    };
+    monitor.and_then(|monitor| monitor.get_database())
+}
+
 /**
  * Get the current clipboard content
  */
@@ -148,18 +179,69 @@ pub async fn clear_clipboard_history(state: State<'_, AppState>) -> Result<(), S
  * Get application settings
  */
 #[command]
-pub async fn get_settings() -> Result<AppSettings, String> {
-    // TODO: Load settings from file or database
-    Ok(AppSettings::default())
+pub async fn get_settings(state: State<'_, AppState>) -> Result<AppSettings, String> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    // API keys stay in the backend; updates send the mask back to keep them
+    providers::mask_api_keys(&mut settings.ai_providers);
+    Ok(settings)
 }
 
 /**
  * Update application settings
  */
 #[command]
-pub async fn update_settings(settings: AppSettings) -> Result<(), String> {
-    // TODO: Save settings to file or database
-    log::info!("Updating settings: {:?}", settings);
+pub async fn update_settings(
+    #[cfg_attr(not(unix), allow(unused_variables))] app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    settings: SettingsUpdate
+) -> Result<(), String> {
+    let SettingsUpdate(mut settings) = settings;
+    let previous = state.settings.lock().unwrap().clone();
+    providers::restore_masked_api_keys(&mut settings.ai_providers, &previous.ai_providers);
+    log::info!("Updating settings: {}", changed_setting_names(&previous, &settings).join(", "));
+    if settings.digest_enabled && digest::parse_digest_time(&settings.digest_time).is_none() {
+        return Err("Digest time must be in HH:MM format".to_string());
+    }
+    state.ai_queue.set_max_concurrent(settings.ai_max_concurrent_requests as usize);
+    if let Some(db) = get_database(&state) {
+        db.set_max_history_items(settings.max_history_items);
+    }
+    *state.settings.lock().unwrap() = settings.clone();
+    #[cfg(unix)]
+    if settings.ipc_enabled != previous.ipc_enabled {
+        crate::ipc_server::set_enabled(&app, settings.ipc_enabled);
+    }
+    persist_settings(&state, &settings).await
+}
+
+/**
+ * Names of the settings that differ, so updates can be logged without their values
+ */
+fn changed_setting_names(previous: &AppSettings, updated: &AppSettings) -> Vec<String> {
+    let (Ok(serde_json::Value::Object(previous)), Ok(serde_json::Value::Object(updated))) =
+        (serde_json::to_value(previous), serde_json::to_value(updated))
+    else {
+        return Vec::new();
+    };
+    updated
+        .iter()
+        .filter(|(name, value)| previous.get(name.as_str()) != Some(value))
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/**
+ * Save settings to the database so they survive restarts
+ */
+async fn persist_settings(state: &State<'_, AppState>, settings: &AppSettings) -> Result<(), String> {
+    if let Some(db) = get_database(state) {
+        db.save_settings(settings).await.map_err(|e| {
+            log::error!("Failed to save settings: {}", e);
+            "Failed to save settings".to_string()
+        })?;
+    } else {
+        log::warn!("Database not initialized, settings will not persist");
+    }
     Ok(())
 }
 
@@ -186,38 +268,31 @@ pub async fn unregister_global_shortcut() -> Result<(), String> {
     Ok(())
 }
 
-// Static HTTP client for connection pooling with improved configuration
-static HTTP_CLIENT: once_cell::sync::Lazy<reqwest::Client> = once_cell::sync::Lazy::new(|| {
-    reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(120)) // Increased timeout for large models
-        .connect_timeout(std::time::Duration::from_secs(10)) // Quick connection detection
-        .pool_idle_timeout(std::time::Duration::from_secs(30)) // Keep connections alive
-        .pool_max_idle_per_host(4) // Maintain connection pool
-        .tcp_keepalive(std::time::Duration::from_secs(60)) // Keep TCP connections alive
-        .http1_title_case_headers() // Better compatibility with Ollama
-        .build()
-        .expect("Failed to create HTTP client")
-});
-
 /**
  * Process clipboard content with AI using custom prompt and optional context
  * 
- * Enhanced with improved error handling, retry logic, and context support
+ * Enhanced with improved error handling, retry logic, and context support.
+ * Content larger than the model's context window is processed in chunks.
  */
 #[command]
 pub async fn process_with_ai(
     content: String,
     custom_prompt: Option<String>,
     context_items: Option<Vec<String>>, // New: Support for additional context
+    options: Option<AIProcessOptions>,
+    app: tauri::AppHandle,
     state: State<'_, AppState>
 ) -> Result<Vec<AITransformation>, String> {
     if content.trim().is_empty() {
         return Err("Content is empty".to_string());
     }
 
-    log::info!("🤖 Processing content with AI: {} chars, {} context items", 
+    let options = options.unwrap_or_default();
+
+    log::info!("🤖 Processing content with AI: {} chars, {} context items, {} context item ids", 
                content.len(), 
-               context_items.as_ref().map(|items| items.len()).unwrap_or(0));
+               context_items.as_ref().map(|items| items.len()).unwrap_or(0),
+               options.context_item_ids.len());
     
     // Debug logging for context items
     if let Some(ref context) = context_items {
@@ -226,15 +301,259 @@ pub async fn process_with_ai(
             log::info!("  Context {}: {} chars - Preview: {}", 
                       i + 1, 
                       item.len(), 
-                      &item.chars().take(50).collect::<String>());
+                      redaction::redact_for_log(&item.chars().take(50).collect::<String>()));
         }
     } else {
         log::info!("❌ No context items provided");
     }
 
-    // Create a unique task ID for this request
+    let settings = state.settings.lock().unwrap().clone();
+    let database = get_database(&state);
+
+    // Resolve the prompt template, if one was chosen
+    let template = match &options.template_id {
+        Some(id) => {
+            let db = database.as_ref().ok_or_else(|| "Database not available".to_string())?;
+            let template = db.get_prompt_template(id).await
+                .map_err(|e| {
+                    log::error!("Failed to load prompt template {}: {}", id, e);
+                    "Failed to load prompt template".to_string()
+                })?
+                .ok_or_else(|| format!("Prompt template not found: {}", id))?;
+            Some(template)
+        }
+        None => None,
+    };
+
+    let content_info = ContentDetector::new().detect_content(&content);
+
+    // Templates render their placeholders; the prompt recorded in history stays unrendered.
+    // {{content}} is kept as a placeholder so chunked requests can insert each part.
+    let (instruction, history_prompt) = match &template {
+        Some(template) => {
+            let variables = TemplateVariables {
+                content: ai::CONTENT_PLACEHOLDER.to_string(),
+                language: content_info.code_language.clone(),
+                content_type: content_info.content_type.clone(),
+                date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+            };
+
+            let mut rendered = prompt_templates::render_template(&template.body, &variables);
+            let mut history_prompt = template.body.clone();
+            if let Some(extra) = &custom_prompt {
+                rendered.push_str(&format!("\n\nAdditional instructions: {}", extra));
+                history_prompt.push_str(&format!("\n\nAdditional instructions: {}", extra));
+            }
+            (rendered, history_prompt)
+        }
+        None => {
+            let text = custom_prompt.clone().unwrap_or_else(|| ai::DEFAULT_REQUEST.to_string());
+            (text.clone(), text)
+        }
+    };
+
+    // Label context items and keep them within the context budget
+    let context = resolve_context_items(
+        database.as_ref(),
+        context_items.unwrap_or_default(),
+        &options.context_item_ids,
+    ).await?;
+    let context_budget = settings.ai_context_budget_tokens.min(settings.ai_context_window / 2) as usize;
+
+    // Related recent items fill whatever budget the chosen ones leave
+    let mut auto_reasons: HashMap<String, String> = HashMap::new();
+    let mut context = context;
+    if options.auto_context.unwrap_or(settings.ai_auto_context) {
+        if let Some(db) = &database {
+            let used: usize = context.iter().map(|item| estimate_tokens(&item.content)).sum();
+            let mut excluded = options.context_item_ids.clone();
+            excluded.extend(options.item_id.clone());
+
+            for selected in select_auto_context(db, &content, content_info.code_language.as_deref(), &excluded, context_budget.saturating_sub(used)).await {
+                log::info!("🧲 Auto-selected context item {} ({})", selected.item.id, selected.reason);
+                auto_reasons.insert(selected.item.id.clone(), selected.reason);
+                context.push(ContextItem {
+                    item_id: Some(selected.item.id),
+                    content_type: selected.item.content_type,
+                    code_language: selected.item.code_language,
+                    content: selected.item.content,
+                    truncated: false,
+                });
+            }
+        }
+    }
+
+    let context = ai::fit_context_to_budget(context, context_budget);
+    let context_sources: Vec<ContextSource> = context
+        .iter()
+        .filter_map(|item| {
+            item.item_id.as_ref().map(|item_id| ContextSource {
+                item_id: item_id.clone(),
+                content_type: item.content_type.clone(),
+                code_language: item.code_language.clone(),
+                tokens: estimate_tokens(&item.content),
+                truncated: item.truncated,
+                reason: auto_reasons.get(item_id).cloned(),
+            })
+        })
+        .collect();
+
+    // Structured answers are validated against the format the user asked for
+    let output_format = output_validation::expected_format(
+        &instruction,
+        &content_info.content_type,
+        content_info.code_language.as_deref(),
+        &content,
+    );
+
+    // Replace secrets and personal data with placeholders before anything reaches the model
+    let mut redactor = Redactor::new();
+    let (request_content, instruction, context) = if settings.ai_redaction_enabled {
+        let request_content = redactor.redact(&content);
+        let instruction = redactor.redact(&instruction);
+        let context = context
+            .into_iter()
+            .map(|mut item| {
+                item.content = redactor.redact(&item.content);
+                item
+            })
+            .collect();
+        if redactor.redaction_count() > 0 {
+            log::info!("🕶️ Redacted {} sensitive value(s) before sending to AI", redactor.redaction_count());
+        }
+        (request_content, instruction, context)
+    } else {
+        (content.clone(), instruction, context)
+    };
+
+    // Explicit options win over template defaults, which win over settings
+    let providers = providers::provider_chain(
+        &settings,
+        template.as_ref().and_then(|template| template.model.as_deref()),
+    );
+    let request = AIRequest {
+        content: request_content,
+        instruction,
+        has_custom_instruction: custom_prompt.is_some() || template.is_some(),
+        instruction_from_template: template.is_some(),
+        context_items: context,
+        system_prompt: ai::SYSTEM_PROMPT.to_string(),
+        model: providers[0].model.clone(),
+        providers,
+        temperature: options.temperature
+            .or(template.as_ref().and_then(|template| template.temperature))
+            .unwrap_or(settings.ai_temperature),
+        max_tokens: options.max_tokens
+            .or(template.as_ref().and_then(|template| template.max_tokens))
+            .unwrap_or(settings.ai_max_tokens),
+        context_window: settings.ai_context_window,
+        output_format,
+        history: Vec::new(),
+        timeout: providers::request_timeout(&settings),
+    };
+
+    let mut executed = execute_ai_request(&request, &redactor, &options, &app, &state).await?;
+    for transformation in &mut executed.transformations {
+        transformation.context_sources = context_sources.clone();
+    }
+
+    if let Some(db) = &database {
+        // Record fresh results in history so they can be found and re-applied later
+        if executed.source == ResultSource::Fresh {
+            for transformation in &executed.transformations {
+                let record = AITransformationRecord {
+                    id: transformation.id.clone(),
+                    item_id: options.item_id.clone(),
+                    prompt: history_prompt.clone(),
+                    model: transformation.model.clone().unwrap_or_else(|| request.model.clone()),
+                    transformation: transformation.clone(),
+                    latency_ms: executed.latency_ms,
+                    template_id: options.template_id.clone(),
+                    context_sources: context_sources.clone(),
+                    created_at: chrono::Utc::now(),
+                };
+                if let Err(e) = db.store_ai_transformation(&record).await {
+                    log::warn!("Failed to record AI transformation: {}", e);
+                }
+            }
+        }
+
+        // Results for an item start or continue its conversation, so follow-ups can refine them
+        if executed.source != ResultSource::Shared {
+            if let (Some(item_id), Some(transformation)) = (&options.item_id, executed.transformations.first()) {
+                if let Err(e) = record_conversation_turn(db, item_id, &history_prompt, transformation).await {
+                    log::warn!("Failed to record conversation turn: {}", e);
+                }
+            }
+        }
+    }
+
+    Ok(executed.transformations)
+}
+
+/**
+ * Where the results of an AI request came from
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResultSource {
+    /// This call ran the request
+    Fresh,
+    /// Served from the result cache
+    Cached,
+    /// Shared from an identical request that was already running
+    Shared,
+}
+
+/**
+ * Results of a prepared AI request, with redacted values restored
+ */
+struct ExecutedRequest {
+    transformations: Vec<AITransformation>,
+    source: ResultSource,
+    latency_ms: u64,
+}
+
+/**
+ * Run a prepared request through the result cache and the AI queue
+ *
+ * The request is registered in active_ai_tasks so it can be cancelled while
+ * queued or running. Fresh results are cached in their redacted form.
+ */
+async fn execute_ai_request(
+    request: &AIRequest,
+    redactor: &Redactor,
+    options: &AIProcessOptions,
+    app: &tauri::AppHandle,
+    state: &State<'_, AppState>,
+) -> Result<ExecutedRequest, String> {
+    let settings = state.settings.lock().unwrap().clone();
+    let database = get_database(state);
+
+    // Serve identical requests from the cache unless the caller asked for a fresh result
+    let cache_key = request.cache_key();
+    let use_cache = settings.cache_ai_results && database.is_some();
+    if use_cache && !options.bypass_cache {
+        if let Some(db) = &database {
+            match db.get_cached_ai_result(&cache_key).await {
+                Ok(Some(mut cached)) => {
+                    for transformation in &mut cached {
+                        transformation.is_cached = true;
+                        redactor.restore_transformation(transformation);
+                    }
+                    log::info!("💾 Serving AI result from cache");
+                    return Ok(ExecutedRequest { transformations: cached, source: ResultSource::Cached, latency_ms: 0 });
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("AI cache lookup failed: {}", e),
+            }
+        }
+    }
+
+    // Tasks and queue jobs are always keyed by a fresh id; the caller's
+    // request id is only a label, so reusing one cannot clobber another request
     let task_id = Uuid::new_v4().to_string();
     let (cancel_tx, mut cancel_rx) = broadcast::channel(1);
+    let mut queue_cancel_rx = cancel_tx.subscribe();
 
     // Store the cancellation token
     {
@@ -242,242 +561,1144 @@ pub async fn process_with_ai(
         tasks.insert(task_id.clone(), cancel_tx);
     }
 
-    // Enhanced prompt building with context support
-    let system_prompt = "You are an AI assistant that helps transform clipboard content. Be helpful, accurate, and preserve important information. When provided with additional context, use it to give better, more relevant responses.";
-    
-    let _has_custom_prompt = custom_prompt.is_some();
-    let mut user_prompt = String::new();
-    
-    // Add context items if provided
-    if let Some(ref context) = context_items {
-        if !context.is_empty() {
-            user_prompt.push_str("Additional Context:\n");
-            for (i, item) in context.iter().enumerate() {
-                user_prompt.push_str(&format!("Context {}: {}\n\n", i + 1, item));
+    // Let the frontend show where the request is in the queue
+    let on_position: PositionCallback = {
+        let app = app.clone();
+        let job_id = options.request_id.clone().unwrap_or_else(|| task_id.clone());
+        let priority = options.priority;
+        std::sync::Arc::new(move |position| {
+            let event = QueuePositionEvent { job_id: job_id.clone(), position, priority };
+            if let Err(e) = app.emit("ai-queue-position", event) {
+                log::warn!("Failed to emit queue position: {}", e);
             }
-            user_prompt.push_str("---\n\n");
-        }
+        })
+    };
+
+    let started_at = std::time::Instant::now();
+    let queue = std::sync::Arc::clone(&state.ai_queue);
+    let job_cancel_rx = &mut cancel_rx;
+    let queued = queue.run(
+        task_id.clone(),
+        Some(cache_key.clone()),
+        options.priority,
+        on_position,
+        move || async move {
+            let transformation = ai::run_ai_request(request, job_cancel_rx).await?;
+            // Free-text answers with several code blocks also get one result per block
+            Ok(if request.output_format == OutputFormat::Text {
+                response_segments::split_transformation(transformation)
+            } else {
+                vec![transformation]
+            })
+        },
+    );
+
+    // Cancellation also applies while the request is still waiting in the queue
+    let (result, shared) = tokio::select! {
+        outcome = queued => outcome,
+        _ = queue_cancel_rx.recv() => (Err("Request cancelled by user".to_string()), false),
+    };
+
+    // Clean up the task from active tasks
+    {
+        let mut tasks = state.active_ai_tasks.lock().unwrap();
+        tasks.remove(&task_id);
     }
-    
-    // Add main content and request
-    user_prompt.push_str(&format!("Main Content:\n```\n{}\n```\n\n", content));
-    
-    if let Some(ref prompt) = custom_prompt {
-        user_prompt.push_str(&format!("Request: {}", prompt));
-    } else {
-        user_prompt.push_str("Please improve and format this content, taking into account any provided context.");
+
+    let transformations = result.inspect_err(|e| {
+        if e.contains("cancelled") {
+            log::info!("❌ AI request cancelled: {}", task_id);
+        }
+    })?;
+
+    let latency_ms = started_at.elapsed().as_millis() as u64;
+    log::info!("✅ AI processing completed in {}ms", latency_ms);
+
+    // The cache keeps the redacted answer; it is restored for each caller
+    let mut restored = transformations.clone();
+    for transformation in &mut restored {
+        redactor.restore_transformation(transformation);
     }
 
-    // Build request with dynamic token limit based on content size
-    let estimated_tokens = (content.len() + user_prompt.len()) / 4; // Rough estimation
-    let max_tokens = if estimated_tokens > 2000 { 2000 } else { 1000 };
-    
-    // Log the final prompt being sent to AI
-    log::info!("📝 Final prompt being sent to AI:");
-    log::info!("System: {}", system_prompt);
-    log::info!("User prompt (first 500 chars): {}", 
-              &user_prompt.chars().take(500).collect::<String>());
-    if user_prompt.len() > 500 {
-        log::info!("... (prompt continues for {} more chars)", user_prompt.len() - 500);
+    // The job that actually ran the request caches it
+    if shared {
+        return Ok(ExecutedRequest { transformations: restored, source: ResultSource::Shared, latency_ms });
     }
-    
-    let request_body = serde_json::json!({
-        "model": "gpt-oss:20b",
-        "messages": [
-            {"role": "system", "content": system_prompt},
-            {"role": "user", "content": user_prompt}
-        ],
-        "temperature": 0.7,
-        "max_tokens": max_tokens,
-        "stream": false // Ensure we get complete response
-    });
-
-    // Retry logic with exponential backoff
-    let max_retries = 3;
-    let mut last_error = String::new();
-    
-    for attempt in 1..=max_retries {
-        log::debug!("🔄 AI request attempt {}/{}", attempt, max_retries);
-        
-        // Make the request with cancellation support
-        let result = tokio::select! {
-            response_result = make_ai_request(&request_body) => {
-                response_result
+
+    if use_cache {
+        if let Some(db) = &database {
+            if let Err(e) = db.store_cached_ai_result(
+                &cache_key,
+                &request.model,
+                &transformations,
+                settings.ai_cache_ttl_hours,
+                settings.ai_cache_max_entries,
+                settings.ai_cache_max_size_mb as u64 * 1024 * 1024,
+            ).await {
+                log::warn!("Failed to cache AI result: {}", e);
             }
-            _ = cancel_rx.recv() => {
-                // Clean up the task from active tasks
-                {
-                    let mut tasks = state.active_ai_tasks.lock().unwrap();
-                    tasks.remove(&task_id);
+        }
+    }
+
+    Ok(ExecutedRequest { transformations: restored, source: ResultSource::Fresh, latency_ms })
+}
+
+/**
+ * Append an instruction and the answer it produced to an item's conversation
+ *
+ * Returns the stored assistant message.
+ */
+async fn record_conversation_turn(
+    db: &ClipboardDatabase,
+    item_id: &str,
+    instruction: &str,
+    transformation: &AITransformation,
+) -> Result<ConversationMessage> {
+    let user_message = ConversationMessage {
+        id: Uuid::new_v4().to_string(),
+        item_id: item_id.to_string(),
+        role: "user".to_string(),
+        content: instruction.to_string(),
+        transformation_id: None,
+        created_at: chrono::Utc::now(),
+    };
+    db.append_conversation_message(&user_message).await?;
+
+    let assistant_message = ConversationMessage {
+        id: Uuid::new_v4().to_string(),
+        item_id: item_id.to_string(),
+        role: "assistant".to_string(),
+        content: transformation.result.clone(),
+        transformation_id: Some(transformation.id.clone()),
+        created_at: chrono::Utc::now(),
+    };
+    db.append_conversation_message(&assistant_message).await?;
+
+    Ok(assistant_message)
+}
+
+/**
+ * Apply one prompt to several clipboard items
+ *
+ * Items are chosen by id, or by a filter when no ids are given, and run
+ * through process_with_ai one after another so results are cached and
+ * recorded per item. An 'ai-batch-progress' event is emitted after each
+ * item. Failures are reported per item; cancelling stops the remaining items.
+ */
+#[command]
+pub async fn batch_process_with_ai(
+    request: BatchAIRequest,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>
+) -> Result<BatchAIResult, String> {
+    if request.prompt.is_none() && request.options.template_id.is_none() {
+        return Err("A prompt or template is required".to_string());
+    }
+
+    let db = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+
+    // Each requested id with its item, or why the item could not be loaded
+    let entries: Vec<(String, Result<ClipboardItem, String>)> = if !request.item_ids.is_empty() {
+        if request.item_ids.len() > batch::MAX_BATCH_ITEMS as usize {
+            return Err(format!(
+                "A batch can have at most {} items, got {}",
+                batch::MAX_BATCH_ITEMS,
+                request.item_ids.len()
+            ));
+        }
+
+        let mut entries = Vec::with_capacity(request.item_ids.len());
+        for item_id in &request.item_ids {
+            let entry = match db.get_clipboard_item(item_id).await {
+                Ok(Some(item)) => Ok(item),
+                Ok(None) => Err("Item not found".to_string()),
+                Err(e) => {
+                    log::warn!("Failed to load batch item {}: {}", item_id, e);
+                    Err("Failed to load item".to_string())
                 }
-                log::info!("❌ AI request cancelled: {}", task_id);
-                return Err("Request cancelled by user".to_string());
+            };
+            entries.push((item_id.clone(), entry));
+        }
+        entries
+    } else if let Some(filter) = &request.filter {
+        db.search_clipboard_items(filter, batch::MAX_BATCH_ITEMS)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to select batch items: {}", e);
+                "Failed to select items".to_string()
+            })?
+            .into_iter()
+            .map(|item| (item.id.clone(), Ok(item)))
+            .collect()
+    } else {
+        return Err("Item ids or a filter are required".to_string());
+    };
+
+    if entries.is_empty() {
+        return Err("No clipboard items matched".to_string());
+    }
+
+    let items: Vec<ClipboardItem> = entries.iter().filter_map(|(_, entry)| entry.as_ref().ok().cloned()).collect();
+    let batch_id = request.batch_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    let total = entries.len();
+    log::info!("📚 Starting AI batch {} over {} items", batch_id, total);
+
+    let mut results: Vec<BatchItemResult> = Vec::with_capacity(total);
+    let mut cancelled = false;
+
+    for (item_id, entry) in &entries {
+        let outcome = match entry {
+            Err(e) => Err(e.clone()),
+            Ok(_) if cancelled => Err("Batch cancelled".to_string()),
+            Ok(item) => {
+                let options = AIProcessOptions {
+                    item_id: Some(item.id.clone()),
+                    request_id: Some(format!("{}:{}", batch_id, item.id)),
+                    ..request.options.clone()
+                };
+                process_with_ai(item.content.clone(), request.prompt.clone(), None, Some(options), app.clone(), state.clone()).await
             }
         };
 
-        match result {
-            Ok(transformation) => {
-                // Clean up the task from active tasks
-                {
-                    let mut tasks = state.active_ai_tasks.lock().unwrap();
-                    tasks.remove(&task_id);
+        if let Err(e) = &outcome {
+            cancelled = cancelled || e.contains("cancelled");
+        }
+
+        let result = match outcome {
+            Ok(transformations) => BatchItemResult { item_id: item_id.clone(), transformations, error: None },
+            Err(e) => {
+                log::warn!("⚠️ Batch item {} failed: {}", item_id, e);
+                BatchItemResult { item_id: item_id.clone(), transformations: Vec::new(), error: Some(e) }
+            }
+        };
+
+        let event = BatchProgressEvent {
+            batch_id: batch_id.clone(),
+            item_id: item_id.clone(),
+            completed: results.len() + 1,
+            total,
+            error: result.error.clone(),
+        };
+        if let Err(e) = app.emit("ai-batch-progress", event) {
+            log::warn!("Failed to emit batch progress: {}", e);
+        }
+
+        results.push(result);
+    }
+
+    let succeeded = results.iter().filter(|result| result.error.is_none()).count();
+    let failed = total - succeeded;
+    log::info!("📚 AI batch {} finished: {} succeeded, {} failed", batch_id, succeeded, failed);
+
+    let combined = if request.combine && succeeded > 0 && !cancelled {
+        let document = batch::combine_results(&items, &results);
+        match &request.combine_prompt {
+            Some(combine_prompt) => {
+                let options = AIProcessOptions {
+                    item_id: None,
+                    template_id: None,
+                    context_item_ids: Vec::new(),
+                    request_id: Some(format!("{}:combine", batch_id)),
+                    ..request.options.clone()
+                };
+                match process_with_ai(document, Some(combine_prompt.clone()), None, Some(options), app.clone(), state.clone()).await {
+                    Ok(transformations) => transformations.into_iter().next(),
+                    Err(e) => {
+                        log::warn!("⚠️ Failed to combine batch results: {}", e);
+                        None
+                    }
                 }
-                
-                log::info!("✅ AI processing completed successfully on attempt {}", attempt);
-                return Ok(vec![transformation]);
             }
+            None => {
+                let confidence = results
+                    .iter()
+                    .filter_map(|result| result.transformations.first())
+                    .map(|transformation| transformation.confidence)
+                    .fold(1.0, f64::min);
+                Some(AITransformation {
+                    id: Uuid::new_v4().to_string(),
+                    title: "Combined results".to_string(),
+                    description: format!("Results for {} of {} items", succeeded, total),
+                    result: document,
+                    confidence,
+                    is_applied: false,
+                    transformation_type: "batch_combined".to_string(),
+                    is_cached: false,
+                    provider: None,
+                    model: None,
+                    context_sources: Vec::new(),
+                    language: None,
+                })
+            }
+        }
+    } else {
+        None
+    };
+
+    Ok(BatchAIResult { batch_id, results, succeeded, failed, combined })
+}
+
+/**
+ * Turn raw context strings and clipboard item ids into labelled context items
+ *
+ * Raw strings are classified with the content detector; referenced items use
+ * the type and language stored with them. Ids that no longer exist are skipped.
+ */
+async fn resolve_context_items(
+    database: Option<&ClipboardDatabase>,
+    raw_items: Vec<String>,
+    item_ids: &[String],
+) -> Result<Vec<ContextItem>, String> {
+    let detector = ContentDetector::new();
+    let mut items: Vec<ContextItem> = raw_items
+        .into_iter()
+        .map(|content| {
+            let info = detector.detect_content(&content);
+            ContextItem {
+                item_id: None,
+                content_type: info.content_type,
+                code_language: info.code_language,
+                content,
+                truncated: false,
+            }
+        })
+        .collect();
+
+    if item_ids.is_empty() {
+        return Ok(items);
+    }
+
+    let db = database.ok_or_else(|| "Database not available".to_string())?;
+    for item_id in item_ids {
+        match db.get_clipboard_item(item_id).await {
+            Ok(Some(item)) => items.push(ContextItem {
+                item_id: Some(item.id),
+                content_type: item.content_type,
+                code_language: item.code_language,
+                content: item.content,
+                truncated: false,
+            }),
+            Ok(None) => log::warn!("Context item not found, skipping: {}", item_id),
             Err(e) => {
-                last_error = e;
-                log::warn!("⚠️  AI request attempt {} failed: {}", attempt, last_error);
-                
-                // Don't retry for certain errors
-                if last_error.contains("cancelled") || 
-                   last_error.contains("Invalid response format") ||
-                   last_error.contains("Cannot connect") {
-                    break;
-                }
-                
-                // Exponential backoff before retry
-                if attempt < max_retries {
-                    let delay = std::time::Duration::from_millis(1000 * (2_u64.pow(attempt - 1)));
-                    log::debug!("⏳ Waiting {}ms before retry", delay.as_millis());
-                    tokio::time::sleep(delay).await;
-                }
+                log::error!("Failed to load context item {}: {}", item_id, e);
+                return Err("Failed to load context items".to_string());
             }
         }
     }
 
-    // Clean up the task from active tasks
-    {
-        let mut tasks = state.active_ai_tasks.lock().unwrap();
-        tasks.remove(&task_id);
+    Ok(items)
+}
+
+/**
+ * Recent clipboard items related to the content, excluding the given ids
+ *
+ * Failing to load history only means no context is added.
+ */
+async fn select_auto_context(
+    db: &ClipboardDatabase,
+    content: &str,
+    code_language: Option<&str>,
+    excluded_ids: &[String],
+    budget: usize,
+) -> Vec<SelectedContext> {
+    let candidates = match db.search_clipboard_items(&ClipboardFilter::default(), context_selection::MAX_CANDIDATES).await {
+        Ok(items) => items,
+        Err(e) => {
+            log::warn!("Failed to load recent items for context selection: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let candidates: Vec<ClipboardItem> = candidates
+        .into_iter()
+        .filter(|item| !excluded_ids.contains(&item.id))
+        .collect();
+    context_selection::select_context(content, code_language, &candidates, budget)
+}
+
+/**
+ * Search clipboard history by meaning using local embeddings
+ *
+ * Parameters:
+ * - query: What to look for, in natural language
+ * - limit: Maximum number of results (default 20)
+ * - hybrid: Blend in keyword matches, which also finds items not indexed yet
+ */
+#[command]
+pub async fn semantic_search(
+    query: String,
+    limit: Option<u32>,
+    hybrid: Option<bool>,
+    state: State<'_, AppState>
+) -> Result<Vec<SemanticSearchResult>, String> {
+    if query.trim().is_empty() {
+        return Err("Query is empty".to_string());
     }
 
-    log::error!("❌ AI processing failed after {} attempts: {}", max_retries, last_error);
-    Err(format!("AI processing failed after {} attempts: {}", max_retries, last_error))
+    let database = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+    let model = state.settings.lock().unwrap().embedding_model.clone();
+
+    let results = embeddings::semantic_search(
+        &database,
+        &model,
+        &query,
+        limit.unwrap_or(20) as usize,
+        hybrid.unwrap_or(false),
+    ).await?;
+
+    log::info!("🔎 Semantic search returned {} results", results.len());
+    Ok(results)
 }
 
 /**
- * Helper function to make AI requests with improved error handling
+ * Generate the clipboard digest for a day now
+ *
+ * Defaults to today. Creates a new digest even if one already exists.
  */
-async fn make_ai_request(request_body: &serde_json::Value) -> Result<AITransformation, String> {
-    let response = HTTP_CLIENT
-        .post("http://localhost:11434/v1/chat/completions")
-        .header("Content-Type", "application/json")
-        .header("Accept", "application/json")
-        .json(request_body)
-        .send()
-        .await
-        .map_err(|e| {
-            if e.is_timeout() {
-                "AI request timed out (120s). The model might be busy or Ollama needs restart.".to_string()
-            } else if e.is_connect() {
-                "Cannot connect to AI service. Please start Ollama: ollama serve".to_string()
-            } else {
-                format!("Network error: {}", e)
-            }
-        })?;
+#[command]
+pub async fn generate_digest(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    date: Option<String>
+) -> Result<ClipboardItem, String> {
+    let date = match date {
+        Some(date) => chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .map_err(|_| "Date must be in YYYY-MM-DD format".to_string())?,
+        None => chrono::Local::now().date_naive(),
+    };
 
-    if !response.status().is_success() {
-        return Err(format!("AI service error: {} - {}", 
-                          response.status(), 
-                          response.text().await.unwrap_or_else(|_| "Unknown error".to_string())));
-    }
-
-    let json = response.json::<serde_json::Value>().await
-        .map_err(|e| format!("Failed to parse AI response as JSON: {}", e))?;
-
-    // Better response validation
-    let content = json
-        .get("choices")
-        .and_then(|choices| choices.get(0))
-        .and_then(|choice| choice.get("message"))
-        .and_then(|message| message.get("content"))
-        .and_then(|content| content.as_str())
-        .ok_or_else(|| {
-            log::error!("Invalid AI response structure: {}", json);
-            "Invalid response format from AI service".to_string()
-        })?;
+    let settings = state.settings.lock().unwrap().clone();
+    let db = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
 
-    if content.trim().is_empty() {
-        return Err("AI returned empty response".to_string());
+    digest::generate_digest(&app, &db, &settings, date).await.inspect_err(|e| {
+        log::warn!("⚠️ Clipboard digest for {} failed: {}", date, e);
+    })
+}
+
+/**
+ * Get AI call metrics aggregated per model and day
+ *
+ * Covers the last `days` days (default 30), newest day first.
+ */
+#[command]
+pub async fn get_ai_metrics(state: State<'_, AppState>, days: Option<u32>) -> Result<Vec<ModelDayMetrics>, String> {
+    let Some(db) = get_database(&state) else {
+        log::warn!("Database not initialized");
+        return Ok(vec![]);
+    };
+
+    let since = chrono::Utc::now() - chrono::Duration::days(days.unwrap_or(30).max(1) as i64);
+    let calls = db.get_ai_call_metrics(since).await.map_err(|e| {
+        log::error!("Failed to get AI call metrics: {}", e);
+        "Failed to fetch AI metrics".to_string()
+    })?;
+
+    Ok(metrics::aggregate_by_model_and_day(&calls))
+}
+
+/**
+ * Unload the models of the local Ollama providers and the embedded model to free memory
+ *
+ * They are loaded again by the next request or warm-up.
+ */
+#[command]
+pub async fn unload_ai_model(state: State<'_, AppState>) -> Result<(), String> {
+    let settings = state.settings.lock().unwrap().clone();
+
+    for provider in providers::provider_chain(&settings, None) {
+        if provider.kind == ProviderKind::Ollama && provider.is_local() {
+            ollama::unload(&provider).await.inspect_err(|e| {
+                log::error!("Failed to unload model {}: {}", provider.model, e);
+            })?;
+        }
+    }
+
+    if embedded::unload() {
+        log::info!("💤 Unloaded embedded model");
+    }
+
+    Ok(())
+}
+
+/**
+ * List the built-in (non-AI) transforms
+ */
+#[command]
+pub async fn list_builtin_transforms() -> Result<Vec<BuiltinTransform>, String> {
+    Ok(TRANSFORM_REGISTRY.transforms().to_vec())
+}
+
+/**
+ * Apply a single built-in transform to content
+ *
+ * Runs instantly and offline, independent of the AI service.
+ */
+#[command]
+pub async fn apply_builtin_transform(
+    content: String,
+    transform_id: String
+) -> Result<AITransformation, String> {
+    if content.is_empty() {
+        return Err("Content is empty".to_string());
     }
 
-    let transformation = AITransformation {
-        id: format!("ai-{}", chrono::Utc::now().timestamp()),
-        title: "AI Enhanced Content".to_string(),
-        description: "AI-processed content with context".to_string(),
-        result: content.to_string(),
-        confidence: 0.9,
-        is_applied: false,
-        transformation_type: "enhancement".to_string(),
+    TRANSFORM_REGISTRY.apply(&transform_id, &content).map_err(|e| {
+        log::warn!("Built-in transform {} failed: {}", transform_id, e);
+        e
+    })
+}
+
+/**
+ * Run every built-in transform that applies to the content
+ *
+ * Transforms that worked well on this type of content come first.
+ */
+#[command]
+pub async fn run_builtin_transforms(
+    content: String,
+    state: State<'_, AppState>
+) -> Result<Vec<AITransformation>, String> {
+    if content.is_empty() {
+        return Err("Content is empty".to_string());
+    }
+
+    let mut transformations = TRANSFORM_REGISTRY.applicable(&content);
+    log::debug!("🧰 {} built-in transforms apply to {} chars", transformations.len(), content.len());
+
+    if let Some(db) = get_database(&state) {
+        let content_type = ContentDetector::new().detect_content(&content).content_type;
+        let scores = preset_scores(&db, &content_type).await;
+        feedback::sort_by_score(&mut transformations, &scores, PresetKind::Builtin, |transformation| {
+            feedback::builtin_preset(&transformation.id).unwrap_or_default()
+        });
+    }
+
+    Ok(transformations)
+}
+
+
+/**
+ * List the models installed in the local Ollama server
+ */
+#[command]
+pub async fn list_ai_models(state: State<'_, AppState>) -> Result<Vec<AIModelInfo>, String> {
+    let active_model = state.settings.lock().unwrap().ai_model.clone();
+    ollama::list_models(&active_model).await.inspect_err(|e| {
+        log::warn!("Failed to list AI models: {}", e);
+    })
+}
+
+/**
+ * Select the model used for AI requests
+ *
+ * The model must already be installed; use pull_ai_model to download it first.
+ */
+#[command]
+pub async fn set_active_model(
+    state: State<'_, AppState>,
+    model: String
+) -> Result<(), String> {
+    let models = ollama::list_models(&model).await?;
+    if !models.iter().any(|installed| installed.is_active) {
+        return Err(format!("Model '{}' is not installed. Pull it first.", model));
+    }
+
+    let settings = {
+        let mut settings = state.settings.lock().unwrap();
+        settings.ai_model = model.clone();
+        settings.clone()
     };
+    persist_settings(&state, &settings).await?;
 
-    Ok(transformation)
+    log::info!("🧠 Active AI model set to {}", model);
+    Ok(())
 }
 
+/**
+ * Download a model through the Ollama pull API
+ *
+ * Emits "ai-model-pull-progress" events while downloading. The pull can be
+ * stopped with cancel_ai_requests.
+ */
+#[command]
+pub async fn pull_ai_model(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    model: String
+) -> Result<(), String> {
+    if model.trim().is_empty() {
+        return Err("Model name is empty".to_string());
+    }
+
+    let task_id = Uuid::new_v4().to_string();
+    let (cancel_tx, mut cancel_rx) = broadcast::channel(1);
+    state.active_ai_tasks.lock().unwrap().insert(task_id.clone(), cancel_tx);
+
+    log::info!("⬇️  Pulling AI model: {}", model);
+    let result = ollama::pull_model(&model, &mut cancel_rx, |progress| {
+        if let Err(e) = app.emit("ai-model-pull-progress", &progress) {
+            log::warn!("Failed to emit pull progress: {}", e);
+        }
+    }).await;
+
+    state.active_ai_tasks.lock().unwrap().remove(&task_id);
+
+    match &result {
+        Ok(()) => log::info!("✅ Pulled AI model: {}", model),
+        Err(e) => log::error!("❌ Failed to pull AI model {}: {}", model, e),
+    }
+    result
+}
 
+/**
+ * Check if Ollama is running and the configured model is available
+ *
+ * Returns detailed diagnostics (version, latency, loaded state and a
+ * classified error) so the setup guide can suggest a precise fix.
+ */
+#[command]
+pub async fn check_ai_status(state: State<'_, AppState>) -> Result<AIStatus, String> {
+    let model = state.settings.lock().unwrap().ai_model.clone();
+    Ok(ollama::check_status(&model).await)
+}
 
 /**
- * Check if Ollama is running and has gpt-oss model
+ * Get the AI transformations recorded for a clipboard item, newest first
  */
 #[command]
-pub async fn check_ai_status() -> Result<serde_json::Value, String> {
-    let client = reqwest::Client::new();
-    
-    // Check if Ollama is running (increased timeout for busy server)
-    match client
-        .get("http://localhost:11434/api/tags")
-        .timeout(std::time::Duration::from_secs(10))
-        .send()
-        .await
-    {
-        Ok(response) => {
-            if response.status().is_success() {
-                match response.json::<serde_json::Value>().await {
-                    Ok(data) => {
-                        let empty_vec = vec![];
-                        let models = data["models"].as_array().unwrap_or(&empty_vec);
-                        let has_gpt_oss = models.iter().any(|model| {
-                            model["name"].as_str()
-                                .map(|name| name.contains("gpt-oss"))
-                                .unwrap_or(false)
-                        });
-                        
-                        Ok(serde_json::json!({
-                            "ollama_running": true,
-                            "has_gpt_oss": has_gpt_oss,
-                            "message": if has_gpt_oss {
-                                "AI features are ready!"
-                            } else {
-                                "Ollama is running but gpt-oss model not found. Run: ollama pull gpt-oss:20b"
-                            }
-                        }))
-                    }
-                    Err(_) => Ok(serde_json::json!({
-                        "ollama_running": false,
-                        "has_gpt_oss": false,
-                        "message": "Ollama is running but returned invalid response"
-                    }))
+pub async fn get_item_transformations(
+    state: State<'_, AppState>,
+    item_id: String
+) -> Result<Vec<AITransformationRecord>, String> {
+    let Some(db) = get_database(&state) else {
+        log::warn!("Database not initialized");
+        return Ok(vec![]);
+    };
+
+    db.get_item_transformations(&item_id).await.map_err(|e| {
+        log::error!("Failed to get transformations for item {}: {}", item_id, e);
+        "Failed to fetch AI transformation history".to_string()
+    })
+}
+
+/**
+ * Re-apply an earlier AI transformation
+ *
+ * Copies the stored result to the clipboard and records it as applied,
+ * without calling the model again.
+ */
+#[command]
+pub async fn reapply_transformation(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    transformation_id: String
+) -> Result<AITransformationRecord, String> {
+    let db = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+
+    let mut record = db.get_ai_transformation(&transformation_id).await
+        .map_err(|e| {
+            log::error!("Failed to load AI transformation {}: {}", transformation_id, e);
+            "Failed to load AI transformation".to_string()
+        })?
+        .ok_or_else(|| "AI transformation not found".to_string())?;
+
+    app.clipboard().write_text(record.transformation.result.clone()).map_err(|e| {
+        log::error!("Failed to set clipboard content: {}", e);
+        "Failed to set clipboard content".to_string()
+    })?;
+
+    record_feedback(&db, &transformation_id, FeedbackAction::Applied, None).await?;
+    record.transformation.is_applied = true;
+
+    log::info!("♻️  Re-applied AI transformation: {}", transformation_id);
+    Ok(record)
+}
+
+/**
+ * Record what the user did with a transformation result
+ *
+ * Parameters:
+ * - transformation_id: A recorded AI transformation, or a built-in result ("builtin-<id>-<uuid>")
+ * - action: applied, copied, rejected, thumbs_up or thumbs_down
+ * - content_type: Type of the content the result was for; recorded AI
+ *   transformations use their source item's type instead
+ */
+#[command]
+pub async fn record_transformation_feedback(
+    state: State<'_, AppState>,
+    transformation_id: String,
+    action: FeedbackAction,
+    content_type: Option<String>
+) -> Result<(), String> {
+    let db = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+    record_feedback(&db, &transformation_id, action, content_type).await?;
+
+    log::info!("👍 Recorded {} for {}", action.as_str(), transformation_id);
+    Ok(())
+}
+
+/**
+ * Get how prompt templates and built-in transforms have done on a content type, best first
+ */
+#[command]
+pub async fn get_preset_rankings(
+    state: State<'_, AppState>,
+    content_type: String
+) -> Result<Vec<PresetScore>, String> {
+    let Some(db) = get_database(&state) else {
+        log::warn!("Database not initialized");
+        return Ok(vec![]);
+    };
+
+    let since = chrono::Utc::now() - chrono::Duration::days(feedback::RANKING_WINDOW_DAYS);
+    let entries = db.get_feedback(&content_type, since).await.map_err(|e| {
+        log::error!("Failed to load feedback for {}: {}", content_type, e);
+        "Failed to fetch preset rankings".to_string()
+    })?;
+
+    Ok(feedback::rank_presets(&entries, chrono::Utc::now()))
+}
+
+/**
+ * Store feedback, attributing it to the preset and content type of the result
+ *
+ * Applying a recorded AI transformation also marks it as applied. Results
+ * that are neither recorded nor built-in are recorded without a preset, and
+ * use the given content type or "text".
+ */
+async fn record_feedback(
+    db: &ClipboardDatabase,
+    transformation_id: &str,
+    action: FeedbackAction,
+    content_type: Option<String>,
+) -> Result<(), String> {
+    let mut preset = feedback::builtin_preset(transformation_id)
+        .map(|id| (PresetKind::Builtin, id.to_string()));
+    let mut content_type = content_type;
+
+    if preset.is_none() {
+        let record = db.get_ai_transformation(transformation_id).await.map_err(|e| {
+            log::error!("Failed to load AI transformation {}: {}", transformation_id, e);
+            "Failed to load AI transformation".to_string()
+        })?;
+
+        if let Some(record) = record {
+            preset = record.template_id.map(|id| (PresetKind::Template, id));
+
+            if let Some(item_id) = &record.item_id {
+                match db.get_clipboard_item(item_id).await {
+                    Ok(Some(item)) => content_type = Some(item.content_type),
+                    Ok(None) => {}
+                    Err(e) => log::warn!("Failed to load source item {}: {}", item_id, e),
                 }
-            } else {
-                Ok(serde_json::json!({
-                    "ollama_running": false,
-                    "has_gpt_oss": false,
-                    "message": "Ollama server error"
-                }))
+            }
+
+            if action == FeedbackAction::Applied {
+                db.mark_transformation_applied(transformation_id).await.map_err(|e| {
+                    log::error!("Failed to mark AI transformation {} as applied: {}", transformation_id, e);
+                    "Failed to update AI transformation".to_string()
+                })?;
             }
         }
-        Err(_) => {
-            Ok(serde_json::json!({
-                "ollama_running": false,
-                "has_gpt_oss": false,
-                "message": "Ollama not running. Please start it with: ollama serve"
-            }))
+    }
+
+    let (preset_kind, preset_id) = match preset {
+        Some((kind, id)) => (Some(kind), Some(id)),
+        None => (None, None),
+    };
+    let entry = TransformationFeedback {
+        id: Uuid::new_v4().to_string(),
+        transformation_id: transformation_id.to_string(),
+        action,
+        preset_kind,
+        preset_id,
+        content_type: content_type.unwrap_or_else(|| "text".to_string()),
+        created_at: chrono::Utc::now(),
+    };
+
+    db.store_feedback(&entry).await.map_err(|e| {
+        log::error!("Failed to store feedback for {}: {}", transformation_id, e);
+        "Failed to record feedback".to_string()
+    })
+}
+
+/**
+ * Preset scores for a content type; no feedback means no reordering
+ */
+async fn preset_scores(db: &ClipboardDatabase, content_type: &str) -> Vec<PresetScore> {
+    let since = chrono::Utc::now() - chrono::Duration::days(feedback::RANKING_WINDOW_DAYS);
+    match db.get_feedback(content_type, since).await {
+        Ok(entries) => feedback::rank_presets(&entries, chrono::Utc::now()),
+        Err(e) => {
+            log::warn!("Failed to load feedback for {}: {}", content_type, e);
+            Vec::new()
         }
     }
 }
 
+/**
+ * Get the refinement conversation about a clipboard item, oldest message first
+ */
+#[command]
+pub async fn get_conversation(state: State<'_, AppState>, item_id: String) -> Result<Vec<ConversationMessage>, String> {
+    let Some(db) = get_database(&state) else {
+        log::warn!("Database not initialized");
+        return Ok(vec![]);
+    };
+
+    db.get_conversation(&item_id).await.map_err(|e| {
+        log::error!("Failed to get conversation for item {}: {}", item_id, e);
+        "Failed to fetch conversation".to_string()
+    })
+}
+
+/**
+ * Refine the latest result for a clipboard item with a follow-up instruction
+ *
+ * The model receives the earlier turns of the conversation (as many as fit
+ * in the context budget) along with the item and the new instruction, so
+ * requests like "shorter" or "now in TypeScript" build on the last answer.
+ * Returns the assistant's reply, which is also appended to the conversation.
+ */
+#[command]
+pub async fn send_follow_up(
+    item_id: String,
+    instruction: String,
+    options: Option<AIProcessOptions>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>
+) -> Result<ConversationMessage, String> {
+    if instruction.trim().is_empty() {
+        return Err("Instruction is empty".to_string());
+    }
+
+    let options = options.unwrap_or_default();
+    let settings = state.settings.lock().unwrap().clone();
+    let db = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+
+    let item = db.get_clipboard_item(&item_id).await
+        .map_err(|e| {
+            log::error!("Failed to load clipboard item {}: {}", item_id, e);
+            "Failed to load clipboard item".to_string()
+        })?
+        .ok_or_else(|| "Clipboard item not found".to_string())?;
+
+    let turns = db.get_conversation(&item_id).await.map_err(|e| {
+        log::error!("Failed to get conversation for item {}: {}", item_id, e);
+        "Failed to fetch conversation".to_string()
+    })?;
+
+    log::info!("💬 Follow-up on item {} after {} conversation messages", item_id, turns.len());
+
+    // Keep the format asked for earlier unless the follow-up asks for another one
+    let instructions: Vec<&str> = turns
+        .iter()
+        .filter(|turn| turn.role == "user")
+        .map(|turn| turn.content.as_str())
+        .chain(std::iter::once(instruction.as_str()))
+        .collect();
+    let output_format = output_validation::expected_follow_up_format(
+        &instructions,
+        &item.content_type,
+        item.code_language.as_deref(),
+        &item.content,
+    );
+
+    let history: Vec<ChatMessage> = turns
+        .iter()
+        .map(|turn| ChatMessage { role: turn.role.clone(), content: turn.content.clone() })
+        .collect();
+    let history_budget = settings.ai_context_budget_tokens.min(settings.ai_context_window / 2);
+    let history = ai::fit_history_to_budget(history, history_budget as usize);
+
+    // Replace secrets and personal data with placeholders before anything reaches the model
+    let mut redactor = Redactor::new();
+    let (request_content, request_instruction, history) = if settings.ai_redaction_enabled {
+        let request_content = redactor.redact(&item.content);
+        let request_instruction = redactor.redact(&instruction);
+        let history = history
+            .into_iter()
+            .map(|mut message| {
+                message.content = redactor.redact(&message.content);
+                message
+            })
+            .collect();
+        if redactor.redaction_count() > 0 {
+            log::info!("🕶️ Redacted {} sensitive value(s) before sending to AI", redactor.redaction_count());
+        }
+        (request_content, request_instruction, history)
+    } else {
+        (item.content.clone(), instruction.clone(), history)
+    };
+
+    let providers = providers::provider_chain(&settings, None);
+    let request = AIRequest {
+        content: request_content,
+        instruction: request_instruction,
+        has_custom_instruction: true,
+        instruction_from_template: false,
+        context_items: Vec::new(),
+        system_prompt: ai::SYSTEM_PROMPT.to_string(),
+        model: providers[0].model.clone(),
+        temperature: options.temperature.unwrap_or(settings.ai_temperature),
+        max_tokens: options.max_tokens.unwrap_or(settings.ai_max_tokens),
+        context_window: settings.ai_context_window,
+        output_format,
+        history,
+        providers,
+        timeout: providers::request_timeout(&settings),
+    };
+
+    let executed = execute_ai_request(&request, &redactor, &options, &app, &state).await?;
+    let transformation = executed.transformations
+        .into_iter()
+        .next()
+        .ok_or_else(|| "Model returned no result".to_string())?;
+
+    // An identical follow-up that was already running records the turn itself
+    if executed.source == ResultSource::Shared {
+        return Ok(ConversationMessage {
+            id: Uuid::new_v4().to_string(),
+            item_id,
+            role: "assistant".to_string(),
+            content: transformation.result,
+            transformation_id: Some(transformation.id),
+            created_at: chrono::Utc::now(),
+        });
+    }
+
+    if executed.source == ResultSource::Fresh {
+        let record = AITransformationRecord {
+            id: transformation.id.clone(),
+            item_id: Some(item_id.clone()),
+            prompt: instruction.clone(),
+            model: transformation.model.clone().unwrap_or_else(|| request.model.clone()),
+            transformation: transformation.clone(),
+            latency_ms: executed.latency_ms,
+            template_id: None,
+            context_sources: Vec::new(),
+            created_at: chrono::Utc::now(),
+        };
+        if let Err(e) = db.store_ai_transformation(&record).await {
+            log::warn!("Failed to record AI transformation: {}", e);
+        }
+    }
+
+    record_conversation_turn(&db, &item_id, &instruction, &transformation).await.map_err(|e| {
+        log::error!("Failed to record conversation turn for item {}: {}", item_id, e);
+        "Failed to save conversation".to_string()
+    })
+}
+
+/**
+ * Copy the content of a conversation message to the clipboard
+ */
+#[command]
+pub async fn copy_conversation_turn(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    message_id: String
+) -> Result<(), String> {
+    let db = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+
+    let message = db.get_conversation_message(&message_id).await
+        .map_err(|e| {
+            log::error!("Failed to load conversation message {}: {}", message_id, e);
+            "Failed to load conversation message".to_string()
+        })?
+        .ok_or_else(|| "Conversation message not found".to_string())?;
+
+    app.clipboard().write_text(message.content).map_err(|e| {
+        log::error!("Failed to set clipboard content: {}", e);
+        "Failed to set clipboard content".to_string()
+    })?;
+
+    if let Some(transformation_id) = &message.transformation_id {
+        if let Err(e) = record_feedback(&db, transformation_id, FeedbackAction::Copied, None).await {
+            log::warn!("Failed to record copy of {}: {}", transformation_id, e);
+        }
+    }
+
+    log::info!("📋 Copied conversation message: {}", message_id);
+    Ok(())
+}
+
+/**
+ * Delete the refinement conversation about a clipboard item
+ */
+#[command]
+pub async fn clear_conversation(state: State<'_, AppState>, item_id: String) -> Result<(), String> {
+    let db = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+
+    db.clear_conversation(&item_id).await.map_err(|e| {
+        log::error!("Failed to clear conversation for item {}: {}", item_id, e);
+        "Failed to clear conversation".to_string()
+    })?;
+
+    log::info!("🧹 Cleared conversation for item {}", item_id);
+    Ok(())
+}
+
+/**
+ * List all prompt templates
+ *
+ * With a content type, templates that worked well on it come first;
+ * otherwise they are ordered by name.
+ */
+#[command]
+pub async fn list_prompt_templates(
+    state: State<'_, AppState>,
+    content_type: Option<String>
+) -> Result<Vec<PromptTemplate>, String> {
+    let Some(db) = get_database(&state) else {
+        log::warn!("Database not initialized");
+        return Ok(vec![]);
+    };
+
+    let mut templates = db.list_prompt_templates().await.map_err(|e| {
+        log::error!("Failed to list prompt templates: {}", e);
+        "Failed to fetch prompt templates".to_string()
+    })?;
+
+    if let Some(content_type) = &content_type {
+        let scores = preset_scores(&db, content_type).await;
+        feedback::sort_by_score(&mut templates, &scores, PresetKind::Template, |template| &template.id);
+    }
+
+    Ok(templates)
+}
+
+/**
+ * Create or update a prompt template
+ *
+ * Templates without an id are created; otherwise the existing template is updated.
+ */
+#[command]
+pub async fn save_prompt_template(
+    state: State<'_, AppState>,
+    template: PromptTemplateInput
+) -> Result<PromptTemplate, String> {
+    if template.name.trim().is_empty() {
+        return Err("Template name is required".to_string());
+    }
+    if template.body.trim().is_empty() {
+        return Err("Template body is required".to_string());
+    }
+
+    let db = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+
+    let now = chrono::Utc::now();
+    let created_at = match &template.id {
+        Some(id) => db.get_prompt_template(id).await
+            .map_err(|e| e.to_string())?
+            .map(|existing| existing.created_at)
+            .unwrap_or(now),
+        None => now,
+    };
+
+    let template = PromptTemplate {
+        id: template.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+        name: template.name.trim().to_string(),
+        description: template.description,
+        body: template.body,
+        model: template.model,
+        temperature: template.temperature,
+        max_tokens: template.max_tokens,
+        created_at,
+        updated_at: now,
+    };
+
+    db.save_prompt_template(&template).await.map_err(|e| {
+        log::error!("Failed to save prompt template {}: {}", template.name, e);
+        format!("Failed to save prompt template '{}'. Template names must be unique.", template.name)
+    })?;
+
+    log::info!("Saved prompt template: {}", template.name);
+    Ok(template)
+}
+
+/**
+ * Delete a prompt template
+ */
+#[command]
+pub async fn delete_prompt_template(
+    state: State<'_, AppState>,
+    template_id: String
+) -> Result<(), String> {
+    let db = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+    db.delete_prompt_template(&template_id).await.map_err(|e| {
+        log::error!("Failed to delete prompt template {}: {}", template_id, e);
+        "Failed to delete prompt template".to_string()
+    })
+}
+
+/**
+ * Export all prompt templates as a TOML document
+ */
+#[command]
+pub async fn export_prompt_templates(state: State<'_, AppState>) -> Result<String, String> {
+    let db = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+    let templates = db.list_prompt_templates().await.map_err(|e| {
+        log::error!("Failed to list prompt templates: {}", e);
+        "Failed to fetch prompt templates".to_string()
+    })?;
+
+    prompt_templates::export_templates(&templates)
+}
+
+/**
+ * Import prompt templates from a TOML document
+ *
+ * Templates are matched by name: existing ones are updated, new ones created.
+ * Returns the number of templates imported.
+ */
+#[command]
+pub async fn import_prompt_templates(
+    state: State<'_, AppState>,
+    toml: String
+) -> Result<u32, String> {
+    let entries = prompt_templates::parse_templates(&toml)?;
+    let db = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+
+    let now = chrono::Utc::now();
+    for entry in &entries {
+        let existing = db.find_prompt_template_by_name(entry.name.trim()).await
+            .map_err(|e| e.to_string())?;
+
+        let template = PromptTemplate {
+            id: existing.as_ref().map(|t| t.id.clone()).unwrap_or_else(|| Uuid::new_v4().to_string()),
+            name: entry.name.trim().to_string(),
+            description: entry.description.clone(),
+            body: entry.body.clone(),
+            model: entry.model.clone(),
+            temperature: entry.temperature,
+            max_tokens: entry.max_tokens,
+            created_at: existing.as_ref().map(|t| t.created_at).unwrap_or(now),
+            updated_at: now,
+        };
+
+        db.save_prompt_template(&template).await.map_err(|e| {
+            log::error!("Failed to import prompt template {}: {}", template.name, e);
+            format!("Failed to import prompt template '{}'", template.name)
+        })?;
+    }
+
+    log::info!("📥 Imported {} prompt template(s)", entries.len());
+    Ok(entries.len() as u32)
+}
+
+/**
+ * Remove every cached AI result
+ */
+#[command]
+pub async fn clear_ai_cache(state: State<'_, AppState>) -> Result<(), String> {
+    let db = get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+    db.clear_ai_cache().await.map_err(|e| {
+        log::error!("Failed to clear AI cache: {}", e);
+        "Failed to clear AI cache".to_string()
+    })
+}
+
 /**
  * Toggle panel visibility
  */
@@ -491,6 +1712,7 @@ pub async fn toggle_panel_visibility(app_handle: tauri::AppHandle) -> Result<boo
         } else {
             main_window.show().map_err(|e| e.to_string())?;
             main_window.set_focus().map_err(|e| e.to_string())?;
+            warm_up_on_panel_open(&app_handle);
             Ok(true)
         }
     } else {
@@ -525,8 +1747,19 @@ pub async fn show_panel(app_handle: tauri::AppHandle) -> Result<(), String> {
         main_window.show().map_err(|e| e.to_string())?;
         main_window.set_focus().map_err(|e| e.to_string())?;
         main_window.set_always_on_top(true).map_err(|e| e.to_string())?;
+        warm_up_on_panel_open(&app_handle);
         Ok(())
     } else {
         Err("Main window not found".to_string())
     }
 }
+
+/**
+ * Load the model in the background when the panel opens, if enabled
+ */
+pub fn warm_up_on_panel_open(app_handle: &tauri::AppHandle) {
+    let settings = app_handle.state::<AppState>().settings.lock().unwrap().clone();
+    if settings.ai_warmup_on_panel_open {
+        ollama::spawn_warm_up(&settings);
+    }
+}
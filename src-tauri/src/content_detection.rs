@@ -20,6 +20,7 @@ pub struct ContentDetector {
     html_regex: Regex,
     markdown_regex: Regex,
     code_patterns: HashMap<String, Vec<Regex>>,
+    sensitive_patterns: Vec<Regex>,
 }
 
 impl ContentDetector {
@@ -36,9 +37,11 @@ impl ContentDetector {
             html_regex: Regex::new(r"<\/?[a-z][\s\S]*>").unwrap(),
             markdown_regex: Regex::new(r"^#{1,6}\s|^\*\*|^__|\[.*\]\(.*\)|^\s*[-+*]\s").unwrap(),
             code_patterns: HashMap::new(),
+            sensitive_patterns: Vec::new(),
         };
         
         detector.init_code_patterns();
+        detector.init_sensitive_patterns();
         detector
     }
     
@@ -89,6 +92,36 @@ impl ContentDetector {
         // Add more language patterns as needed
     }
     
+    /**
+     * Initialize patterns for secrets and credentials
+     */
+    fn init_sensitive_patterns(&mut self) {
+        self.sensitive_patterns = vec![
+            // PEM private keys
+            Regex::new(r"-----BEGIN (?:[A-Z]+ )?PRIVATE KEY-----").unwrap(),
+            // AWS access key ids
+            Regex::new(r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b").unwrap(),
+            // GitHub, Slack and OpenAI style tokens
+            Regex::new(r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|xox[abprs]-[A-Za-z0-9-]{10,}|sk-[A-Za-z0-9_-]{20,})\b").unwrap(),
+            // JSON Web Tokens
+            Regex::new(r"\beyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}").unwrap(),
+            // password = ..., api_key: ..., secret=...
+            Regex::new(r#"(?i)\b(?:password|passwd|pwd|secret|api[_-]?key|access[_-]?token|auth[_-]?token|client[_-]?secret)\b\s*[:=]\s*["']?[^\s"']{4,}"#).unwrap(),
+            // Credentials embedded in URLs
+            Regex::new(r"[a-z][a-z0-9+.-]*://[^\s/:@]+:[^\s/@]+@").unwrap(),
+        ];
+    }
+    
+    /**
+     * Check whether content looks like a secret or credential
+     * 
+     * Matches private keys, well-known token formats, credential assignments
+     * and URLs with embedded passwords.
+     */
+    pub fn is_sensitive(&self, content: &str) -> bool {
+        self.sensitive_patterns.iter().any(|pattern| pattern.is_match(content))
+    }
+    
     /**
      * Detect content type and properties
      */
@@ -0,0 +1,345 @@
+/**
+ * Automatic context selection for Wurdump
+ *
+ * Picks recent clipboard items that are likely to help the model with the
+ * content being processed: items sharing distinctive keywords, code in the
+ * same language, and errors or stack traces that mention code (or code that
+ * an error mentions). Each selected item carries the reasons it was picked
+ * so the user can see and remove it. Sensitive items are never selected.
+ */
+
+use std::collections::HashSet;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use crate::chunking::estimate_tokens;
+use crate::content_detection::ContentDetector;
+use crate::ClipboardItem;
+
+/// Recent items considered for selection
+pub const MAX_CANDIDATES: u32 = 30;
+
+/// Most items selected automatically for one request
+const MAX_SELECTED_ITEMS: usize = 3;
+
+/// Score an item needs to be selected
+const MIN_SCORE: f64 = 0.35;
+
+/// Distinctive words two items need in common before overlap counts
+const MIN_SHARED_KEYWORDS: usize = 2;
+
+const SAME_LANGUAGE_SCORE: f64 = 0.3;
+const TRACE_MENTION_SCORE: f64 = 0.6;
+
+/// Keywords listed in a reason
+const REASON_KEYWORDS: usize = 4;
+
+static DETECTOR: Lazy<ContentDetector> = Lazy::new(ContentDetector::new);
+
+static WORD_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"[A-Za-z_][A-Za-z0-9_]{3,}").unwrap());
+
+/// Error messages and stack traces from common languages and tools
+static ERROR_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?m)Traceback \(most recent call last\)|panicked at|^\s*at [\w$.<>]+ ?\(.*:\d+(?::\d+)?\)|File "[^"]+", line \d+|^\s*--> [\w./\\-]+:\d+:\d+|\b(?:[A-Z]\w*(?:Error|Exception)|error\[E\d+\]|Uncaught \w+|FATAL|Segmentation fault)\b"#).unwrap()
+});
+
+/// Names of functions and types defined in code
+static DEFINITION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(?:fn|def|function|class|struct|enum|trait|interface|func|type)\s+([A-Za-z_]\w*)").unwrap()
+});
+
+/// Words too common in prose and code to say anything about relatedness
+const STOP_WORDS: &[&str] = &[
+    "this", "that", "with", "from", "have", "will", "what", "when", "where", "which", "there", "their",
+    "they", "them", "then", "than", "these", "those", "were", "been", "being", "into", "your", "about",
+    "would", "could", "should", "some", "more", "other", "only", "also", "just", "like", "here", "each",
+    "true", "false", "null", "none", "self", "return", "import", "export", "const", "static", "public",
+    "private", "function", "class", "struct", "impl", "async", "await", "default", "string", "number",
+    "value", "type", "else", "while", "break", "continue", "print", "println", "console", "unwrap",
+    "http", "https", "error",
+];
+
+/**
+ * A recent item chosen as context, with the reasons it was picked
+ */
+#[derive(Debug, Clone)]
+pub struct SelectedContext {
+    pub item: ClipboardItem,
+    pub reason: String,
+}
+
+/**
+ * Pick the recent items most related to the content, within a token budget
+ *
+ * Candidates are expected newest first; among equally related items the
+ * newer one wins. Items that do not fit the remaining budget whole are
+ * skipped rather than truncated.
+ */
+pub fn select_context(
+    content: &str,
+    code_language: Option<&str>,
+    candidates: &[ClipboardItem],
+    budget: usize,
+) -> Vec<SelectedContext> {
+    let source = Profile::new(content, code_language);
+
+    let mut scored: Vec<(f64, SelectedContext)> = candidates
+        .iter()
+        .filter(|item| item.content.trim() != content.trim() && !DETECTOR.is_sensitive(&item.content))
+        .filter_map(|item| {
+            let candidate = Profile::new(&item.content, item.code_language.as_deref());
+            let (score, reasons) = relate(&source, &candidate);
+            (score >= MIN_SCORE).then(|| {
+                (score, SelectedContext { item: item.clone(), reason: reasons.join("; ") })
+            })
+        })
+        .collect();
+
+    // Stable sort keeps newer items first among equal scores
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut remaining = budget;
+    let mut selected = Vec::new();
+    for (_, context) in scored {
+        if selected.len() == MAX_SELECTED_ITEMS {
+            break;
+        }
+        let tokens = estimate_tokens(&context.item.content);
+        if tokens <= remaining {
+            remaining -= tokens;
+            selected.push(context);
+        }
+    }
+
+    selected
+}
+
+/**
+ * What is compared between two pieces of content
+ */
+struct Profile<'a> {
+    content: &'a str,
+    language: Option<&'a str>,
+    keywords: HashSet<String>,
+    definitions: Vec<String>,
+    is_error: bool,
+}
+
+impl<'a> Profile<'a> {
+    fn new(content: &'a str, language: Option<&'a str>) -> Self {
+        Self {
+            content,
+            language,
+            keywords: keywords(content),
+            definitions: DEFINITION_REGEX
+                .captures_iter(content)
+                .map(|caps| caps[1].to_string())
+                .filter(|name| name.len() >= 4)
+                .collect(),
+            is_error: ERROR_REGEX.is_match(content),
+        }
+    }
+}
+
+/**
+ * Score how related a candidate is to the source, with the reasons
+ */
+fn relate(source: &Profile, candidate: &Profile) -> (f64, Vec<String>) {
+    let mut score = 0.0;
+    let mut reasons = Vec::new();
+
+    let mut shared: Vec<&String> = source.keywords.intersection(&candidate.keywords).collect();
+    if shared.len() >= MIN_SHARED_KEYWORDS {
+        // Overlap relative to the smaller item, so a short snippet inside a long file still counts
+        let smaller = source.keywords.len().min(candidate.keywords.len()).max(1);
+        score += shared.len() as f64 / smaller as f64;
+
+        shared.sort_by_key(|keyword| std::cmp::Reverse(keyword.len()));
+        let listed: Vec<&str> = shared.iter().take(REASON_KEYWORDS).map(|keyword| keyword.as_str()).collect();
+        reasons.push(format!("shares keywords: {}", listed.join(", ")));
+    }
+
+    if let (Some(source_language), Some(candidate_language)) = (source.language, candidate.language) {
+        if source_language == candidate_language {
+            score += SAME_LANGUAGE_SCORE;
+            reasons.push(format!("same language: {}", source_language));
+        }
+    }
+
+    if let Some(name) = mentioned_definition(source, candidate) {
+        score += TRACE_MENTION_SCORE;
+        reasons.push(format!("defines {} from the error", name));
+    } else if let Some(name) = mentioned_definition(candidate, source) {
+        score += TRACE_MENTION_SCORE;
+        reasons.push(format!("error mentions {}", name));
+    }
+
+    (score, reasons)
+}
+
+/**
+ * A function or type defined in the code that the error mentions
+ */
+fn mentioned_definition<'a>(error: &Profile, code: &'a Profile) -> Option<&'a str> {
+    if !error.is_error || code.is_error {
+        return None;
+    }
+
+    code.definitions
+        .iter()
+        .find(|name| {
+            Regex::new(&format!(r"\b{}\b", regex::escape(name)))
+                .map(|regex| regex.is_match(error.content))
+                .unwrap_or(false)
+        })
+        .map(|name| name.as_str())
+}
+
+/**
+ * Distinctive words of the content, lowercased
+ *
+ * camelCase and snake_case identifiers are also split into their parts, so
+ * `parseConfig` and `parse_config` relate.
+ */
+fn keywords(content: &str) -> HashSet<String> {
+    let mut keywords = HashSet::new();
+
+    for word in WORD_REGEX.find_iter(content).map(|word| word.as_str()) {
+        let mut parts: Vec<String> = Vec::new();
+        for segment in word.split('_').filter(|segment| !segment.is_empty()) {
+            let mut part = String::new();
+            for c in segment.chars() {
+                if c.is_uppercase() && part.chars().last().is_some_and(|last| last.is_lowercase()) {
+                    parts.push(std::mem::take(&mut part));
+                }
+                part.push(c);
+            }
+            parts.push(part);
+        }
+
+        keywords.insert(word.to_lowercase());
+        if parts.len() > 1 {
+            keywords.extend(parts.into_iter().map(|part| part.to_lowercase()));
+        }
+    }
+
+    keywords.retain(|word| word.len() >= 4 && !STOP_WORDS.contains(&word.as_str()));
+    keywords
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn item(content: &str, code_language: Option<&str>) -> ClipboardItem {
+        ClipboardItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            content: content.to_string(),
+            content_type: if code_language.is_some() { "code" } else { "text" }.to_string(),
+            code_language: code_language.map(str::to_string),
+            source_app: "test".to_string(),
+            timestamp: Utc::now(),
+            size: content.len(),
+            is_favorite: false,
+            tags: Vec::new(),
+            preview: content.to_string(),
+            title: None,
+        }
+    }
+
+    fn set(words: &[&str]) -> HashSet<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    const TRACE: &str = "TypeError: cannot read property of undefined\n    at parseConfig (src/config.js:12:5)";
+    const CODE: &str = "function parseConfig(text) {\n  return JSON.parse(text);\n}";
+
+    #[test]
+    fn keywords_split_identifiers() {
+        assert_eq!(
+            keywords("parseConfig(user_settings)"),
+            set(&["parseconfig", "parse", "config", "user_settings", "user", "settings"])
+        );
+        // Parts shorter than four characters are dropped
+        assert_eq!(keywords("getId"), set(&["getid"]));
+    }
+
+    #[test]
+    fn keywords_skip_stop_words() {
+        assert_eq!(keywords("This function returns the value"), set(&["returns"]));
+    }
+
+    #[test]
+    fn one_shared_keyword_is_not_enough() {
+        let source = Profile::new("deploy the frontend", None);
+
+        let (score, reasons) = relate(&source, &Profile::new("frontend tests pass", None));
+        assert_eq!(score, 0.0);
+        assert!(reasons.is_empty());
+
+        let (score, reasons) = relate(&source, &Profile::new("deploy frontend now", None));
+        assert_eq!(score, 1.0);
+        assert_eq!(reasons, vec!["shares keywords: frontend, deploy"]);
+    }
+
+    #[test]
+    fn same_language_adds_to_the_score() {
+        let (score, reasons) = relate(
+            &Profile::new("let total = 1;", Some("rust")),
+            &Profile::new("let count = 2;", Some("rust")),
+        );
+        assert_eq!(score, SAME_LANGUAGE_SCORE);
+        assert_eq!(reasons, vec!["same language: rust"]);
+    }
+
+    #[test]
+    fn errors_relate_to_the_code_they_mention() {
+        let trace = Profile::new(TRACE, None);
+        let code = Profile::new(CODE, Some("javascript"));
+
+        let (_, reasons) = relate(&trace, &code);
+        assert!(reasons.contains(&"defines parseConfig from the error".to_string()));
+
+        let (_, reasons) = relate(&code, &trace);
+        assert!(reasons.contains(&"error mentions parseConfig".to_string()));
+
+        // Two pieces of code never relate through definitions
+        let (_, reasons) = relate(&code, &Profile::new("parseConfig(input)", Some("javascript")));
+        assert!(!reasons.iter().any(|reason| reason.contains("error")));
+    }
+
+    #[test]
+    fn selects_related_items_only() {
+        let candidates = vec![
+            item(CODE, Some("javascript")),
+            item("Lunch at noon tomorrow?", None),
+            item(TRACE, None),
+        ];
+
+        let selected = select_context(TRACE, None, &candidates, 10_000);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].item.id, candidates[0].id);
+    }
+
+    #[test]
+    fn skips_items_over_the_budget() {
+        let large = format!("deploy frontend bundle {}", "unrelated words ".repeat(200));
+        let small = "deploy frontend";
+        let candidates = vec![item(&large, None), item(small, None)];
+
+        let selected = select_context("deploy frontend bundle", None, &candidates, estimate_tokens(small));
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].item.id, candidates[1].id);
+    }
+
+    #[test]
+    fn selects_at_most_three_items() {
+        let candidates: Vec<ClipboardItem> = (0..5)
+            .map(|n| item(&format!("deploy frontend build {}", n), None))
+            .collect();
+
+        let selected = select_context("deploy frontend", None, &candidates, 10_000);
+        let ids: Vec<&str> = selected.iter().map(|context| context.item.id.as_str()).collect();
+        assert_eq!(ids, vec![&candidates[0].id, &candidates[1].id, &candidates[2].id]);
+    }
+}
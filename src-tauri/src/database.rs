@@ -8,7 +8,11 @@ use chrono::{DateTime, Utc};
 use uuid::Uuid;
 use anyhow::{Result, Context};
 use std::path::PathBuf;
-use crate::{ClipboardItem, content_detection::ContentDetector};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use crate::feedback::{FeedbackAction, PresetKind, TransformationFeedback};
+use crate::metrics::AICallMetrics;
+use crate::{AITransformation, AITransformationRecord, AppSettings, ContextSource, ClipboardFilter, ClipboardItem, ConversationMessage, PromptTemplate, content_detection::ContentDetector};
 
 /**
  * Database manager for clipboard history
@@ -17,8 +21,13 @@ use crate::{ClipboardItem, content_detection::ContentDetector};
 pub struct ClipboardDatabase {
     pool: Pool<Sqlite>,
     content_detector: ContentDetector,
+    /// Most clipboard items kept; older ones are removed with their AI history
+    max_items: Arc<AtomicU32>,
 }
 
+/// Items kept until the max_history_items setting is applied
+pub const DEFAULT_MAX_HISTORY_ITEMS: u32 = 1000;
+
 impl ClipboardDatabase {
     /**
      * Initialize the database connection and create tables
@@ -48,7 +57,8 @@ impl ClipboardDatabase {
         // Try to connect to SQLite database with additional options for better compatibility
         let connect_options = sqlx::sqlite::SqliteConnectOptions::new()
             .filename(&db_path)
-            .create_if_missing(true);
+            .create_if_missing(true)
+            .foreign_keys(true);
             
         let pool = SqlitePool::connect_with(connect_options)
             .await
@@ -57,7 +67,8 @@ impl ClipboardDatabase {
         // Create tables if they don't exist
         let database = Self { 
             pool, 
-            content_detector: ContentDetector::new() 
+            content_detector: ContentDetector::new(),
+            max_items: Arc::new(AtomicU32::new(DEFAULT_MAX_HISTORY_ITEMS)),
         };
         database.create_tables().await?;
 
@@ -89,6 +100,9 @@ impl ClipboardDatabase {
         .await
         .context("Failed to create clipboard_items table")?;
 
+        // Short title generated by background enrichment
+        self.add_column_if_missing("clipboard_items", "title", "TEXT").await?;
+
         // Create index for faster timestamp queries
         sqlx::query(
             r#"
@@ -100,6 +114,226 @@ impl ClipboardDatabase {
         .await
         .context("Failed to create timestamp index")?;
 
+        // Persisted application settings (single JSON document per key)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS app_settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create app_settings table")?;
+
+        // Cache of AI results keyed by a hash of the full request
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS ai_cache (
+                cache_key TEXT PRIMARY KEY,
+                model TEXT NOT NULL,
+                result TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                last_accessed TEXT NOT NULL,
+                hit_count INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create ai_cache table")?;
+
+        // History of AI results, removed together with their source item
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS ai_transformations (
+                id TEXT PRIMARY KEY,
+                item_id TEXT REFERENCES clipboard_items(id) ON DELETE CASCADE,
+                prompt TEXT NOT NULL,
+                model TEXT NOT NULL,
+                title TEXT NOT NULL,
+                description TEXT NOT NULL,
+                result TEXT NOT NULL,
+                transformation_type TEXT NOT NULL,
+                confidence REAL NOT NULL,
+                latency_ms INTEGER NOT NULL,
+                is_applied BOOLEAN NOT NULL DEFAULT FALSE,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create ai_transformations table")?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_ai_transformations_item
+            ON ai_transformations(item_id, created_at DESC)
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create ai_transformations index")?;
+
+        self.add_column_if_missing("ai_transformations", "template_id", "TEXT").await?;
+        self.add_column_if_missing("ai_transformations", "context_sources", "TEXT NOT NULL DEFAULT '[]'").await?;
+        self.add_column_if_missing("ai_transformations", "provider", "TEXT").await?;
+        self.add_column_if_missing("ai_transformations", "language", "TEXT").await?;
+
+        // Reusable prompt templates, unique by name so imports can update in place
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS prompt_templates (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                description TEXT NOT NULL DEFAULT '',
+                body TEXT NOT NULL,
+                model TEXT,
+                temperature REAL,
+                max_tokens INTEGER,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create prompt_templates table")?;
+
+        // Embedding vectors for semantic search, stored as little-endian f32 blobs
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS item_embeddings (
+                item_id TEXT PRIMARY KEY REFERENCES clipboard_items(id) ON DELETE CASCADE,
+                model TEXT NOT NULL,
+                dimensions INTEGER NOT NULL,
+                vector BLOB NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create item_embeddings table")?;
+
+        // Usage and timing of every provider call
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS ai_call_metrics (
+                id TEXT PRIMARY KEY,
+                provider TEXT NOT NULL,
+                model TEXT NOT NULL,
+                prompt_tokens INTEGER NOT NULL,
+                completion_tokens INTEGER NOT NULL,
+                usage_estimated BOOLEAN NOT NULL,
+                time_to_first_token_ms INTEGER,
+                latency_ms INTEGER NOT NULL,
+                cost REAL,
+                success BOOLEAN NOT NULL,
+                error TEXT,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create ai_call_metrics table")?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_ai_call_metrics_created
+            ON ai_call_metrics(created_at)
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create ai_call_metrics index")?;
+
+        // Refinement conversations, one thread per clipboard item
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS ai_conversation_messages (
+                id TEXT PRIMARY KEY,
+                item_id TEXT NOT NULL REFERENCES clipboard_items(id) ON DELETE CASCADE,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                transformation_id TEXT,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create ai_conversation_messages table")?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_ai_conversation_messages_item
+            ON ai_conversation_messages(item_id, created_at)
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create ai_conversation_messages index")?;
+
+        // What the user did with results, used to rank presets per content type
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS transformation_feedback (
+                id TEXT PRIMARY KEY,
+                transformation_id TEXT NOT NULL,
+                action TEXT NOT NULL,
+                preset_kind TEXT,
+                preset_id TEXT,
+                content_type TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create transformation_feedback table")?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_transformation_feedback_content_type
+            ON transformation_feedback(content_type, created_at)
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create transformation_feedback index")?;
+
+        Ok(())
+    }
+
+    /**
+     * Add a column to an existing table if an older schema lacks it
+     *
+     * CREATE TABLE IF NOT EXISTS leaves tables from earlier versions untouched,
+     * so new columns are added here instead.
+     */
+    async fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let columns: Vec<String> = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(&self.pool)
+            .await
+            .context(format!("Failed to inspect {} table", table))?
+            .iter()
+            .map(|row| row.get::<String, _>("name"))
+            .collect();
+
+        if !columns.iter().any(|name| name == column) {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(&self.pool)
+                .await
+                .context(format!("Failed to add {}.{} column", table, column))?;
+            log::info!("Added {}.{} column", table, column);
+        }
+
         Ok(())
     }
 
@@ -110,7 +344,7 @@ impl ClipboardDatabase {
      * 1. Analyzes content to detect type (text, code, JSON, URL, etc.)
      * 2. Generates a preview for UI display
      * 3. Stores the item in SQLite database
-     * 4. Automatically removes old items to maintain 20-item limit
+     * 4. Automatically removes old items beyond the history limit
      * 
      * Parameters:
      * - content: The clipboard text content to store
@@ -138,6 +372,7 @@ impl ClipboardDatabase {
             is_favorite: false,
             tags: vec![],
             preview: content_info.preview.clone(),
+            title: None,
         };
 
         // DATABASE INSERTION
@@ -163,8 +398,8 @@ impl ClipboardDatabase {
         .await
         .context("Failed to insert clipboard item")?;
 
-        // AUTOMATIC CLEANUP - MAINTAIN 20 ITEM LIMIT
-        // Remove older items to keep only the most recent 20 clipboard entries
+        // AUTOMATIC CLEANUP - MAINTAIN THE HISTORY LIMIT
+        // Remove older items to keep only the most recent max_history_items entries
         // This ensures the database doesn't grow indefinitely
         self.cleanup_old_items().await?;
 
@@ -172,6 +407,56 @@ impl ClipboardDatabase {
         Ok(item)
     }
 
+    /**
+     * Store an item produced by the app itself, such as a digest
+     *
+     * Unlike captured items, the content type and title are given rather than detected.
+     */
+    pub async fn store_generated_item(&self, content: &str, content_type: &str, title: &str, tags: &[String]) -> Result<ClipboardItem> {
+        let content_info = self.content_detector.detect_content(content);
+
+        let item = ClipboardItem {
+            id: Uuid::new_v4().to_string(),
+            content: content.to_string(),
+            content_type: content_type.to_string(),
+            code_language: None,
+            source_app: "Wurdump".to_string(),
+            timestamp: Utc::now(),
+            size: content.len(),
+            is_favorite: false,
+            tags: tags.to_vec(),
+            preview: content_info.preview,
+            title: Some(title.to_string()),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO clipboard_items 
+            (id, content, content_type, code_language, source_app, timestamp, size, is_favorite, tags, preview, title)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&item.id)
+        .bind(&item.content)
+        .bind(&item.content_type)
+        .bind(&item.code_language)
+        .bind(&item.source_app)
+        .bind(item.timestamp.to_rfc3339())
+        .bind(item.size as i64)
+        .bind(item.is_favorite)
+        .bind(serde_json::to_string(&item.tags).unwrap_or_default())
+        .bind(&item.preview)
+        .bind(&item.title)
+        .execute(&self.pool)
+        .await
+        .context("Failed to insert generated item")?;
+
+        self.cleanup_old_items().await?;
+
+        log::info!("📦 Stored generated {} item: {} chars", item.content_type, item.size);
+        Ok(item)
+    }
+
     /**
      * Get clipboard history with pagination
      */
@@ -179,7 +464,7 @@ impl ClipboardDatabase {
         let rows = sqlx::query(
             r#"
             SELECT id, content, content_type, code_language, source_app, timestamp, 
-                   size, is_favorite, tags, preview
+                   size, is_favorite, tags, preview, title
             FROM clipboard_items 
             ORDER BY timestamp DESC 
             LIMIT ? OFFSET ?
@@ -191,29 +476,150 @@ impl ClipboardDatabase {
         .await
         .context("Failed to fetch clipboard history")?;
 
-        let mut items = Vec::new();
-        for row in rows {
-            let timestamp_str: String = row.get("timestamp");
-            let tags_str: String = row.get("tags");
-            
-            let item = ClipboardItem {
-                id: row.get("id"),
-                content: row.get("content"),
-                content_type: row.get("content_type"),
-                code_language: row.get("code_language"),
-                source_app: row.get("source_app"),
-                timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
-                    .context("Failed to parse timestamp")?
-                    .with_timezone(&Utc),
-                size: row.get::<i64, _>("size") as usize,
-                is_favorite: row.get("is_favorite"),
-                tags: serde_json::from_str(&tags_str).unwrap_or_default(),
-                preview: row.get("preview"),
-            };
-            items.push(item);
+        rows.iter().map(Self::row_to_clipboard_item).collect()
+    }
+
+    /**
+     * Find clipboard items matching a filter, newest first
+     */
+    pub async fn search_clipboard_items(&self, filter: &ClipboardFilter, limit: u32) -> Result<Vec<ClipboardItem>> {
+        // LIKE wildcards in the query are matched literally
+        let pattern = filter.query.as_ref().map(|query| {
+            let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{}%", escaped)
+        });
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, content, content_type, code_language, source_app, timestamp, 
+                   size, is_favorite, tags, preview, title
+            FROM clipboard_items 
+            WHERE (?1 IS NULL OR content LIKE ?1 ESCAPE '\' OR IFNULL(title, '') LIKE ?1 ESCAPE '\' OR tags LIKE ?1 ESCAPE '\')
+              AND (?2 IS NULL OR content_type = ?2)
+              AND (?3 IS NULL OR code_language = ?3)
+              AND (?4 IS NULL OR timestamp >= ?4)
+              AND (?5 IS NULL OR timestamp < ?5)
+              AND (?6 = 0 OR is_favorite = 1)
+            ORDER BY timestamp DESC 
+            LIMIT ?7
+            "#,
+        )
+        .bind(pattern)
+        .bind(&filter.content_type)
+        .bind(&filter.code_language)
+        .bind(filter.since.map(|since| since.to_rfc3339()))
+        .bind(filter.until.map(|until| until.to_rfc3339()))
+        .bind(filter.favorites_only)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to search clipboard items")?;
+
+        rows.iter().map(Self::row_to_clipboard_item).collect()
+    }
+
+    /**
+     * Get a single clipboard item by id
+     */
+    pub async fn get_clipboard_item(&self, item_id: &str) -> Result<Option<ClipboardItem>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, content, content_type, code_language, source_app, timestamp, 
+                   size, is_favorite, tags, preview, title
+            FROM clipboard_items 
+            WHERE id = ?
+            "#,
+        )
+        .bind(item_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch clipboard item")?;
+
+        row.as_ref().map(Self::row_to_clipboard_item).transpose()
+    }
+
+    /**
+     * Get up to `limit` items whose id starts with the given prefix
+     */
+    pub async fn find_items_by_id_prefix(&self, prefix: &str, limit: u32) -> Result<Vec<ClipboardItem>> {
+        let pattern = format!(
+            "{}%",
+            prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+        );
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, content, content_type, code_language, source_app, timestamp,
+                   size, is_favorite, tags, preview, title
+            FROM clipboard_items
+            WHERE id LIKE ? ESCAPE '\'
+            ORDER BY timestamp DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(pattern)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to look up clipboard items by id prefix")?;
+
+        rows.iter().map(Self::row_to_clipboard_item).collect()
+    }
+
+    fn row_to_clipboard_item(row: &sqlx::sqlite::SqliteRow) -> Result<ClipboardItem> {
+        let timestamp_str: String = row.get("timestamp");
+        let tags_str: String = row.get("tags");
+
+        Ok(ClipboardItem {
+            id: row.get("id"),
+            content: row.get("content"),
+            content_type: row.get("content_type"),
+            code_language: row.get("code_language"),
+            source_app: row.get("source_app"),
+            timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
+                .context("Failed to parse timestamp")?
+                .with_timezone(&Utc),
+            size: row.get::<i64, _>("size") as usize,
+            is_favorite: row.get("is_favorite"),
+            tags: serde_json::from_str(&tags_str).unwrap_or_default(),
+            preview: row.get("preview"),
+            title: row.get("title"),
+        })
+    }
+
+    /**
+     * Save the generated title and tags of a clipboard item
+     *
+     * Tags are merged with any the item already has.
+     */
+    pub async fn update_item_enrichment(&self, item_id: &str, title: &str, tags: &[String]) -> Result<()> {
+        let existing: Option<String> = sqlx::query_scalar("SELECT tags FROM clipboard_items WHERE id = ?")
+            .bind(item_id)
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to fetch clipboard item tags")?;
+
+        // The item may have been cleaned up while it was being enriched
+        let Some(existing) = existing else {
+            return Ok(());
+        };
+
+        let mut merged: Vec<String> = serde_json::from_str(&existing).unwrap_or_default();
+        for tag in tags {
+            if !merged.contains(tag) {
+                merged.push(tag.clone());
+            }
         }
 
-        Ok(items)
+        sqlx::query("UPDATE clipboard_items SET title = ?, tags = ? WHERE id = ?")
+            .bind(title)
+            .bind(serde_json::to_string(&merged)?)
+            .bind(item_id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to save clipboard item enrichment")?;
+
+        Ok(())
     }
 
     /**
@@ -259,33 +665,45 @@ impl ClipboardDatabase {
     }
 
     /**
-     * Cleanup old items to maintain only the latest 20 clipboard entries
+     * Set how many clipboard items are kept (the max_history_items setting)
+     *
+     * Removing an item also removes its transformation history, conversation
+     * and embedding, so the limit bounds how far back those reach too.
+     * Takes effect with the next stored item.
+     */
+    pub fn set_max_history_items(&self, max_items: u32) {
+        self.max_items.store(max_items.max(1), Ordering::Relaxed);
+    }
+
+    /**
+     * Cleanup old items to maintain only the latest max_history_items entries
      * 
-     * This method implements the core requirement of keeping only the most recent
-     * 20 clipboard items. It works by:
-     * 1. Finding the 20 most recent items (by timestamp)
-     * 2. Deleting all items that are NOT in that top 20 list
+     * This works by:
+     * 1. Finding the most recent items (by timestamp) up to the limit
+     * 2. Deleting all items that are NOT in that list
      * 
      * This is called automatically after every new item insertion to ensure
-     * the database never grows beyond the 20-item limit.
+     * the database never grows beyond the history limit.
      */
     async fn cleanup_old_items(&self) -> Result<()> {
+        let max_items = self.max_items.load(Ordering::Relaxed);
         let deleted = sqlx::query(
             r#"
             DELETE FROM clipboard_items 
             WHERE id NOT IN (
                 SELECT id FROM clipboard_items 
                 ORDER BY timestamp DESC 
-                LIMIT 20
+                LIMIT ?
             )
             "#,
         )
+        .bind(max_items as i64)
         .execute(&self.pool)
         .await
         .context("Failed to cleanup old items")?;
 
         if deleted.rows_affected() > 0 {
-            log::debug!("🧹 Cleaned up {} old clipboard items to maintain 20-item limit", deleted.rows_affected());
+            log::debug!("🧹 Cleaned up {} old clipboard items to maintain {}-item limit", deleted.rows_affected(), max_items);
         }
 
         Ok(())
@@ -302,4 +720,738 @@ impl ClipboardDatabase {
 
         Ok(count as u32)
     }
+
+    /**
+     * Load persisted application settings, if any were saved
+     */
+    pub async fn load_settings(&self) -> Result<Option<AppSettings>> {
+        let value: Option<String> = sqlx::query_scalar("SELECT value FROM app_settings WHERE key = 'app'")
+            .fetch_optional(&self.pool)
+            .await
+            .context("Failed to load settings")?;
+
+        match value {
+            Some(json) => Ok(Some(serde_json::from_str(&json).context("Failed to parse saved settings")?)),
+            None => Ok(None),
+        }
+    }
+
+    /**
+     * Persist application settings
+     */
+    pub async fn save_settings(&self, settings: &AppSettings) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO app_settings (key, value) VALUES ('app', ?)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value
+            "#,
+        )
+        .bind(serde_json::to_string(settings)?)
+        .execute(&self.pool)
+        .await
+        .context("Failed to save settings")?;
+
+        Ok(())
+    }
+
+    /**
+     * Look up a cached AI result
+     *
+     * Expired entries are treated as missing. A hit refreshes the entry's
+     * last-access time so size-based eviction drops the least recently used first.
+     */
+    pub async fn get_cached_ai_result(&self, cache_key: &str) -> Result<Option<Vec<AITransformation>>> {
+        let now = Utc::now().to_rfc3339();
+        let result: Option<String> = sqlx::query_scalar(
+            "SELECT result FROM ai_cache WHERE cache_key = ? AND expires_at > ?"
+        )
+        .bind(cache_key)
+        .bind(&now)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to read AI cache")?;
+
+        let Some(result) = result else {
+            return Ok(None);
+        };
+
+        sqlx::query("UPDATE ai_cache SET last_accessed = ?, hit_count = hit_count + 1 WHERE cache_key = ?")
+            .bind(&now)
+            .bind(cache_key)
+            .execute(&self.pool)
+            .await
+            .context("Failed to update AI cache entry")?;
+
+        Ok(Some(serde_json::from_str(&result).context("Failed to parse cached AI result")?))
+    }
+
+    /**
+     * Store an AI result in the cache and enforce the TTL and size limits
+     *
+     * Parameters:
+     * - ttl_hours: How long the entry stays valid
+     * - max_entries: Maximum number of entries kept after insertion
+     * - max_bytes: Maximum total size of cached results after insertion
+     */
+    pub async fn store_cached_ai_result(
+        &self,
+        cache_key: &str,
+        model: &str,
+        transformations: &[AITransformation],
+        ttl_hours: u32,
+        max_entries: u32,
+        max_bytes: u64,
+    ) -> Result<()> {
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::hours(ttl_hours as i64);
+        let result = serde_json::to_string(transformations)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO ai_cache (cache_key, model, result, size, created_at, expires_at, last_accessed, hit_count)
+            VALUES (?, ?, ?, ?, ?, ?, ?, 0)
+            ON CONFLICT(cache_key) DO UPDATE SET
+                model = excluded.model,
+                result = excluded.result,
+                size = excluded.size,
+                created_at = excluded.created_at,
+                expires_at = excluded.expires_at,
+                last_accessed = excluded.last_accessed
+            "#,
+        )
+        .bind(cache_key)
+        .bind(model)
+        .bind(&result)
+        .bind(result.len() as i64)
+        .bind(now.to_rfc3339())
+        .bind(expires_at.to_rfc3339())
+        .bind(now.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .context("Failed to store AI cache entry")?;
+
+        self.evict_ai_cache(max_entries, max_bytes).await
+    }
+
+    /**
+     * Drop expired cache entries, then least recently used ones until within limits
+     */
+    async fn evict_ai_cache(&self, max_entries: u32, max_bytes: u64) -> Result<()> {
+        sqlx::query("DELETE FROM ai_cache WHERE expires_at <= ?")
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await
+            .context("Failed to remove expired AI cache entries")?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM ai_cache
+            WHERE cache_key NOT IN (
+                SELECT cache_key FROM ai_cache
+                ORDER BY last_accessed DESC
+                LIMIT ?
+            )
+            "#,
+        )
+        .bind(max_entries as i64)
+        .execute(&self.pool)
+        .await
+        .context("Failed to trim AI cache entries")?;
+
+        // Walk entries from most to least recently used and drop everything past the byte budget
+        let rows = sqlx::query("SELECT cache_key, size FROM ai_cache ORDER BY last_accessed DESC")
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to read AI cache sizes")?;
+
+        let mut total: u64 = 0;
+        for row in rows {
+            total += row.get::<i64, _>("size") as u64;
+            if total > max_bytes {
+                let cache_key: String = row.get("cache_key");
+                sqlx::query("DELETE FROM ai_cache WHERE cache_key = ?")
+                    .bind(&cache_key)
+                    .execute(&self.pool)
+                    .await
+                    .context("Failed to evict AI cache entry")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Remove every cached AI result
+     */
+    pub async fn clear_ai_cache(&self) -> Result<()> {
+        sqlx::query("DELETE FROM ai_cache")
+            .execute(&self.pool)
+            .await
+            .context("Failed to clear AI cache")?;
+
+        log::info!("Cleared AI result cache");
+        Ok(())
+    }
+
+    /**
+     * Record an AI transformation in history
+     */
+    pub async fn store_ai_transformation(&self, record: &AITransformationRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO ai_transformations
+            (id, item_id, prompt, model, title, description, result, transformation_type,
+             confidence, latency_ms, is_applied, template_id, context_sources, provider, language, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&record.id)
+        .bind(&record.item_id)
+        .bind(&record.prompt)
+        .bind(&record.model)
+        .bind(&record.transformation.title)
+        .bind(&record.transformation.description)
+        .bind(&record.transformation.result)
+        .bind(&record.transformation.transformation_type)
+        .bind(record.transformation.confidence)
+        .bind(record.latency_ms as i64)
+        .bind(record.transformation.is_applied)
+        .bind(&record.template_id)
+        .bind(serde_json::to_string(&record.context_sources)?)
+        .bind(&record.transformation.provider)
+        .bind(&record.transformation.language)
+        .bind(record.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .context("Failed to store AI transformation")?;
+
+        Ok(())
+    }
+
+    /**
+     * Get the AI transformations recorded for a clipboard item, newest first
+     */
+    pub async fn get_item_transformations(&self, item_id: &str) -> Result<Vec<AITransformationRecord>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, item_id, prompt, model, title, description, result, transformation_type,
+                   confidence, latency_ms, is_applied, template_id, context_sources, provider, language, created_at
+            FROM ai_transformations
+            WHERE item_id = ?
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(item_id)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch AI transformations")?;
+
+        rows.iter().map(Self::row_to_transformation_record).collect()
+    }
+
+    /**
+     * Get a single recorded AI transformation
+     */
+    pub async fn get_ai_transformation(&self, transformation_id: &str) -> Result<Option<AITransformationRecord>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, item_id, prompt, model, title, description, result, transformation_type,
+                   confidence, latency_ms, is_applied, template_id, context_sources, provider, language, created_at
+            FROM ai_transformations
+            WHERE id = ?
+            "#,
+        )
+        .bind(transformation_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch AI transformation")?;
+
+        row.as_ref().map(Self::row_to_transformation_record).transpose()
+    }
+
+    /**
+     * Mark a recorded AI transformation as applied
+     */
+    pub async fn mark_transformation_applied(&self, transformation_id: &str) -> Result<()> {
+        sqlx::query("UPDATE ai_transformations SET is_applied = TRUE WHERE id = ?")
+            .bind(transformation_id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to mark AI transformation as applied")?;
+
+        Ok(())
+    }
+
+    fn row_to_transformation_record(row: &sqlx::sqlite::SqliteRow) -> Result<AITransformationRecord> {
+        let id: String = row.get("id");
+        let created_at: String = row.get("created_at");
+        let context_sources: String = row.get("context_sources");
+        let context_sources: Vec<ContextSource> = serde_json::from_str(&context_sources).unwrap_or_default();
+
+        Ok(AITransformationRecord {
+            id: id.clone(),
+            item_id: row.get("item_id"),
+            prompt: row.get("prompt"),
+            model: row.get("model"),
+            transformation: AITransformation {
+                id,
+                title: row.get("title"),
+                description: row.get("description"),
+                result: row.get("result"),
+                confidence: row.get("confidence"),
+                is_applied: row.get("is_applied"),
+                transformation_type: row.get("transformation_type"),
+                is_cached: false,
+                provider: row.get("provider"),
+                model: Some(row.get("model")),
+                context_sources: context_sources.clone(),
+                language: row.get("language"),
+            },
+            latency_ms: row.get::<i64, _>("latency_ms") as u64,
+            template_id: row.get("template_id"),
+            context_sources,
+            created_at: DateTime::parse_from_rfc3339(&created_at)
+                .context("Failed to parse timestamp")?
+                .with_timezone(&Utc),
+        })
+    }
+
+    /**
+     * Get items that have no embedding from the given model yet, newest first
+     *
+     * Indexing works through this list, so it resumes where it left off
+     * after a restart and re-embeds items when the embedding model changes.
+     */
+    pub async fn get_items_without_embedding(&self, model: &str, limit: u32) -> Result<Vec<ClipboardItem>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT c.id, c.content, c.content_type, c.code_language, c.source_app, c.timestamp,
+                   c.size, c.is_favorite, c.tags, c.preview, c.title
+            FROM clipboard_items c
+            LEFT JOIN item_embeddings e ON e.item_id = c.id AND e.model = ?
+            WHERE e.item_id IS NULL
+            ORDER BY c.timestamp DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(model)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch items without embeddings")?;
+
+        rows.iter().map(Self::row_to_clipboard_item).collect()
+    }
+
+    /**
+     * Store (or replace) the embedding of a clipboard item
+     */
+    pub async fn store_item_embedding(&self, item_id: &str, model: &str, vector: &[f32]) -> Result<()> {
+        let blob: Vec<u8> = vector.iter().flat_map(|value| value.to_le_bytes()).collect();
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO item_embeddings (item_id, model, dimensions, vector, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(item_id)
+        .bind(model)
+        .bind(vector.len() as i64)
+        .bind(blob)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .context("Failed to store item embedding")?;
+
+        Ok(())
+    }
+
+    /**
+     * Get all clipboard items with their embedding from the given model, if any
+     */
+    pub async fn get_items_with_embeddings(&self, model: &str) -> Result<Vec<(ClipboardItem, Option<Vec<f32>>)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT c.id, c.content, c.content_type, c.code_language, c.source_app, c.timestamp,
+                   c.size, c.is_favorite, c.tags, c.preview, c.title, e.vector
+            FROM clipboard_items c
+            LEFT JOIN item_embeddings e ON e.item_id = c.id AND e.model = ?
+            ORDER BY c.timestamp DESC
+            "#,
+        )
+        .bind(model)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch item embeddings")?;
+
+        rows.iter()
+            .map(|row| {
+                let item = Self::row_to_clipboard_item(row)?;
+                let vector = row.get::<Option<Vec<u8>>, _>("vector").map(|blob| {
+                    blob.chunks_exact(4)
+                        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                        .collect()
+                });
+                Ok((item, vector))
+            })
+            .collect()
+    }
+
+    /**
+     * Store the metrics of a provider call
+     */
+    pub async fn store_ai_call_metrics(&self, metrics: &AICallMetrics) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO ai_call_metrics
+            (id, provider, model, prompt_tokens, completion_tokens, usage_estimated,
+             time_to_first_token_ms, latency_ms, cost, success, error, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&metrics.id)
+        .bind(&metrics.provider)
+        .bind(&metrics.model)
+        .bind(metrics.prompt_tokens as i64)
+        .bind(metrics.completion_tokens as i64)
+        .bind(metrics.usage_estimated)
+        .bind(metrics.time_to_first_token_ms.map(|ms| ms as i64))
+        .bind(metrics.latency_ms as i64)
+        .bind(metrics.cost)
+        .bind(metrics.success)
+        .bind(&metrics.error)
+        .bind(metrics.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .context("Failed to store AI call metrics")?;
+
+        Ok(())
+    }
+
+    /**
+     * Get the provider calls made since the given time, oldest first
+     */
+    pub async fn get_ai_call_metrics(&self, since: DateTime<Utc>) -> Result<Vec<AICallMetrics>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, provider, model, prompt_tokens, completion_tokens, usage_estimated,
+                   time_to_first_token_ms, latency_ms, cost, success, error, created_at
+            FROM ai_call_metrics
+            WHERE created_at >= ?
+            ORDER BY created_at ASC
+            "#,
+        )
+        .bind(since.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch AI call metrics")?;
+
+        rows.iter()
+            .map(|row| {
+                let created_at: String = row.get("created_at");
+                Ok(AICallMetrics {
+                    id: row.get("id"),
+                    provider: row.get("provider"),
+                    model: row.get("model"),
+                    prompt_tokens: row.get::<i64, _>("prompt_tokens") as u32,
+                    completion_tokens: row.get::<i64, _>("completion_tokens") as u32,
+                    usage_estimated: row.get("usage_estimated"),
+                    time_to_first_token_ms: row.get::<Option<i64>, _>("time_to_first_token_ms").map(|ms| ms as u64),
+                    latency_ms: row.get::<i64, _>("latency_ms") as u64,
+                    cost: row.get("cost"),
+                    success: row.get("success"),
+                    error: row.get("error"),
+                    created_at: DateTime::parse_from_rfc3339(&created_at)
+                        .context("Failed to parse timestamp")?
+                        .with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    /**
+     * Append a message to a clipboard item's conversation
+     */
+    pub async fn append_conversation_message(&self, message: &ConversationMessage) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO ai_conversation_messages (id, item_id, role, content, transformation_id, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&message.id)
+        .bind(&message.item_id)
+        .bind(&message.role)
+        .bind(&message.content)
+        .bind(&message.transformation_id)
+        .bind(message.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .context("Failed to store conversation message")?;
+
+        Ok(())
+    }
+
+    /**
+     * Get the conversation about a clipboard item, oldest message first
+     */
+    pub async fn get_conversation(&self, item_id: &str) -> Result<Vec<ConversationMessage>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, item_id, role, content, transformation_id, created_at
+            FROM ai_conversation_messages
+            WHERE item_id = ?
+            ORDER BY created_at ASC, rowid ASC
+            "#,
+        )
+        .bind(item_id)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch conversation")?;
+
+        rows.iter().map(Self::row_to_conversation_message).collect()
+    }
+
+    /**
+     * Get a single conversation message
+     */
+    pub async fn get_conversation_message(&self, message_id: &str) -> Result<Option<ConversationMessage>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, item_id, role, content, transformation_id, created_at
+            FROM ai_conversation_messages
+            WHERE id = ?
+            "#,
+        )
+        .bind(message_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch conversation message")?;
+
+        row.as_ref().map(Self::row_to_conversation_message).transpose()
+    }
+
+    /**
+     * Delete the conversation about a clipboard item
+     */
+    pub async fn clear_conversation(&self, item_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM ai_conversation_messages WHERE item_id = ?")
+            .bind(item_id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to clear conversation")?;
+
+        Ok(())
+    }
+
+    /**
+     * Store feedback on a transformation
+     */
+    pub async fn store_feedback(&self, feedback: &TransformationFeedback) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO transformation_feedback
+            (id, transformation_id, action, preset_kind, preset_id, content_type, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&feedback.id)
+        .bind(&feedback.transformation_id)
+        .bind(feedback.action.as_str())
+        .bind(feedback.preset_kind.map(|kind| kind.as_str()))
+        .bind(&feedback.preset_id)
+        .bind(&feedback.content_type)
+        .bind(feedback.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .context("Failed to store feedback")?;
+
+        Ok(())
+    }
+
+    /**
+     * Get the feedback given on results for a content type since the given time
+     */
+    pub async fn get_feedback(&self, content_type: &str, since: DateTime<Utc>) -> Result<Vec<TransformationFeedback>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, transformation_id, action, preset_kind, preset_id, content_type, created_at
+            FROM transformation_feedback
+            WHERE content_type = ? AND created_at >= ?
+            ORDER BY created_at ASC
+            "#,
+        )
+        .bind(content_type)
+        .bind(since.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch feedback")?;
+
+        let mut feedback = Vec::new();
+        for row in &rows {
+            let action: String = row.get("action");
+            let Some(action) = FeedbackAction::parse(&action) else {
+                log::warn!("Skipping feedback with unknown action: {}", action);
+                continue;
+            };
+            let preset_kind: Option<String> = row.get("preset_kind");
+            let created_at: String = row.get("created_at");
+
+            feedback.push(TransformationFeedback {
+                id: row.get("id"),
+                transformation_id: row.get("transformation_id"),
+                action,
+                preset_kind: preset_kind.as_deref().and_then(PresetKind::parse),
+                preset_id: row.get("preset_id"),
+                content_type: row.get("content_type"),
+                created_at: DateTime::parse_from_rfc3339(&created_at)
+                    .context("Failed to parse timestamp")?
+                    .with_timezone(&Utc),
+            });
+        }
+
+        Ok(feedback)
+    }
+
+    fn row_to_conversation_message(row: &sqlx::sqlite::SqliteRow) -> Result<ConversationMessage> {
+        let created_at: String = row.get("created_at");
+
+        Ok(ConversationMessage {
+            id: row.get("id"),
+            item_id: row.get("item_id"),
+            role: row.get("role"),
+            content: row.get("content"),
+            transformation_id: row.get("transformation_id"),
+            created_at: DateTime::parse_from_rfc3339(&created_at)
+                .context("Failed to parse timestamp")?
+                .with_timezone(&Utc),
+        })
+    }
+
+    /**
+     * List all prompt templates ordered by name
+     */
+    pub async fn list_prompt_templates(&self) -> Result<Vec<PromptTemplate>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, name, description, body, model, temperature, max_tokens, created_at, updated_at
+            FROM prompt_templates
+            ORDER BY name COLLATE NOCASE
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch prompt templates")?;
+
+        rows.iter().map(Self::row_to_prompt_template).collect()
+    }
+
+    /**
+     * Get a single prompt template
+     */
+    pub async fn get_prompt_template(&self, template_id: &str) -> Result<Option<PromptTemplate>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, name, description, body, model, temperature, max_tokens, created_at, updated_at
+            FROM prompt_templates
+            WHERE id = ?
+            "#,
+        )
+        .bind(template_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch prompt template")?;
+
+        row.as_ref().map(Self::row_to_prompt_template).transpose()
+    }
+
+    /**
+     * Insert or update a prompt template by id
+     */
+    pub async fn save_prompt_template(&self, template: &PromptTemplate) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO prompt_templates
+            (id, name, description, body, model, temperature, max_tokens, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                description = excluded.description,
+                body = excluded.body,
+                model = excluded.model,
+                temperature = excluded.temperature,
+                max_tokens = excluded.max_tokens,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(&template.id)
+        .bind(&template.name)
+        .bind(&template.description)
+        .bind(&template.body)
+        .bind(&template.model)
+        .bind(template.temperature)
+        .bind(template.max_tokens.map(|tokens| tokens as i64))
+        .bind(template.created_at.to_rfc3339())
+        .bind(template.updated_at.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .context("Failed to save prompt template")?;
+
+        Ok(())
+    }
+
+    /**
+     * Find a prompt template by its (unique) name
+     */
+    pub async fn find_prompt_template_by_name(&self, name: &str) -> Result<Option<PromptTemplate>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, name, description, body, model, temperature, max_tokens, created_at, updated_at
+            FROM prompt_templates
+            WHERE name = ?
+            "#,
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to fetch prompt template")?;
+
+        row.as_ref().map(Self::row_to_prompt_template).transpose()
+    }
+
+    /**
+     * Delete a prompt template
+     */
+    pub async fn delete_prompt_template(&self, template_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM prompt_templates WHERE id = ?")
+            .bind(template_id)
+            .execute(&self.pool)
+            .await
+            .context("Failed to delete prompt template")?;
+
+        log::info!("Deleted prompt template: {}", template_id);
+        Ok(())
+    }
+
+    fn row_to_prompt_template(row: &sqlx::sqlite::SqliteRow) -> Result<PromptTemplate> {
+        let created_at: String = row.get("created_at");
+        let updated_at: String = row.get("updated_at");
+
+        Ok(PromptTemplate {
+            id: row.get("id"),
+            name: row.get("name"),
+            description: row.get("description"),
+            body: row.get("body"),
+            model: row.get("model"),
+            temperature: row.get("temperature"),
+            max_tokens: row.get::<Option<i64>, _>("max_tokens").map(|tokens| tokens as u32),
+            created_at: DateTime::parse_from_rfc3339(&created_at)
+                .context("Failed to parse timestamp")?
+                .with_timezone(&Utc),
+            updated_at: DateTime::parse_from_rfc3339(&updated_at)
+                .context("Failed to parse timestamp")?
+                .with_timezone(&Utc),
+        })
+    }
 }
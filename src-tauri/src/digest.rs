@@ -0,0 +1,356 @@
+/**
+ * Daily clipboard digest for Wurdump
+ *
+ * Once a day, after the configured time, gathers the day's clipboard items,
+ * groups them by content type and asks the local model for a short summary
+ * of the topics, code and links. The digest is stored as its own clipboard
+ * item and can also be written as a Markdown file. Sensitive items never
+ * make it into the prompt.
+ */
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use once_cell::sync::Lazy;
+use tauri::{AppHandle, Emitter, Manager};
+use crate::ai::{self, AIRequest};
+use crate::content_detection::ContentDetector;
+use crate::database::ClipboardDatabase;
+use crate::output_validation::OutputFormat;
+use crate::providers;
+use crate::redaction::Redactor;
+use crate::{AppSettings, AppState, ClipboardFilter, ClipboardItem};
+
+/// Time of day the digest is generated until the user picks another
+pub const DEFAULT_DIGEST_TIME: &str = "18:00";
+
+/// Content type of stored digest items
+pub const DIGEST_CONTENT_TYPE: &str = "digest";
+
+/// How often the scheduler checks whether the digest is due
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Most items considered for one digest
+const MAX_DIGEST_ITEMS: u32 = 200;
+
+/// Characters of each item included in the prompt
+const EXCERPT_CHARS: usize = 240;
+
+/// Favorites with any of these tags are treated as stored secrets
+const SECRET_TAGS: &[&str] = &["secret", "password", "private", "sensitive", "credentials"];
+
+const DIGEST_SYSTEM_PROMPT: &str = "You write concise end-of-day summaries of what a developer copied to their clipboard. Only mention what appears in the activity you are given.";
+
+const DIGEST_INSTRUCTION: &str = "Write a short Markdown digest of this clipboard activity. Summarize the main topics, describe the code that was worked on (languages and what it does), and list the links collected. Use short sections with bullet points and do not add a title.";
+
+static DETECTOR: Lazy<ContentDetector> = Lazy::new(ContentDetector::new);
+
+/**
+ * Start the daily digest scheduler
+ *
+ * Checks periodically and generates the digest once the configured time has
+ * passed, unless one already exists for today. Each day is attempted once;
+ * a failed digest can still be generated manually.
+ */
+pub fn spawn_scheduler(app: AppHandle, database: ClipboardDatabase) {
+    tokio::spawn(async move {
+        let mut last_attempt: Option<NaiveDate> = None;
+
+        loop {
+            tokio::time::sleep(DIGEST_CHECK_INTERVAL).await;
+
+            let settings = app.state::<AppState>().settings.lock().unwrap().clone();
+            let now = Local::now();
+            let today = now.date_naive();
+
+            if !settings.digest_enabled || !settings.ai_enabled || last_attempt == Some(today) {
+                continue;
+            }
+            let Some(digest_time) = parse_digest_time(&settings.digest_time) else {
+                continue;
+            };
+            if now.time() < digest_time {
+                continue;
+            }
+
+            last_attempt = Some(today);
+
+            match digest_exists(&database, today).await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => {
+                    log::warn!("Failed to check for an existing digest: {}", e);
+                    continue;
+                }
+            }
+
+            match generate_digest(&app, &database, &settings, today).await {
+                Ok(item) => log::info!("📰 Generated clipboard digest for {}: {}", today, item.id),
+                Err(e) => log::warn!("⚠️ Clipboard digest for {} failed: {}", today, e),
+            }
+        }
+    });
+}
+
+/**
+ * Generate, store and optionally export the digest for a day
+ *
+ * Emits 'clipboard-digest-created' with the stored item.
+ */
+pub async fn generate_digest(
+    app: &AppHandle,
+    database: &ClipboardDatabase,
+    settings: &AppSettings,
+    date: NaiveDate,
+) -> Result<ClipboardItem, String> {
+    let (start, end) = day_bounds(date)?;
+
+    let filter = ClipboardFilter { since: Some(start), until: Some(end), ..Default::default() };
+    let items: Vec<ClipboardItem> = database.search_clipboard_items(&filter, MAX_DIGEST_ITEMS).await
+        .map_err(|e| format!("Failed to load clipboard items: {}", e))?
+        .into_iter()
+        .filter(|item| item.content_type != DIGEST_CONTENT_TYPE)
+        .filter(|item| !is_excluded(item))
+        .collect();
+
+    if items.is_empty() {
+        return Err(format!("No clipboard activity to summarize for {}", date));
+    }
+
+    let activity = describe_activity(&items, date);
+
+    let mut redactor = Redactor::new();
+    let content = if settings.ai_redaction_enabled {
+        redactor.redact(&activity)
+    } else {
+        activity
+    };
+
+    let providers = providers::provider_chain(settings, None);
+    let request = AIRequest {
+        content,
+        instruction: DIGEST_INSTRUCTION.to_string(),
+        has_custom_instruction: true,
+        instruction_from_template: false,
+        context_items: Vec::new(),
+        system_prompt: DIGEST_SYSTEM_PROMPT.to_string(),
+        model: providers[0].model.clone(),
+        temperature: 0.3,
+        max_tokens: settings.ai_max_tokens,
+        context_window: settings.ai_context_window,
+        output_format: OutputFormat::Text,
+        history: Vec::new(),
+        providers,
+        timeout: providers::request_timeout(settings),
+    };
+
+    let queue = Arc::clone(&app.state::<AppState>().ai_queue);
+    let summary = ai::run_background_request(&queue, format!("digest-{}", date), &request).await?;
+
+    let title = format!("Clipboard digest for {}", date);
+    let markdown = format!("# {}\n\n{}\n", title, redactor.restore(summary.trim()));
+
+    let item = database
+        .store_generated_item(&markdown, DIGEST_CONTENT_TYPE, &title, &[DIGEST_CONTENT_TYPE.to_string()])
+        .await
+        .map_err(|e| format!("Failed to store digest: {}", e))?;
+
+    if !settings.digest_output_dir.trim().is_empty() {
+        match write_markdown(&settings.digest_output_dir, date, &markdown).await {
+            Ok(path) => log::info!("📝 Wrote clipboard digest to {}", path.display()),
+            Err(e) => log::warn!("Failed to write digest file: {}", e),
+        }
+    }
+
+    if let Err(e) = app.emit("clipboard-digest-created", &item) {
+        log::warn!("Failed to emit digest event: {}", e);
+    }
+
+    Ok(item)
+}
+
+/**
+ * Whether a digest was already stored on the given day
+ */
+async fn digest_exists(database: &ClipboardDatabase, date: NaiveDate) -> Result<bool, String> {
+    let (start, end) = day_bounds(date)?;
+    let filter = ClipboardFilter {
+        content_type: Some(DIGEST_CONTENT_TYPE.to_string()),
+        since: Some(start),
+        until: Some(end),
+        ..Default::default()
+    };
+
+    database.search_clipboard_items(&filter, 1).await
+        .map(|items| !items.is_empty())
+        .map_err(|e| e.to_string())
+}
+
+/**
+ * Items that must not be summarized: anything sensitive, and favorites kept as secrets
+ */
+fn is_excluded(item: &ClipboardItem) -> bool {
+    if DETECTOR.is_sensitive(&item.content) {
+        return true;
+    }
+
+    item.is_favorite
+        && item
+            .tags
+            .iter()
+            .any(|tag| SECRET_TAGS.contains(&tag.to_lowercase().as_str()))
+}
+
+/**
+ * Describe the day's items grouped by content type, largest group first
+ */
+fn describe_activity(items: &[ClipboardItem], date: NaiveDate) -> String {
+    let mut groups: Vec<(&str, Vec<&ClipboardItem>)> = Vec::new();
+    for item in items {
+        match groups.iter_mut().find(|(content_type, _)| *content_type == item.content_type) {
+            Some((_, group)) => group.push(item),
+            None => groups.push((&item.content_type, vec![item])),
+        }
+    }
+    groups.sort_by_key(|(_, group)| std::cmp::Reverse(group.len()));
+
+    let mut activity = format!("Clipboard activity on {} ({} items)\n", date, items.len());
+    for (content_type, group) in groups {
+        activity.push_str(&format!("\n## {} ({})\n", content_type, group.len()));
+        for item in group {
+            let excerpt = excerpt(&item.content);
+            match &item.code_language {
+                Some(language) => activity.push_str(&format!("- [{}] {}\n", language, excerpt)),
+                None => activity.push_str(&format!("- {}\n", excerpt)),
+            }
+        }
+    }
+
+    activity
+}
+
+/**
+ * Start of the content on a single line
+ */
+fn excerpt(content: &str) -> String {
+    let collapsed = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.chars().count() > EXCERPT_CHARS {
+        format!("{}…", collapsed.chars().take(EXCERPT_CHARS).collect::<String>())
+    } else {
+        collapsed
+    }
+}
+
+/**
+ * Start and end of a local calendar day in UTC
+ */
+fn day_bounds(date: NaiveDate) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+    let local_midnight = |date: NaiveDate| {
+        Local
+            .from_local_datetime(&date.and_time(NaiveTime::MIN))
+            .earliest()
+            .map(|time| time.with_timezone(&Utc))
+            .ok_or_else(|| format!("Invalid local date: {}", date))
+    };
+
+    let next_day = date.succ_opt().ok_or_else(|| format!("Invalid date: {}", date))?;
+    Ok((local_midnight(date)?, local_midnight(next_day)?))
+}
+
+/**
+ * Parse the configured digest time ("HH:MM")
+ */
+pub fn parse_digest_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()
+}
+
+/**
+ * Write the digest to <dir>/wurdump-digest-<date>.md
+ */
+async fn write_markdown(directory: &str, date: NaiveDate, markdown: &str) -> Result<PathBuf, String> {
+    let directory = match (directory.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(directory),
+    };
+
+    tokio::fs::create_dir_all(&directory).await.map_err(|e| e.to_string())?;
+
+    let path = directory.join(format!("wurdump-digest-{}.md", date));
+    tokio::fs::write(&path, markdown).await.map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    fn item(content_type: &str, code_language: Option<&str>, content: &str) -> ClipboardItem {
+        ClipboardItem {
+            id: uuid::Uuid::new_v4().to_string(),
+            content: content.to_string(),
+            content_type: content_type.to_string(),
+            code_language: code_language.map(str::to_string),
+            source_app: "test".to_string(),
+            timestamp: Utc::now(),
+            size: content.len(),
+            is_favorite: false,
+            tags: Vec::new(),
+            preview: content.to_string(),
+            title: None,
+        }
+    }
+
+    #[test]
+    fn day_bounds_span_one_local_day() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let (start, end) = day_bounds(date).unwrap();
+
+        let local_start = start.with_timezone(&Local);
+        let local_end = end.with_timezone(&Local);
+        assert_eq!(local_start.date_naive(), date);
+        assert_eq!((local_start.hour(), local_start.minute()), (0, 0));
+        assert_eq!(local_end.date_naive(), date.succ_opt().unwrap());
+        assert_eq!((local_end.hour(), local_end.minute()), (0, 0));
+
+        // The next day starts where this one ends
+        assert_eq!(day_bounds(date.succ_opt().unwrap()).unwrap().0, end);
+    }
+
+    #[test]
+    fn activity_is_grouped_by_content_type_largest_first() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let items = vec![
+            item("url", None, "https://example.com/docs"),
+            item("code", Some("rust"), "fn main() {\n    println!(\"hi\");\n}"),
+            item("code", Some("python"), "print('hi')"),
+        ];
+
+        assert_eq!(
+            describe_activity(&items, date),
+            "Clipboard activity on 2024-05-01 (3 items)\n\
+             \n## code (2)\n\
+             - [rust] fn main() { println!(\"hi\"); }\n\
+             - [python] print('hi')\n\
+             \n## url (1)\n\
+             - https://example.com/docs\n"
+        );
+    }
+
+    #[test]
+    fn long_items_are_shortened() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let activity = describe_activity(&[item("text", None, &"word ".repeat(100))], date);
+        let line = activity.lines().last().unwrap();
+        assert!(line.ends_with('…'));
+        assert_eq!(line.chars().count(), "- ".len() + EXCERPT_CHARS + 1);
+    }
+
+    #[test]
+    fn digest_time_is_hours_and_minutes() {
+        assert_eq!(parse_digest_time(" 18:30 "), NaiveTime::from_hms_opt(18, 30, 0));
+        assert_eq!(parse_digest_time("6pm"), None);
+        assert_eq!(parse_digest_time("25:00"), None);
+    }
+}
@@ -0,0 +1,329 @@
+/**
+ * Embedded model backend for Wurdump
+ *
+ * Runs a small quantized GGUF model in-process on the CPU through llama.cpp,
+ * so the AI features work without an Ollama server. It is an ordinary
+ * provider in the chain and answers the same chat completions bodies as the
+ * HTTP providers. The backend is only compiled with the `embedded-model`
+ * cargo feature; without it embedded providers fail over to the next
+ * provider. A loaded model stays in memory until it is unloaded.
+ */
+
+use std::path::PathBuf;
+use std::time::Duration;
+use serde_json::Value;
+use crate::providers::{ChatCompletion, ProviderConfig};
+
+/// Whether this build can run embedded models
+pub const AVAILABLE: bool = cfg!(feature = "embedded-model");
+
+/**
+ * Answer a chat completions request with the provider's GGUF model
+ */
+pub async fn chat_completion(provider: &ProviderConfig, request_body: &Value, timeout: Duration) -> Result<ChatCompletion, String> {
+    let path = model_path(provider)?;
+
+    #[cfg(feature = "embedded-model")]
+    {
+        llama::chat_completion(path, request_body, timeout).await
+    }
+
+    #[cfg(not(feature = "embedded-model"))]
+    {
+        let _ = (path, request_body, timeout);
+        Err(not_built())
+    }
+}
+
+/**
+ * Load the provider's model so the next request does not wait for it
+ */
+pub async fn warm_up(provider: &ProviderConfig) -> Result<(), String> {
+    let path = model_path(provider)?;
+
+    #[cfg(feature = "embedded-model")]
+    {
+        tokio::task::spawn_blocking(move || llama::load(&path).map(|_| ()))
+            .await
+            .map_err(|e| format!("Embedded model warm-up failed: {}", e))?
+    }
+
+    #[cfg(not(feature = "embedded-model"))]
+    {
+        let _ = path;
+        Err(not_built())
+    }
+}
+
+/**
+ * Drop the loaded model, if any, to free its memory
+ *
+ * Returns whether a model was loaded.
+ */
+pub fn unload() -> bool {
+    #[cfg(feature = "embedded-model")]
+    {
+        llama::unload()
+    }
+
+    #[cfg(not(feature = "embedded-model"))]
+    {
+        false
+    }
+}
+
+/**
+ * Path of the GGUF file an embedded provider runs
+ */
+fn model_path(provider: &ProviderConfig) -> Result<PathBuf, String> {
+    let path = provider
+        .model_path
+        .as_deref()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .ok_or_else(|| format!("Cannot load embedded model for {}: no model path configured", provider.name))?;
+
+    Ok(match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    })
+}
+
+#[cfg(not(feature = "embedded-model"))]
+fn not_built() -> String {
+    "Cannot load embedded model: this build does not include the embedded-model feature".to_string()
+}
+
+#[cfg(feature = "embedded-model")]
+mod llama {
+    use std::num::NonZeroU32;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+    use llama_cpp_2::context::params::LlamaContextParams;
+    use llama_cpp_2::llama_backend::LlamaBackend;
+    use llama_cpp_2::llama_batch::LlamaBatch;
+    use llama_cpp_2::model::params::LlamaModelParams;
+    use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaModel, Special};
+    use llama_cpp_2::sampling::LlamaSampler;
+    use once_cell::sync::{Lazy, OnceCell};
+    use serde_json::Value;
+    use crate::providers::ChatCompletion;
+
+    /// llama.cpp may only be initialized once per process
+    static BACKEND: OnceCell<LlamaBackend> = OnceCell::new();
+
+    /// The loaded model and the file it came from
+    static LOADED: Lazy<Mutex<Option<(PathBuf, Arc<LlamaModel>)>>> = Lazy::new(|| Mutex::new(None));
+
+    /**
+     * Sets the stop flag when the request future is dropped, e.g. on timeout or cancellation
+     */
+    struct StopOnDrop(Arc<AtomicBool>);
+
+    impl Drop for StopOnDrop {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub async fn chat_completion(path: PathBuf, request_body: &Value, timeout: Duration) -> Result<ChatCompletion, String> {
+        let messages: Vec<(String, String)> = request_body["messages"]
+            .as_array()
+            .map(|messages| {
+                messages
+                    .iter()
+                    .map(|message| {
+                        (
+                            message["role"].as_str().unwrap_or("user").to_string(),
+                            message["content"].as_str().unwrap_or_default().to_string(),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let temperature = request_body["temperature"].as_f64().unwrap_or(0.7) as f32;
+        let max_tokens = request_body["max_tokens"].as_u64().unwrap_or(1000) as u32;
+
+        // Generation runs on a blocking thread and cannot be aborted, so it
+        // checks this flag between tokens instead
+        let stop = Arc::new(AtomicBool::new(false));
+        let _stop_on_drop = StopOnDrop(Arc::clone(&stop));
+
+        let task = tokio::task::spawn_blocking(move || generate(&path, &messages, temperature, max_tokens, &stop));
+        match tokio::time::timeout(timeout, task).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(e)) => Err(format!("Embedded model failed: {}", e)),
+            Err(_) => Err(format!("AI request to embedded model timed out ({}s)", timeout.as_secs())),
+        }
+    }
+
+    /**
+     * Load a model, reusing it if it is already loaded
+     *
+     * The lock is held while loading so concurrent requests wait for one
+     * load instead of each reading the file.
+     */
+    pub fn load(path: &Path) -> Result<Arc<LlamaModel>, String> {
+        let mut loaded = LOADED.lock().unwrap();
+        if let Some((loaded_path, model)) = loaded.as_ref() {
+            if loaded_path == path {
+                return Ok(Arc::clone(model));
+            }
+        }
+
+        let started_at = Instant::now();
+        let model = LlamaModel::load_from_file(backend()?, path, &LlamaModelParams::default())
+            .map_err(|e| format!("Cannot load embedded model {}: {}", path.display(), e))?;
+        log::info!("🧠 Loaded embedded model {} in {}ms", path.display(), started_at.elapsed().as_millis());
+
+        let model = Arc::new(model);
+        *loaded = Some((path.to_path_buf(), Arc::clone(&model)));
+        Ok(model)
+    }
+
+    pub fn unload() -> bool {
+        LOADED.lock().unwrap().take().is_some()
+    }
+
+    fn backend() -> Result<&'static LlamaBackend, String> {
+        BACKEND.get_or_try_init(|| {
+            let mut backend = LlamaBackend::init()
+                .map_err(|e| format!("Cannot load embedded model: {}", e))?;
+            backend.void_logs();
+            Ok(backend)
+        })
+    }
+
+    /**
+     * Run the prompt and sample the answer token by token
+     */
+    fn generate(
+        path: &Path,
+        messages: &[(String, String)],
+        temperature: f32,
+        max_tokens: u32,
+        stop: &AtomicBool,
+    ) -> Result<ChatCompletion, String> {
+        let model = load(path)?;
+        let started_at = Instant::now();
+
+        let prompt = build_prompt(&model, messages);
+        let tokens = model
+            .str_to_token(&prompt, AddBos::Always)
+            .map_err(|e| format!("Failed to tokenize prompt: {}", e))?;
+        let prompt_tokens = tokens.len() as u32;
+
+        let context_size = (prompt_tokens + max_tokens).min(model.n_ctx_train());
+        if prompt_tokens >= context_size {
+            return Err(format!(
+                "Prompt of {} tokens does not fit the embedded model's context of {} tokens",
+                prompt_tokens, context_size
+            ));
+        }
+
+        let threads = std::thread::available_parallelism()
+            .map(|threads| threads.get() as i32)
+            .unwrap_or(4);
+        let params = LlamaContextParams::default()
+            .with_n_ctx(NonZeroU32::new(context_size))
+            .with_n_batch(context_size)
+            .with_n_threads(threads)
+            .with_n_threads_batch(threads);
+        let mut context = model
+            .new_context(backend()?, params)
+            .map_err(|e| format!("Failed to create embedded model context: {}", e))?;
+
+        let mut batch = LlamaBatch::new(tokens.len(), 1);
+        let last_index = tokens.len() as i32 - 1;
+        for (index, token) in (0_i32..).zip(tokens.iter()) {
+            batch
+                .add(*token, index, &[0], index == last_index)
+                .map_err(|e| format!("Failed to prepare prompt: {}", e))?;
+        }
+        context
+            .decode(&mut batch)
+            .map_err(|e| format!("Embedded model failed to read the prompt: {}", e))?;
+
+        let mut sampler = if temperature <= 0.0 {
+            LlamaSampler::greedy()
+        } else {
+            let seed = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.subsec_nanos())
+                .unwrap_or_default();
+            LlamaSampler::chain_simple([LlamaSampler::temp(temperature), LlamaSampler::dist(seed)])
+        };
+
+        // Tokens can end in the middle of a character, so the answer is collected as bytes
+        let mut output: Vec<u8> = Vec::new();
+        let mut time_to_first_token = None;
+        let mut completion_tokens = 0;
+        let mut position = batch.n_tokens();
+
+        while completion_tokens < max_tokens && (position as u32) < context_size {
+            if stop.load(Ordering::Relaxed) {
+                return Err("Embedded generation stopped".to_string());
+            }
+
+            let token = sampler.sample(&context, batch.n_tokens() - 1);
+            if model.is_eog_token(token) {
+                break;
+            }
+
+            let bytes = model
+                .token_to_bytes(token, Special::Tokenize)
+                .map_err(|e| format!("Failed to decode token: {}", e))?;
+            output.extend_from_slice(&bytes);
+            time_to_first_token.get_or_insert_with(|| started_at.elapsed());
+            completion_tokens += 1;
+
+            batch.clear();
+            batch
+                .add(token, position, &[0], true)
+                .map_err(|e| format!("Failed to prepare token: {}", e))?;
+            position += 1;
+            context
+                .decode(&mut batch)
+                .map_err(|e| format!("Embedded model failed to generate: {}", e))?;
+        }
+
+        let content = String::from_utf8_lossy(&output).into_owned();
+        if content.trim().is_empty() {
+            return Err("AI returned empty response".to_string());
+        }
+
+        Ok(ChatCompletion {
+            content,
+            prompt_tokens: Some(prompt_tokens),
+            completion_tokens: Some(completion_tokens),
+            time_to_first_token,
+        })
+    }
+
+    /**
+     * Format the conversation with the model's own chat template
+     *
+     * Models without a usable template get a plain role-labelled transcript.
+     */
+    fn build_prompt(model: &LlamaModel, messages: &[(String, String)]) -> String {
+        let templated = model.chat_template(None).ok().and_then(|template| {
+            let chat = messages
+                .iter()
+                .map(|(role, content)| LlamaChatMessage::new(role.clone(), content.clone()))
+                .collect::<Result<Vec<_>, _>>()
+                .ok()?;
+            model.apply_chat_template(&template, &chat, true).ok()
+        });
+
+        templated.unwrap_or_else(|| {
+            let mut prompt = String::new();
+            for (role, content) in messages {
+                prompt.push_str(&format!("### {}:\n{}\n\n", role, content));
+            }
+            prompt.push_str("### assistant:\n");
+            prompt
+        })
+    }
+}
@@ -0,0 +1,304 @@
+/**
+ * Semantic search for Wurdump
+ *
+ * Computes embeddings for clipboard items through the local Ollama embedding
+ * endpoint in a background task, stores the vectors in SQLite and ranks
+ * history by cosine similarity to a query, optionally blended with keyword
+ * matches.
+ */
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::Serialize;
+use crate::ai::{HTTP_CLIENT, OLLAMA_BASE_URL};
+use crate::database::ClipboardDatabase;
+use crate::{AppSettings, ClipboardItem};
+
+/// Embedding model used until the user selects another one
+pub const DEFAULT_EMBEDDING_MODEL: &str = "nomic-embed-text";
+
+/// Items embedded per request while indexing
+const INDEX_BATCH_SIZE: u32 = 16;
+
+/// How often the indexer looks for new items once it has caught up
+const INDEX_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Wait after a failed batch (e.g. Ollama not running) before trying again
+const INDEX_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Content beyond this many characters is not embedded
+const MAX_EMBED_CHARS: usize = 8000;
+
+/// Share of the semantic score in hybrid ranking; the rest comes from keywords
+const SEMANTIC_WEIGHT: f32 = 0.7;
+
+/**
+ * A clipboard item matched by semantic search
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticSearchResult {
+    pub item: ClipboardItem,
+    /// Final ranking score (0-1)
+    pub score: f32,
+    /// Cosine similarity between the query and the item
+    #[serde(rename = "semanticScore")]
+    pub semantic_score: f32,
+    /// Share of query terms found in the item (hybrid mode only)
+    #[serde(rename = "keywordScore")]
+    pub keyword_score: Option<f32>,
+}
+
+/**
+ * Compute embeddings for a batch of texts
+ */
+pub async fn embed(model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>, String> {
+    let inputs: Vec<String> = inputs
+        .iter()
+        .map(|input| input.chars().take(MAX_EMBED_CHARS).collect())
+        .collect();
+
+    let response = HTTP_CLIENT
+        .post(format!("{}/api/embed", OLLAMA_BASE_URL))
+        .json(&serde_json::json!({
+            "model": model,
+            "input": inputs,
+            "truncate": true
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach embedding endpoint: {}", e))?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(format!("Embedding model '{}' is not installed. Pull it to enable semantic search.", model));
+    }
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Embedding request failed with status {}: {}", status, body));
+    }
+
+    let json: serde_json::Value = response.json().await
+        .map_err(|e| format!("Failed to parse embedding response: {}", e))?;
+
+    let embeddings: Vec<Vec<f32>> = json["embeddings"]
+        .as_array()
+        .ok_or_else(|| "Embedding response has no embeddings".to_string())?
+        .iter()
+        .map(|vector| {
+            vector
+                .as_array()
+                .map(|values| values.iter().filter_map(|value| value.as_f64()).map(|value| value as f32).collect())
+                .unwrap_or_default()
+        })
+        .collect();
+
+    if embeddings.len() != inputs.len() {
+        return Err(format!("Expected {} embeddings, got {}", inputs.len(), embeddings.len()));
+    }
+
+    Ok(embeddings)
+}
+
+/**
+ * Embed the next batch of items that have no embedding yet
+ *
+ * Returns the number of items indexed.
+ */
+pub async fn index_pending(database: &ClipboardDatabase, model: &str) -> Result<usize, String> {
+    let items = database.get_items_without_embedding(model, INDEX_BATCH_SIZE).await
+        .map_err(|e| format!("Failed to load items to index: {}", e))?;
+    if items.is_empty() {
+        return Ok(0);
+    }
+
+    let contents: Vec<String> = items.iter().map(|item| item.content.clone()).collect();
+    let vectors = embed(model, &contents).await?;
+
+    for (item, vector) in items.iter().zip(vectors) {
+        database.store_item_embedding(&item.id, model, &vector).await
+            .map_err(|e| format!("Failed to store embedding: {}", e))?;
+    }
+
+    log::info!("🧭 Indexed {} clipboard items for semantic search", items.len());
+    Ok(items.len())
+}
+
+/**
+ * Start the background indexer
+ *
+ * Works through unindexed items in batches, then polls for new ones. Progress
+ * lives in the database, so indexing resumes after a restart.
+ */
+pub fn spawn_indexer(database: ClipboardDatabase, settings: Arc<Mutex<AppSettings>>) {
+    tokio::spawn(async move {
+        let mut last_error: Option<String> = None;
+
+        loop {
+            let (enabled, model) = {
+                let settings = settings.lock().unwrap();
+                (settings.semantic_indexing_enabled, settings.embedding_model.clone())
+            };
+
+            if !enabled {
+                tokio::time::sleep(INDEX_INTERVAL).await;
+                continue;
+            }
+
+            match index_pending(&database, &model).await {
+                // A full batch means there is probably more to do
+                Ok(count) if count as u32 == INDEX_BATCH_SIZE => {
+                    last_error = None;
+                }
+                Ok(_) => {
+                    last_error = None;
+                    tokio::time::sleep(INDEX_INTERVAL).await;
+                }
+                Err(e) => {
+                    // Only log when the failure changes, the server may be down for a while
+                    if last_error.as_deref() != Some(e.as_str()) {
+                        log::warn!("⚠️ Semantic indexing paused: {}", e);
+                        last_error = Some(e);
+                    }
+                    tokio::time::sleep(INDEX_RETRY_INTERVAL).await;
+                }
+            }
+        }
+    });
+}
+
+/**
+ * Rank clipboard history by similarity to a query
+ *
+ * In hybrid mode the score blends cosine similarity with the share of query
+ * terms found in the item, and items that are not indexed yet can still
+ * match on keywords alone.
+ */
+pub async fn semantic_search(
+    database: &ClipboardDatabase,
+    model: &str,
+    query: &str,
+    limit: usize,
+    hybrid: bool,
+) -> Result<Vec<SemanticSearchResult>, String> {
+    let query_vector = embed(model, &[query.to_string()]).await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| "No embedding returned for query".to_string())?;
+
+    let items = database.get_items_with_embeddings(model).await
+        .map_err(|e| format!("Failed to load embeddings: {}", e))?;
+
+    let query_terms = keyword_terms(query);
+
+    let mut results: Vec<SemanticSearchResult> = items
+        .into_iter()
+        .filter_map(|(item, vector)| {
+            let semantic_score = vector.as_ref().map(|vector| cosine_similarity(&query_vector, vector));
+
+            if !hybrid {
+                let semantic_score = semantic_score?;
+                return Some(SemanticSearchResult { item, score: semantic_score, semantic_score, keyword_score: None });
+            }
+
+            let keyword_score = keyword_score(&query_terms, &item.content);
+            let semantic_score = semantic_score.unwrap_or(0.0);
+            let score = SEMANTIC_WEIGHT * semantic_score + (1.0 - SEMANTIC_WEIGHT) * keyword_score;
+            (score > 0.0).then_some(SemanticSearchResult {
+                item,
+                score,
+                semantic_score,
+                keyword_score: Some(keyword_score),
+            })
+        })
+        .collect();
+
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    results.truncate(limit);
+
+    Ok(results)
+}
+
+/**
+ * Cosine similarity of two vectors (0 when they cannot be compared)
+ */
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/**
+ * Lowercased query words worth matching (skips one-letter words)
+ */
+fn keyword_terms(query: &str) -> HashSet<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|term| term.chars().count() > 1)
+        .map(|term| term.to_lowercase())
+        .collect()
+}
+
+/**
+ * Share of query terms that appear in the content
+ */
+fn keyword_score(terms: &HashSet<String>, content: &str) -> f32 {
+    if terms.is_empty() {
+        return 0.0;
+    }
+
+    let content = content.to_lowercase();
+    let matched = terms.iter().filter(|term| content.contains(term.as_str())).count();
+    matched as f32 / terms.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-6, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn cosine_similarity_of_related_vectors() {
+        assert_close(cosine_similarity(&[1.0, 2.0, 3.0], &[2.0, 4.0, 6.0]), 1.0);
+        assert_close(cosine_similarity(&[1.0, 0.0], &[0.0, 5.0]), 0.0);
+        assert_close(cosine_similarity(&[1.0, -1.0], &[-1.0, 1.0]), -1.0);
+        assert_close(cosine_similarity(&[1.0, 1.0], &[1.0, 0.0]), std::f32::consts::FRAC_1_SQRT_2);
+    }
+
+    #[test]
+    fn cosine_similarity_of_incomparable_vectors_is_zero() {
+        assert_eq!(cosine_similarity(&[1.0, 2.0], &[1.0, 2.0, 3.0]), 0.0);
+        assert_eq!(cosine_similarity(&[], &[]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 2.0]), 0.0);
+    }
+
+    #[test]
+    fn keyword_terms_are_lowercased_words() {
+        let terms = keyword_terms("Parse JSON, parse_config() a JSON file!");
+        let mut terms: Vec<_> = terms.into_iter().collect();
+        terms.sort();
+        assert_eq!(terms, vec!["file", "json", "parse", "parse_config"]);
+    }
+
+    #[test]
+    fn keyword_score_is_the_share_of_matched_terms() {
+        let terms = keyword_terms("docker compose volume");
+        assert_close(keyword_score(&terms, "docker-compose.yml with a VOLUME mount"), 1.0);
+        assert_close(keyword_score(&terms, "docker run hello-world"), 1.0 / 3.0);
+        assert_close(keyword_score(&terms, "nothing relevant"), 0.0);
+        assert_eq!(keyword_score(&keyword_terms("a ?"), "a"), 0.0);
+    }
+}
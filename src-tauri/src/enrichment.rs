@@ -0,0 +1,162 @@
+/**
+ * Background enrichment of captured clipboard items for Wurdump
+ *
+ * Newly captured items of opted-in content types get a short AI-generated
+ * title and a few tags. The work runs at background priority on the AI
+ * queue so it never delays requests the user is waiting on.
+ */
+
+use std::sync::Arc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use crate::ai::{self, AIRequest};
+use crate::chunking::split_into_chunks;
+use crate::content_detection::ContentDetector;
+use crate::database::ClipboardDatabase;
+use crate::output_validation::OutputFormat;
+use crate::providers;
+use crate::redaction::Redactor;
+use crate::{AppSettings, AppState, ClipboardItem};
+
+/// System prompt for labelling items
+const ENRICHMENT_SYSTEM_PROMPT: &str = "You label clipboard items so they are easy to find later. Reply with JSON only.";
+
+/// Instruction asking for the title and tags
+const ENRICHMENT_INSTRUCTION: &str = "Give this clipboard item a short descriptive title (at most 8 words) and 2 or 3 lowercase single-word tags. Reply with only this JSON: {\"title\": \"...\", \"tags\": [\"...\"]}";
+
+/// Only the start of long items is sent; it is enough to label them
+const MAX_ENRICHMENT_CONTENT_TOKENS: usize = 1500;
+
+const MAX_TAGS: usize = 3;
+const MAX_TAG_LENGTH: usize = 32;
+const MAX_TITLE_CHARS: usize = 80;
+
+static DETECTOR: Lazy<ContentDetector> = Lazy::new(ContentDetector::new);
+
+#[derive(Debug, Deserialize)]
+struct EnrichmentResponse {
+    title: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/**
+ * Payload of 'clipboard-item-enriched' events
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemEnrichedEvent {
+    #[serde(rename = "itemId")]
+    pub item_id: String,
+    pub title: String,
+    pub tags: Vec<String>,
+}
+
+/**
+ * Whether a captured item should be enriched under the current settings
+ */
+pub fn should_enrich(settings: &AppSettings, item: &ClipboardItem) -> bool {
+    settings.ai_enabled
+        && settings.ai_enrich_content_types.contains(&item.content_type)
+        && !DETECTOR.is_sensitive(&item.content)
+}
+
+/**
+ * Queue a newly captured item for enrichment if it qualifies
+ *
+ * Emits 'clipboard-item-enriched' once the title and tags are saved.
+ */
+pub fn enqueue_enrichment(app: AppHandle, database: ClipboardDatabase, item: ClipboardItem) {
+    let settings = app.state::<AppState>().settings.lock().unwrap().clone();
+
+    if !should_enrich(&settings, &item) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        match enrich_item(&app, &settings, &item).await {
+            Ok((title, tags)) => {
+                if let Err(e) = database.update_item_enrichment(&item.id, &title, &tags).await {
+                    log::warn!("Failed to save enrichment for {}: {}", item.id, e);
+                    return;
+                }
+                log::info!("🏷️ Enriched clipboard item {} with {} tag(s)", item.id, tags.len());
+                let event = ItemEnrichedEvent { item_id: item.id.clone(), title, tags };
+                if let Err(e) = app.emit("clipboard-item-enriched", event) {
+                    log::warn!("Failed to emit enrichment event: {}", e);
+                }
+            }
+            Err(e) => log::warn!("⚠️ Enrichment of clipboard item {} failed: {}", item.id, e),
+        }
+    });
+}
+
+/**
+ * Ask the model for a title and tags at background priority
+ */
+async fn enrich_item(app: &AppHandle, settings: &AppSettings, item: &ClipboardItem) -> Result<(String, Vec<String>), String> {
+    let content = split_into_chunks(&item.content, MAX_ENRICHMENT_CONTENT_TOKENS)
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+
+    let mut redactor = Redactor::new();
+    let content = if settings.ai_redaction_enabled {
+        redactor.redact(&content)
+    } else {
+        content
+    };
+
+    let providers = providers::provider_chain(settings, None);
+    let request = AIRequest {
+        content,
+        instruction: ENRICHMENT_INSTRUCTION.to_string(),
+        has_custom_instruction: true,
+        instruction_from_template: false,
+        context_items: Vec::new(),
+        system_prompt: ENRICHMENT_SYSTEM_PROMPT.to_string(),
+        model: providers[0].model.clone(),
+        temperature: 0.2,
+        max_tokens: settings.ai_max_tokens,
+        context_window: settings.ai_context_window,
+        output_format: OutputFormat::Json,
+        history: Vec::new(),
+        providers,
+        timeout: providers::request_timeout(settings),
+    };
+
+    let queue = Arc::clone(&app.state::<AppState>().ai_queue);
+    let response = ai::run_background_request(&queue, format!("enrich-{}", item.id), &request).await?;
+
+    parse_enrichment(&redactor.restore(&response))
+}
+
+/**
+ * Extract and clean up the title and tags from the model's reply
+ */
+fn parse_enrichment(response: &str) -> Result<(String, Vec<String>), String> {
+    let start = response.find('{').ok_or_else(|| "Reply contains no JSON object".to_string())?;
+    let end = response.rfind('}').ok_or_else(|| "Reply contains no JSON object".to_string())?;
+    if end < start {
+        return Err("Reply contains no JSON object".to_string());
+    }
+
+    let parsed: EnrichmentResponse = serde_json::from_str(&response[start..=end])
+        .map_err(|e| format!("Invalid enrichment JSON: {}", e))?;
+
+    let title: String = parsed.title.trim().chars().take(MAX_TITLE_CHARS).collect();
+    if title.is_empty() {
+        return Err("Model returned an empty title".to_string());
+    }
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in parsed.tags {
+        let tag = tag.trim().trim_start_matches('#').to_lowercase();
+        if !tag.is_empty() && tag.chars().count() <= MAX_TAG_LENGTH && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags.truncate(MAX_TAGS);
+
+    Ok((title, tags))
+}
@@ -0,0 +1,344 @@
+/**
+ * Feedback on transformations for Wurdump
+ *
+ * Records what the user did with a result (applied, copied or rejected it)
+ * and an optional thumbs up or down. Feedback is attributed to the preset
+ * that produced the result, a prompt template or a built-in transform, and
+ * to the content type it was used on, so presets can be ranked per content
+ * type. Recent feedback counts more than old feedback.
+ */
+
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Feedback loses half its weight after this many days
+const HALF_LIFE_DAYS: f64 = 30.0;
+
+/// Neutral weight added to every preset so a single signal does not dominate
+const PRIOR_WEIGHT: f64 = 2.0;
+
+/// How far back feedback is considered for ranking
+pub const RANKING_WINDOW_DAYS: i64 = 180;
+
+/**
+ * What the user did with a result
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackAction {
+    Applied,
+    Copied,
+    Rejected,
+    ThumbsUp,
+    ThumbsDown,
+}
+
+impl FeedbackAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeedbackAction::Applied => "applied",
+            FeedbackAction::Copied => "copied",
+            FeedbackAction::Rejected => "rejected",
+            FeedbackAction::ThumbsUp => "thumbs_up",
+            FeedbackAction::ThumbsDown => "thumbs_down",
+        }
+    }
+
+    pub fn parse(action: &str) -> Option<Self> {
+        match action {
+            "applied" => Some(FeedbackAction::Applied),
+            "copied" => Some(FeedbackAction::Copied),
+            "rejected" => Some(FeedbackAction::Rejected),
+            "thumbs_up" => Some(FeedbackAction::ThumbsUp),
+            "thumbs_down" => Some(FeedbackAction::ThumbsDown),
+            _ => None,
+        }
+    }
+
+    fn is_rating(&self) -> bool {
+        matches!(self, FeedbackAction::ThumbsUp | FeedbackAction::ThumbsDown)
+    }
+
+    /// How much the action says about the result
+    fn weight(&self) -> f64 {
+        match self {
+            FeedbackAction::Applied => 1.0,
+            FeedbackAction::Copied => 0.6,
+            FeedbackAction::Rejected => -1.0,
+            FeedbackAction::ThumbsUp => 1.5,
+            FeedbackAction::ThumbsDown => -1.5,
+        }
+    }
+}
+
+/**
+ * Kind of preset a result came from
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresetKind {
+    /// A prompt template
+    Template,
+    /// A built-in (non-AI) transform
+    Builtin,
+}
+
+impl PresetKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PresetKind::Template => "template",
+            PresetKind::Builtin => "builtin",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "template" => Some(PresetKind::Template),
+            "builtin" => Some(PresetKind::Builtin),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * One piece of feedback on a transformation
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct TransformationFeedback {
+    pub id: String,
+    #[serde(rename = "transformationId")]
+    pub transformation_id: String,
+    pub action: FeedbackAction,
+    /// Preset that produced the result, if any
+    #[serde(rename = "presetKind")]
+    pub preset_kind: Option<PresetKind>,
+    #[serde(rename = "presetId")]
+    pub preset_id: Option<String>,
+    /// Content type of the clipboard item the result was for
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+/**
+ * How well a preset has done on one content type
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct PresetScore {
+    pub kind: PresetKind,
+    pub id: String,
+    /// Between -1 and 1; 0 without feedback
+    pub score: f64,
+    pub applied: u32,
+    pub copied: u32,
+    pub rejected: u32,
+    #[serde(rename = "thumbsUp")]
+    pub thumbs_up: u32,
+    #[serde(rename = "thumbsDown")]
+    pub thumbs_down: u32,
+}
+
+/**
+ * Preset a transformation id belongs to when it is a built-in result ("builtin-<id>-<uuid>")
+ */
+pub fn builtin_preset(transformation_id: &str) -> Option<&str> {
+    let rest = transformation_id.strip_prefix("builtin-")?;
+    let split = rest.len().checked_sub(uuid::fmt::Hyphenated::LENGTH + 1)?;
+    let (preset, run) = (rest.get(..split)?, rest.get(split..)?.strip_prefix('-')?);
+
+    uuid::Uuid::try_parse(run).ok()?;
+    (!preset.is_empty()).then_some(preset)
+}
+
+/**
+ * Score presets from their feedback, best first
+ *
+ * Only the latest thumbs up or down of each transformation counts, so
+ * changing a rating replaces it. Feedback without a preset is ignored.
+ */
+pub fn rank_presets(feedback: &[TransformationFeedback], now: DateTime<Utc>) -> Vec<PresetScore> {
+    let mut latest_ratings: HashMap<&str, &TransformationFeedback> = HashMap::new();
+    for entry in feedback.iter().filter(|entry| entry.action.is_rating()) {
+        match latest_ratings.get(entry.transformation_id.as_str()) {
+            Some(latest) if latest.created_at >= entry.created_at => {}
+            _ => {
+                latest_ratings.insert(&entry.transformation_id, entry);
+            }
+        }
+    }
+
+    // (weighted sum, total weight, counts)
+    let mut presets: HashMap<(PresetKind, &str), (f64, f64, PresetScore)> = HashMap::new();
+    for entry in feedback {
+        let (Some(kind), Some(id)) = (entry.preset_kind, entry.preset_id.as_deref()) else {
+            continue;
+        };
+        if entry.action.is_rating()
+            && !latest_ratings
+                .get(entry.transformation_id.as_str())
+                .is_some_and(|latest| std::ptr::eq(*latest, entry))
+        {
+            continue;
+        }
+
+        let age_days = (now - entry.created_at).num_seconds().max(0) as f64 / 86_400.0;
+        let decay = 0.5_f64.powf(age_days / HALF_LIFE_DAYS);
+
+        let (sum, weight, score) = presets.entry((kind, id)).or_insert_with(|| {
+            (0.0, 0.0, PresetScore {
+                kind,
+                id: id.to_string(),
+                score: 0.0,
+                applied: 0,
+                copied: 0,
+                rejected: 0,
+                thumbs_up: 0,
+                thumbs_down: 0,
+            })
+        });
+        *sum += entry.action.weight() * decay;
+        *weight += entry.action.weight().abs() * decay;
+
+        match entry.action {
+            FeedbackAction::Applied => score.applied += 1,
+            FeedbackAction::Copied => score.copied += 1,
+            FeedbackAction::Rejected => score.rejected += 1,
+            FeedbackAction::ThumbsUp => score.thumbs_up += 1,
+            FeedbackAction::ThumbsDown => score.thumbs_down += 1,
+        }
+    }
+
+    let mut ranked: Vec<PresetScore> = presets
+        .into_values()
+        .map(|(sum, weight, mut score)| {
+            score.score = sum / (weight + PRIOR_WEIGHT);
+            score
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.id.cmp(&b.id)));
+    ranked
+}
+
+/**
+ * Order items by the score of their preset, keeping the given order among equals
+ *
+ * Presets without feedback score 0, so they rank above rejected ones.
+ */
+pub fn sort_by_score<T>(items: &mut [T], scores: &[PresetScore], kind: PresetKind, id: impl Fn(&T) -> &str) {
+    let score_of = |item: &T| {
+        scores
+            .iter()
+            .find(|score| score.kind == kind && score.id == id(item))
+            .map(|score| score.score)
+            .unwrap_or(0.0)
+    };
+    items.sort_by(|a, b| score_of(b).total_cmp(&score_of(a)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn entry(transformation_id: &str, action: FeedbackAction, preset: Option<&str>, created_at: DateTime<Utc>) -> TransformationFeedback {
+        TransformationFeedback {
+            id: format!("{}-{}", transformation_id, action.as_str()),
+            transformation_id: transformation_id.to_string(),
+            action,
+            preset_kind: preset.map(|_| PresetKind::Template),
+            preset_id: preset.map(str::to_string),
+            content_type: "code".to_string(),
+            created_at,
+        }
+    }
+
+    #[test]
+    fn builtin_preset_comes_from_the_result_id() {
+        let id = format!("builtin-snake_case-{}", uuid::Uuid::new_v4());
+        assert_eq!(builtin_preset(&id), Some("snake_case"));
+        assert_eq!(builtin_preset("builtin-snake_case"), None);
+        assert_eq!(builtin_preset(&format!("builtin-{}", uuid::Uuid::new_v4())), None);
+        assert_eq!(builtin_preset(&uuid::Uuid::new_v4().to_string()), None);
+    }
+
+    #[test]
+    fn positive_feedback_ranks_first() {
+        let now = Utc::now();
+        let feedback = vec![
+            entry("t1", FeedbackAction::Rejected, Some("bad"), now),
+            entry("t2", FeedbackAction::Applied, Some("good"), now),
+            entry("t3", FeedbackAction::Copied, Some("good"), now),
+            entry("t4", FeedbackAction::Applied, None, now),
+        ];
+
+        let ranked = rank_presets(&feedback, now);
+        assert_eq!(ranked.iter().map(|score| score.id.as_str()).collect::<Vec<_>>(), vec!["good", "bad"]);
+        assert!(ranked[0].score > 0.0 && ranked[0].score < 1.0);
+        assert!(ranked[1].score < 0.0 && ranked[1].score > -1.0);
+        assert_eq!((ranked[0].applied, ranked[0].copied), (1, 1));
+        assert_eq!(ranked[1].rejected, 1);
+    }
+
+    #[test]
+    fn only_the_latest_rating_counts() {
+        let now = Utc::now();
+        let feedback = vec![
+            entry("t1", FeedbackAction::ThumbsDown, Some("preset"), now - Duration::minutes(5)),
+            entry("t1", FeedbackAction::ThumbsUp, Some("preset"), now),
+        ];
+
+        let ranked = rank_presets(&feedback, now);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!((ranked[0].thumbs_up, ranked[0].thumbs_down), (1, 0));
+        assert!(ranked[0].score > 0.0);
+    }
+
+    #[test]
+    fn old_feedback_weighs_less() {
+        let now = Utc::now();
+        let feedback = vec![
+            entry("t1", FeedbackAction::Applied, Some("recent"), now),
+            entry("t2", FeedbackAction::Applied, Some("old"), now - Duration::days(90)),
+        ];
+
+        let ranked = rank_presets(&feedback, now);
+        assert_eq!(ranked[0].id, "recent");
+        assert!(ranked[0].score > ranked[1].score);
+    }
+
+    #[test]
+    fn ties_are_ordered_by_id() {
+        let now = Utc::now();
+        let feedback = vec![
+            entry("t1", FeedbackAction::Applied, Some("b"), now),
+            entry("t2", FeedbackAction::Applied, Some("a"), now),
+        ];
+
+        let ranked = rank_presets(&feedback, now);
+        assert_eq!(ranked.iter().map(|score| score.id.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn sorting_puts_presets_without_feedback_above_rejected_ones() {
+        let now = Utc::now();
+        let scores = rank_presets(
+            &[
+                entry("t1", FeedbackAction::Rejected, Some("rejected"), now),
+                entry("t2", FeedbackAction::Applied, Some("liked"), now),
+            ],
+            now,
+        );
+
+        let mut presets = vec!["rejected", "unrated", "liked"];
+        sort_by_score(&mut presets, &scores, PresetKind::Template, |preset| preset);
+        assert_eq!(presets, vec!["liked", "unrated", "rejected"]);
+
+        // Scores of another kind do not apply
+        let mut builtins = vec!["rejected", "liked"];
+        sort_by_score(&mut builtins, &scores, PresetKind::Builtin, |preset| preset);
+        assert_eq!(builtins, vec!["rejected", "liked"]);
+    }
+}
@@ -0,0 +1,547 @@
+/**
+ * Local IPC server for Wurdump
+ *
+ * Lets editor plugins and shell scripts talk to the running app without
+ * opening the panel. Clients connect to a Unix domain socket and exchange
+ * JSON-RPC 2.0 messages, one JSON object per line. The socket is only
+ * accessible to the user running the app (directory 0700, socket 0600), so
+ * file permissions are the authentication. Nothing listens on the socket
+ * unless the ipc_enabled setting is turned on.
+ *
+ * Methods:
+ * - history.list { limit?, offset? }
+ * - history.search { query?, contentType?, codeLanguage?, since?, until?, favoritesOnly?, limit? }
+ * - history.get { id }
+ * - history.push { content, copy? }: store an item as if it had been copied
+ * - transform.run { itemId? | content?, prompt?, options? }: AI transformations
+ * - transform.builtin { itemId? | content?, transformId? }: built-in transforms
+ * - transform.listBuiltin
+ * - events.subscribe / events.unsubscribe: 'clipboard.captured' notifications
+ *   with the new item as params
+ */
+
+use std::io::{Error, ErrorKind};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{broadcast, mpsc, OwnedSemaphorePermit, Semaphore};
+use crate::commands;
+use crate::enrichment;
+use crate::{AIProcessOptions, AppState, ClipboardFilter, ClipboardItem};
+
+/// Socket location inside the app data directory
+const SOCKET_DIR: &str = "ipc";
+const SOCKET_FILE: &str = "wurdump.sock";
+
+/// Largest request line accepted; longer requests close the connection
+const MAX_REQUEST_BYTES: usize = 16 * 1024 * 1024;
+
+/// Clients served at once; further connections wait until one closes
+const MAX_CONNECTIONS: usize = 16;
+
+const DEFAULT_LIST_LIMIT: u32 = 20;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Errors reported by the app itself, e.g. an item that does not exist
+const APP_ERROR: i64 = -32000;
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    jsonrpc: String,
+    /// Absent for notifications, which get no response
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+impl From<String> for RpcError {
+    fn from(message: String) -> Self {
+        Self::new(APP_ERROR, message)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ListParams {
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SearchParams {
+    #[serde(flatten)]
+    filter: ClipboardFilter,
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GetParams {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PushParams {
+    content: String,
+    /// Also put the content on the system clipboard
+    #[serde(default)]
+    copy: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TransformParams {
+    #[serde(rename = "itemId")]
+    item_id: Option<String>,
+    content: Option<String>,
+    prompt: Option<String>,
+    options: AIProcessOptions,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BuiltinParams {
+    #[serde(rename = "itemId")]
+    item_id: Option<String>,
+    content: Option<String>,
+    /// Run only this transform instead of every applicable one
+    #[serde(rename = "transformId")]
+    transform_id: Option<String>,
+}
+
+/**
+ * Path of the socket: WURDUMP_SOCKET if set, otherwise in the app data directory
+ *
+ * A custom path must be in a directory only the current user can access.
+ */
+pub fn socket_path(app: &AppHandle) -> PathBuf {
+    if let Some(path) = std::env::var_os("WURDUMP_SOCKET").filter(|path| !path.is_empty()) {
+        return PathBuf::from(path);
+    }
+
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .unwrap_or_else(|_| std::env::temp_dir().join("wurdump"));
+    data_dir.join(SOCKET_DIR).join(SOCKET_FILE)
+}
+
+/**
+ * Start or stop serving the socket to match the ipc_enabled setting
+ *
+ * Turning the server off closes open connections and removes the socket.
+ * If another instance is already serving the socket, this one does not
+ * take it over.
+ */
+pub fn set_enabled(app: &AppHandle, enabled: bool) {
+    let state = app.state::<AppState>();
+    let mut server = state.ipc_server.lock().unwrap();
+    let path = socket_path(app);
+
+    if !enabled {
+        // Dropping the sender stops the accept loop and every connection
+        if server.take().is_some() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                log::warn!("Failed to remove IPC socket {}: {}", path.display(), e);
+            }
+            log::info!("🔌 IPC server stopped");
+        }
+        return;
+    }
+    if server.is_some() {
+        return;
+    }
+
+    let listener = match bind(&path) {
+        Ok(Some(listener)) => listener,
+        Ok(None) => {
+            log::warn!("⚠️ IPC socket {} is served by another instance, not listening", path.display());
+            return;
+        }
+        Err(e) => {
+            log::error!("❌ Failed to start IPC server at {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    let (shutdown, _) = broadcast::channel(1);
+    tauri::async_runtime::spawn(serve(app.clone(), listener, shutdown.subscribe()));
+    *server = Some(shutdown);
+    log::info!("🔌 IPC server listening on {}", path.display());
+}
+
+/**
+ * Accept clients until the server is stopped
+ */
+async fn serve(app: AppHandle, listener: std::os::unix::net::UnixListener, mut shutdown: broadcast::Receiver<()>) {
+    let listener = match UnixListener::from_std(listener) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("❌ Failed to start IPC server: {}", e);
+            return;
+        }
+    };
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+
+    loop {
+        let permit = tokio::select! {
+            _ = shutdown.recv() => break,
+            permit = connections.clone().acquire_owned() => permit,
+        };
+        let Ok(permit) = permit else { break };
+
+        let accepted = tokio::select! {
+            _ = shutdown.recv() => break,
+            accepted = listener.accept() => accepted,
+        };
+        match accepted {
+            Ok((stream, _)) => {
+                tauri::async_runtime::spawn(handle_connection(app.clone(), stream, shutdown.resubscribe(), permit));
+            }
+            Err(e) => log::warn!("Failed to accept IPC connection: {}", e),
+        }
+    }
+}
+
+/**
+ * Bind the socket readable and writable by the current user only
+ *
+ * The socket is created inside a private directory, so other users cannot
+ * reach it in the moment before its own permissions are tightened. A socket
+ * file left behind by a previous run is replaced. Returns None if the socket
+ * is still in use.
+ */
+fn bind(path: &Path) -> std::io::Result<Option<std::os::unix::net::UnixListener>> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    ensure_private_dir(dir)?;
+
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Ok(None);
+        }
+        std::fs::remove_file(path)?;
+    }
+
+    let listener = std::os::unix::net::UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    listener.set_nonblocking(true)?;
+    Ok(Some(listener))
+}
+
+/**
+ * Make sure a directory exists, belongs to the current user and is closed to others
+ *
+ * Missing directories are created with mode 0700 and the app's own socket
+ * directory is tightened if needed. Other existing directories (from
+ * WURDUMP_SOCKET) are never changed; the server refuses to start in them
+ * if they are open to other users.
+ */
+fn ensure_private_dir(dir: &Path) -> std::io::Result<()> {
+    std::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+
+    let metadata = std::fs::metadata(dir)?;
+    if metadata.uid() != nix::unistd::geteuid().as_raw() {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            format!("{} is not owned by the current user", dir.display()),
+        ));
+    }
+
+    if metadata.mode() & 0o077 != 0 {
+        if dir.file_name().is_some_and(|name| name == SOCKET_DIR) {
+            std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+        } else {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("{} is accessible to other users; put the socket in a directory with mode 0700", dir.display()),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Serve one client until it disconnects or the server stops
+ *
+ * Requests are handled concurrently, so a slow transform does not hold up
+ * history queries; responses may arrive out of order and carry their id.
+ * The permit frees the connection slot when the client is done.
+ */
+async fn handle_connection(
+    app: AppHandle,
+    stream: UnixStream,
+    mut shutdown: broadcast::Receiver<()>,
+    _permit: OwnedSemaphorePermit,
+) {
+    let (reader, mut writer) = stream.into_split();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+
+    let writer_task = tauri::async_runtime::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            let mut line = message.to_string();
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut reader = BufReader::new(reader);
+    let mut subscription: Option<tauri::async_runtime::JoinHandle<()>> = None;
+
+    loop {
+        let mut line = String::new();
+        let mut limited = (&mut reader).take(MAX_REQUEST_BYTES as u64 + 1);
+        let read = tokio::select! {
+            _ = shutdown.recv() => break,
+            read = limited.read_line(&mut line) => read,
+        };
+        match read {
+            Ok(0) => break,
+            Ok(read) if read > MAX_REQUEST_BYTES => {
+                let _ = tx.send(error_response(Value::Null, RpcError::new(INVALID_REQUEST, "Request is too large")));
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                log::debug!("IPC connection closed: {}", e);
+                break;
+            }
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request: RpcRequest = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let _ = tx.send(error_response(Value::Null, RpcError::new(PARSE_ERROR, e.to_string())));
+                continue;
+            }
+        };
+        if request.jsonrpc != "2.0" {
+            let id = request.id.unwrap_or(Value::Null);
+            let _ = tx.send(error_response(id, RpcError::new(INVALID_REQUEST, "Only JSON-RPC 2.0 is supported")));
+            continue;
+        }
+
+        log::debug!("🔌 IPC request: {}", request.method);
+        match request.method.as_str() {
+            "events.subscribe" => {
+                if let Some(previous) = subscription.take() {
+                    previous.abort();
+                }
+                subscription = subscribe(&app, tx.clone());
+                if let Some(id) = request.id {
+                    let _ = tx.send(match subscription {
+                        Some(_) => success_response(id, json!(true)),
+                        None => error_response(id, RpcError::new(APP_ERROR, "Capture events are not available yet")),
+                    });
+                }
+            }
+            "events.unsubscribe" => {
+                let was_subscribed = subscription.take().map(|task| task.abort()).is_some();
+                if let Some(id) = request.id {
+                    let _ = tx.send(success_response(id, json!(was_subscribed)));
+                }
+            }
+            _ => {
+                let app = app.clone();
+                let tx = tx.clone();
+                tauri::async_runtime::spawn(async move {
+                    let result = dispatch(&app, &request.method, request.params).await;
+                    if let Some(id) = request.id {
+                        let _ = tx.send(match result {
+                            Ok(value) => success_response(id, value),
+                            Err(error) => error_response(id, error),
+                        });
+                    }
+                });
+            }
+        }
+    }
+
+    if let Some(task) = subscription {
+        task.abort();
+    }
+    drop(tx);
+    let _ = writer_task.await;
+}
+
+/**
+ * Forward capture events to the client as 'clipboard.captured' notifications
+ */
+fn subscribe(app: &AppHandle, tx: mpsc::UnboundedSender<Value>) -> Option<tauri::async_runtime::JoinHandle<()>> {
+    let mut events = app.state::<AppState>().capture_events.lock().unwrap().as_ref()?.subscribe();
+
+    Some(tauri::async_runtime::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(item) => {
+                    let notification = json!({ "jsonrpc": "2.0", "method": "clipboard.captured", "params": item });
+                    if tx.send(notification).is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("IPC subscriber missed {} capture event(s)", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }))
+}
+
+async fn dispatch(app: &AppHandle, method: &str, params: Value) -> Result<Value, RpcError> {
+    let state = app.state::<AppState>();
+
+    match method {
+        "history.list" => {
+            let params: ListParams = parse_params(params)?;
+            let items = commands::get_clipboard_history(state, params.limit, params.offset).await?;
+            to_value(items)
+        }
+        "history.search" => {
+            let params: SearchParams = parse_params(params)?;
+            let db = commands::get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+            let items = db
+                .search_clipboard_items(&params.filter, params.limit.unwrap_or(DEFAULT_LIST_LIMIT))
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to search clipboard history: {}", e);
+                    "Failed to search clipboard history".to_string()
+                })?;
+            to_value(items)
+        }
+        "history.get" => {
+            let params: GetParams = parse_params(params)?;
+            to_value(get_item(app, &params.id).await?)
+        }
+        "history.push" => {
+            let params: PushParams = parse_params(params)?;
+            to_value(push_item(app, params).await?)
+        }
+        "transform.run" => {
+            let params: TransformParams = parse_params(params)?;
+            let (content, item_id) = resolve_content(app, params.item_id, params.content).await?;
+            let mut options = params.options;
+            options.item_id = options.item_id.or(item_id);
+
+            let transformations = commands::process_with_ai(content, params.prompt, None, Some(options), app.clone(), state).await?;
+            to_value(transformations)
+        }
+        "transform.builtin" => {
+            let params: BuiltinParams = parse_params(params)?;
+            let (content, _) = resolve_content(app, params.item_id, params.content).await?;
+            match params.transform_id {
+                Some(transform_id) => to_value(commands::apply_builtin_transform(content, transform_id).await?),
+                None => to_value(commands::run_builtin_transforms(content, state).await?),
+            }
+        }
+        "transform.listBuiltin" => to_value(commands::list_builtin_transforms().await?),
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+    }
+}
+
+async fn get_item(app: &AppHandle, id: &str) -> Result<ClipboardItem, RpcError> {
+    let db = commands::get_database(&app.state::<AppState>()).ok_or_else(|| "Database not available".to_string())?;
+    let item = db.get_clipboard_item(id).await.map_err(|e| {
+        log::error!("Failed to load clipboard item {}: {}", id, e);
+        "Failed to load clipboard item".to_string()
+    })?;
+    Ok(item.ok_or_else(|| format!("Clipboard item not found: {}", id))?)
+}
+
+/**
+ * Store pushed content like a captured item, optionally copying it too
+ *
+ * When copied, the clipboard monitor sees the content already stored and
+ * does not capture it a second time.
+ */
+async fn push_item(app: &AppHandle, params: PushParams) -> Result<ClipboardItem, RpcError> {
+    if params.content.trim().is_empty() {
+        return Err(RpcError::new(INVALID_PARAMS, "Content is empty"));
+    }
+
+    let state = app.state::<AppState>();
+    let db = commands::get_database(&state).ok_or_else(|| "Database not available".to_string())?;
+    let item = db.store_clipboard_item(&params.content).await.map_err(|e| {
+        log::error!("Failed to store pushed clipboard item: {}", e);
+        "Failed to store clipboard item".to_string()
+    })?;
+    log::info!("📥 Stored clipboard item {} pushed over IPC", item.id);
+
+    if params.copy {
+        commands::set_clipboard_content(app.clone(), params.content).await?;
+    }
+
+    enrichment::enqueue_enrichment(app.clone(), db, item.clone());
+    state.publish_capture(&item);
+    Ok(item)
+}
+
+/**
+ * Content to transform: the given item's, or the given text
+ */
+async fn resolve_content(
+    app: &AppHandle,
+    item_id: Option<String>,
+    content: Option<String>,
+) -> Result<(String, Option<String>), RpcError> {
+    match (item_id, content) {
+        (Some(_), Some(_)) => Err(RpcError::new(INVALID_PARAMS, "Pass either itemId or content, not both")),
+        (Some(id), None) => {
+            let item = get_item(app, &id).await?;
+            Ok((item.content, Some(item.id)))
+        }
+        (None, Some(content)) => Ok((content, None)),
+        (None, None) => Err(RpcError::new(INVALID_PARAMS, "itemId or content is required")),
+    }
+}
+
+/**
+ * Deserialize params, treating missing params as an empty object
+ */
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(APP_ERROR, e.to_string()))
+}
+
+fn success_response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
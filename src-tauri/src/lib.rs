@@ -7,14 +7,38 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use tokio::sync::broadcast;
 
+mod ai;
+mod ai_queue;
+mod chunking;
 mod commands;
 mod clipboard_monitor;
 mod content_detection;
 mod database;
+mod embeddings;
+mod enrichment;
+mod transforms;
+mod redaction;
+mod prompt_templates;
+mod ollama;
+mod output_validation;
+mod batch;
+mod digest;
+mod providers;
+mod metrics;
+mod embedded;
+mod context_selection;
+mod feedback;
+mod response_segments;
+pub mod cli;
+#[cfg(unix)]
+mod ipc_server;
 
 use commands::*;
 use clipboard_monitor::ClipboardMonitor;
 
+/// Captured items buffered for IPC subscribers that fall behind
+const CAPTURE_EVENT_CAPACITY: usize = 64;
+
 /**
  * Task cancellation token for AI requests
  */
@@ -27,6 +51,24 @@ pub type CancellationToken = broadcast::Sender<()>;
 pub struct AppState {
     pub clipboard_monitor: Arc<Mutex<Option<Arc<ClipboardMonitor>>>>,
     pub active_ai_tasks: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    pub settings: Arc<Mutex<AppSettings>>,
+    pub ai_queue: Arc<ai_queue::AIJobQueue>,
+    /// Newly captured items, for IPC subscribers
+    pub capture_events: Arc<Mutex<Option<broadcast::Sender<ClipboardItem>>>>,
+    /// Stops the IPC server when dropped; set while the server is listening
+    pub ipc_server: Arc<Mutex<Option<broadcast::Sender<()>>>>,
+}
+
+impl AppState {
+    /**
+     * Tell capture subscribers about a newly stored item
+     */
+    pub fn publish_capture(&self, item: &ClipboardItem) {
+        if let Some(events) = self.capture_events.lock().unwrap().as_ref() {
+            // Sending only fails when nobody is subscribed
+            let _ = events.send(item.clone());
+        }
+    }
 }
 
 /**
@@ -44,6 +86,29 @@ pub struct ClipboardItem {
     pub is_favorite: bool,
     pub tags: Vec<String>,
     pub preview: String,
+    /// Short title generated by AI enrichment
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+/**
+ * Criteria for selecting clipboard history items
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardFilter {
+    /// Text that the content, title or tags must contain (case-insensitive)
+    pub query: Option<String>,
+    #[serde(rename = "contentType")]
+    pub content_type: Option<String>,
+    #[serde(rename = "codeLanguage")]
+    pub code_language: Option<String>,
+    /// Only items captured at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only items captured before this time
+    pub until: Option<DateTime<Utc>>,
+    #[serde(rename = "favoritesOnly")]
+    pub favorites_only: bool,
 }
 
 /**
@@ -60,12 +125,145 @@ pub struct AITransformation {
     pub is_applied: bool,
     #[serde(rename = "transformationType")]
     pub transformation_type: String,
+    #[serde(rename = "isCached", default)]
+    pub is_cached: bool,
+    /// Provider that produced the result, for AI results
+    #[serde(default)]
+    pub provider: Option<String>,
+    /// Model that produced the result, for AI results
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Clipboard items that were given to the model as context
+    #[serde(rename = "contextSources", default)]
+    pub context_sources: Vec<ContextSource>,
+    /// Language of the result, when it is a piece of code
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+/**
+ * AI transformation stored in history, linked to its source clipboard item
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AITransformationRecord {
+    pub id: String,
+    #[serde(rename = "itemId")]
+    pub item_id: Option<String>,
+    pub prompt: String,
+    pub model: String,
+    pub transformation: AITransformation,
+    #[serde(rename = "latencyMs")]
+    pub latency_ms: u64,
+    #[serde(rename = "templateId", default)]
+    pub template_id: Option<String>,
+    /// Clipboard items that were given to the model as context
+    #[serde(rename = "contextSources", default)]
+    pub context_sources: Vec<ContextSource>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+/**
+ * A message in a refinement conversation about a clipboard item
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationMessage {
+    pub id: String,
+    #[serde(rename = "itemId")]
+    pub item_id: String,
+    /// "user" for instructions, "assistant" for results
+    pub role: String,
+    pub content: String,
+    /// Recorded transformation behind an assistant message
+    #[serde(rename = "transformationId")]
+    pub transformation_id: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+/**
+ * Clipboard history item used as context for an AI transformation
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextSource {
+    #[serde(rename = "itemId")]
+    pub item_id: String,
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    #[serde(rename = "codeLanguage")]
+    pub code_language: Option<String>,
+    /// Tokens of the item that were included in the prompt
+    pub tokens: usize,
+    /// Whether the item was cut to fit the context budget
+    pub truncated: bool,
+    /// Why the item was picked, when it was selected automatically
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/**
+ * Optional parameters for AI processing
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AIProcessOptions {
+    /// Skip the cache lookup and always ask the model
+    #[serde(rename = "bypassCache")]
+    pub bypass_cache: bool,
+    /// Source clipboard item, used to link results in history
+    #[serde(rename = "itemId")]
+    pub item_id: Option<String>,
+    #[serde(rename = "templateId")]
+    pub template_id: Option<String>,
+    /// Overrides the template and settings response token limit
+    #[serde(rename = "maxTokens")]
+    pub max_tokens: Option<u32>,
+    /// Overrides the template and settings temperature
+    pub temperature: Option<f64>,
+    /// Queue priority; interactive requests run before background work
+    pub priority: ai_queue::AIJobPriority,
+    /// Caller-chosen id reported in queue position events
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+    /// Clipboard history items to include as context, resolved from the database
+    #[serde(rename = "contextItemIds")]
+    pub context_item_ids: Vec<String>,
+    /// Add related recent items as context; defaults to the setting.
+    /// To drop an automatically picked item, resend with the items to keep
+    /// as contextItemIds and this turned off.
+    #[serde(rename = "autoContext")]
+    pub auto_context: Option<bool>,
+}
+
+/**
+ * Reusable prompt template with optional default model parameters
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub body: String,
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    #[serde(rename = "maxTokens")]
+    pub max_tokens: Option<u32>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: DateTime<Utc>,
 }
 
 /**
  * Application settings
+ *
+ * Missing fields take their defaults and unknown ones are ignored, so
+ * settings saved by older or newer versions still load. Updates from the
+ * frontend go through SettingsUpdate, which is strict.
  */
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
     pub hotkey_enabled: bool,
     pub hotkey_modifiers: Vec<String>,
@@ -73,9 +271,89 @@ pub struct AppSettings {
     pub theme: String,
     pub panel_opacity: f64,
     pub store_history: bool,
+    /// Clipboard items kept; older items are removed with their AI history
     pub max_history_items: u32,
     pub ai_enabled: bool,
+    /// GGUF model run in-process when built with the embedded-model feature (empty = none)
     pub model_path: String,
+    /// Reuse earlier AI results for identical requests
+    pub cache_ai_results: bool,
+    /// How long a cached AI result stays valid
+    pub ai_cache_ttl_hours: u32,
+    /// Maximum number of cached AI results kept
+    pub ai_cache_max_entries: u32,
+    /// Maximum total size of cached AI results
+    pub ai_cache_max_size_mb: u32,
+    /// Maximum tokens the model may generate per response
+    pub ai_max_tokens: u32,
+    /// Sampling temperature for AI generation (0-2)
+    pub ai_temperature: f64,
+    /// Model context window in tokens (prompt plus response)
+    pub ai_context_window: u32,
+    /// Ollama model used for AI requests
+    pub ai_model: String,
+    /// How many AI requests may run against the model at once
+    pub ai_max_concurrent_requests: u32,
+    /// Token budget shared by all context items of a request
+    pub ai_context_budget_tokens: u32,
+    /// Automatically add related recent clipboard items as context
+    pub ai_auto_context: bool,
+    /// Whether clipboard items are embedded in the background for semantic search
+    pub semantic_indexing_enabled: bool,
+    /// Ollama model used to compute embeddings
+    pub embedding_model: String,
+    /// Content types whose new items get an AI title and tags (opt-in)
+    pub ai_enrich_content_types: Vec<String>,
+    /// Replace secrets and personal data with placeholders before sending content to the model
+    pub ai_redaction_enabled: bool,
+    /// Generate a digest of the day's clipboard activity
+    pub digest_enabled: bool,
+    /// Local time (HH:MM) after which the daily digest is generated
+    pub digest_time: String,
+    /// Directory the digest is also written to as Markdown (empty = not written)
+    pub digest_output_dir: String,
+    /// Providers tried in order for AI requests (empty = local Ollama only)
+    pub ai_providers: Vec<providers::ProviderConfig>,
+    /// Allow providers that are not on this machine in the chain
+    pub use_online_fallback: bool,
+    /// Seconds each provider gets to answer before the next one is tried
+    pub max_processing_time: u32,
+    /// How long Ollama keeps the model loaded after a request (e.g. "10m", "-1" = forever)
+    pub ai_keep_alive: String,
+    /// Load the model in the background when the app starts
+    pub ai_warmup_on_startup: bool,
+    /// Load the model in the background when the panel is opened
+    pub ai_warmup_on_panel_open: bool,
+    /// Listen on the local IPC socket (Unix only); off by default
+    pub ipc_enabled: bool,
+}
+
+/**
+ * Settings sent to update_settings
+ *
+ * Unknown fields are rejected, so settings in another shape cannot be
+ * mistaken for an all-defaults update and saved over the real ones.
+ */
+#[derive(Debug, Clone)]
+pub struct SettingsUpdate(pub AppSettings);
+
+impl<'de> Deserialize<'de> for SettingsUpdate {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let fields = serde_json::Map::<String, serde_json::Value>::deserialize(deserializer)?;
+        let known = match serde_json::to_value(AppSettings::default()) {
+            Ok(serde_json::Value::Object(known)) => known,
+            _ => serde_json::Map::new(),
+        };
+        if let Some(unknown) = fields.keys().find(|name| !known.contains_key(name.as_str())) {
+            return Err(D::Error::custom(format!("unknown setting `{}`", unknown)));
+        }
+
+        serde_json::from_value(serde_json::Value::Object(fields))
+            .map(SettingsUpdate)
+            .map_err(D::Error::custom)
+    }
 }
 
 impl Default for AppSettings {
@@ -87,9 +365,34 @@ impl Default for AppSettings {
             theme: "system".to_string(),
             panel_opacity: 0.95,
             store_history: true,
-            max_history_items: 1000,
+            max_history_items: database::DEFAULT_MAX_HISTORY_ITEMS,
             ai_enabled: true,
             model_path: String::new(),
+            cache_ai_results: true,
+            ai_cache_ttl_hours: 24,
+            ai_cache_max_entries: 500,
+            ai_cache_max_size_mb: 20,
+            ai_max_tokens: 1000,
+            ai_temperature: 0.7,
+            ai_context_window: 8192,
+            ai_model: ai::DEFAULT_MODEL.to_string(),
+            ai_max_concurrent_requests: 1,
+            ai_context_budget_tokens: 2000,
+            ai_auto_context: false,
+            semantic_indexing_enabled: true,
+            embedding_model: embeddings::DEFAULT_EMBEDDING_MODEL.to_string(),
+            ai_enrich_content_types: Vec::new(),
+            ai_redaction_enabled: true,
+            digest_enabled: false,
+            digest_time: digest::DEFAULT_DIGEST_TIME.to_string(),
+            digest_output_dir: String::new(),
+            ai_providers: Vec::new(),
+            use_online_fallback: false,
+            max_processing_time: providers::DEFAULT_MAX_PROCESSING_TIME,
+            ai_keep_alive: "10m".to_string(),
+            ai_warmup_on_startup: true,
+            ai_warmup_on_panel_open: true,
+            ipc_enabled: false,
         }
     }
 }
@@ -120,6 +423,7 @@ fn setup_global_shortcut(app: &tauri::App) -> Result<(), Box<dyn std::error::Err
             let _ = main_window.set_focus();
             let _ = main_window.set_always_on_top(true);
             log::info!("Window shown via global shortcut");
+            warm_up_on_panel_open(&app_handle);
         } else {
             log::error!("Main window not found");
         }
@@ -149,11 +453,12 @@ fn setup_global_shortcut(app: &tauri::App) -> Result<(), Box<dyn std::error::Err
  * The clipboard monitor will automatically:
  * - Detect clipboard content changes
  * - Store new content in the database
- * - Maintain only the latest 20 items
+ * - Maintain only the latest max_history_items items
  * - Prevent duplicate storage within 1-hour windows
  */
 async fn setup_app_state(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
     let state: State<AppState> = app.state();
+    *state.capture_events.lock().unwrap() = Some(broadcast::channel(CAPTURE_EVENT_CAPACITY).0);
     
     // STEP 1: Create clipboard monitor instance
     let mut monitor = ClipboardMonitor::new();
@@ -162,6 +467,33 @@ async fn setup_app_state(app: &AppHandle) -> Result<(), Box<dyn std::error::Erro
     // This creates the database file and necessary tables if they don't exist
     monitor.initialize_database(&app).await?;
     
+    // Restore persisted settings before the first capture so it already
+    // uses the saved history limit and enrichment options
+    if let Some(db) = monitor.get_database() {
+        match db.load_settings().await {
+            Ok(Some(settings)) => *state.settings.lock().unwrap() = settings,
+            Ok(None) => log::info!("No saved settings found, using defaults"),
+            Err(e) => log::warn!("Failed to load saved settings: {}", e),
+        }
+    }
+    let settings = state.settings.lock().unwrap().clone();
+    state.ai_queue.set_max_concurrent(settings.ai_max_concurrent_requests as usize);
+    if let Some(db) = monitor.get_database() {
+        db.set_max_history_items(settings.max_history_items);
+    }
+
+    // Load the model now so the first request does not wait for it
+    if settings.ai_warmup_on_startup {
+        ollama::spawn_warm_up(&settings);
+    }
+
+    // Index clipboard history for semantic search in the background
+    if let Some(db) = monitor.get_database() {
+        embeddings::spawn_indexer(db.clone(), Arc::clone(&state.settings));
+        digest::spawn_scheduler(app.clone(), db.clone());
+        metrics::spawn_recorder(db);
+    }
+    
     // STEP 3: Start automatic clipboard monitoring
     // The monitor will check clipboard content every 1000ms (1 second)
     // and automatically store new content to the database
@@ -171,9 +503,13 @@ async fn setup_app_state(app: &AppHandle) -> Result<(), Box<dyn std::error::Erro
     // STEP 4: Store monitor in application state for access by Tauri commands
     // This allows frontend commands to access clipboard history through the monitor
     *state.clipboard_monitor.lock().unwrap() = Some(Arc::new(monitor));
+
+    // Let editors and scripts talk to the running app over a local socket
+    #[cfg(unix)]
+    ipc_server::set_enabled(app, settings.ipc_enabled);
     
     log::info!("🚀 Clipboard monitoring initialized and started with database persistence");
-    log::info!("📋 Monitoring interval: 1000ms | Max items: 20 | Database: SQLite");
+    log::info!("📋 Monitoring interval: 1000ms | Max items: {} | Database: SQLite", settings.max_history_items);
     log::info!("🔧 Environment: {}", if cfg!(debug_assertions) { "Development" } else { "Production" });
     
     // Test clipboard access immediately
@@ -235,11 +571,58 @@ pub fn run() {
             register_global_shortcut,
             unregister_global_shortcut,
             process_with_ai,
+            batch_process_with_ai,
             check_ai_status,
             toggle_panel_visibility,
             show_panel,
-            cancel_ai_requests
+            cancel_ai_requests,
+            list_builtin_transforms,
+            apply_builtin_transform,
+            run_builtin_transforms,
+            clear_ai_cache,
+            get_item_transformations,
+            reapply_transformation,
+            get_conversation,
+            send_follow_up,
+            copy_conversation_turn,
+            clear_conversation,
+            list_prompt_templates,
+            save_prompt_template,
+            delete_prompt_template,
+            export_prompt_templates,
+            import_prompt_templates,
+            list_ai_models,
+            set_active_model,
+            pull_ai_model,
+            semantic_search,
+            generate_digest,
+            get_ai_metrics,
+            record_transformation_feedback,
+            get_preset_rankings,
+            unload_ai_model
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_settings_ignore_unknown_fields() {
+        let settings: AppSettings = serde_json::from_str(r#"{"theme":"dark","removed_setting":true}"#).unwrap();
+        assert_eq!(settings.theme, "dark");
+        assert_eq!(settings.max_history_items, AppSettings::default().max_history_items);
+    }
+
+    #[test]
+    fn settings_updates_reject_unknown_fields() {
+        let error = serde_json::from_str::<SettingsUpdate>(r#"{"theme":"dark","ai":{"enabled":true}}"#).unwrap_err();
+        assert!(error.to_string().contains("unknown setting `ai`"), "{}", error);
+
+        let SettingsUpdate(settings) = serde_json::from_str(r#"{"theme":"dark","store_history":false}"#).unwrap();
+        assert_eq!(settings.theme, "dark");
+        assert!(!settings.store_history);
+    }
+}
@@ -2,5 +2,10 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // `wurdump <command>` runs the headless CLI instead of the app
+    if wurdump_lib::cli::is_cli_invocation() {
+        std::process::exit(wurdump_lib::cli::run());
+    }
+
     wurdump_lib::run()
 }
@@ -0,0 +1,244 @@
+/**
+ * AI call metrics for Wurdump
+ *
+ * Every call to a provider records its token usage, time to first token,
+ * total latency and cost. Calls are handed to a background recorder through
+ * a channel so the request path never waits on the database, and can be
+ * aggregated per model and day to judge whether a model is usable.
+ */
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use chrono::{DateTime, Local, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use crate::database::ClipboardDatabase;
+
+/**
+ * Measurements of a single provider call
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct AICallMetrics {
+    pub id: String,
+    pub provider: String,
+    pub model: String,
+    #[serde(rename = "promptTokens")]
+    pub prompt_tokens: u32,
+    #[serde(rename = "completionTokens")]
+    pub completion_tokens: u32,
+    /// Whether the token counts were estimated because the provider did not report usage
+    #[serde(rename = "usageEstimated")]
+    pub usage_estimated: bool,
+    #[serde(rename = "timeToFirstTokenMs")]
+    pub time_to_first_token_ms: Option<u64>,
+    #[serde(rename = "latencyMs")]
+    pub latency_ms: u64,
+    /// Cost in the provider's currency, when prices are configured
+    pub cost: Option<f64>,
+    pub success: bool,
+    pub error: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+/**
+ * Aggregated metrics for one model on one day
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelDayMetrics {
+    /// Local calendar day (YYYY-MM-DD)
+    pub day: String,
+    pub model: String,
+    pub calls: u32,
+    pub failures: u32,
+    #[serde(rename = "promptTokens")]
+    pub prompt_tokens: u64,
+    #[serde(rename = "completionTokens")]
+    pub completion_tokens: u64,
+    #[serde(rename = "avgTimeToFirstTokenMs")]
+    pub avg_time_to_first_token_ms: Option<f64>,
+    #[serde(rename = "avgLatencyMs")]
+    pub avg_latency_ms: f64,
+    /// Completion tokens per second of generation, after the first token
+    #[serde(rename = "avgTokensPerSecond")]
+    pub avg_tokens_per_second: Option<f64>,
+    pub cost: Option<f64>,
+}
+
+type MetricsChannel = (UnboundedSender<AICallMetrics>, Mutex<Option<UnboundedReceiver<AICallMetrics>>>);
+
+/// Calls recorded before the recorder starts wait in the channel
+static CHANNEL: Lazy<MetricsChannel> = Lazy::new(|| {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    (sender, Mutex::new(Some(receiver)))
+});
+
+/**
+ * Record the metrics of a provider call
+ */
+pub fn record(metrics: AICallMetrics) {
+    if CHANNEL.0.send(metrics).is_err() {
+        log::warn!("AI metrics recorder is not running");
+    }
+}
+
+/**
+ * Start writing recorded calls to the database
+ */
+pub fn spawn_recorder(database: ClipboardDatabase) {
+    let Some(mut receiver) = CHANNEL.1.lock().unwrap().take() else {
+        log::warn!("AI metrics recorder already started");
+        return;
+    };
+
+    tokio::spawn(async move {
+        while let Some(metrics) = receiver.recv().await {
+            if let Err(e) = database.store_ai_call_metrics(&metrics).await {
+                log::warn!("Failed to store AI call metrics: {}", e);
+            }
+        }
+    });
+}
+
+/**
+ * Aggregate calls per model and local day, newest day first
+ */
+pub fn aggregate_by_model_and_day(calls: &[AICallMetrics]) -> Vec<ModelDayMetrics> {
+    let mut groups: BTreeMap<(String, String), Vec<&AICallMetrics>> = BTreeMap::new();
+    for call in calls {
+        let day = call.created_at.with_timezone(&Local).format("%Y-%m-%d").to_string();
+        groups.entry((day, call.model.clone())).or_default().push(call);
+    }
+
+    let mut aggregated: Vec<ModelDayMetrics> = groups
+        .into_iter()
+        .map(|((day, model), calls)| {
+            let succeeded: Vec<&&AICallMetrics> = calls.iter().filter(|call| call.success).collect();
+
+            let ttfts: Vec<f64> = succeeded
+                .iter()
+                .filter_map(|call| call.time_to_first_token_ms)
+                .map(|ms| ms as f64)
+                .collect();
+
+            let speeds: Vec<f64> = succeeded
+                .iter()
+                .filter_map(|call| {
+                    let generation_ms = call.latency_ms.saturating_sub(call.time_to_first_token_ms?);
+                    (generation_ms > 0 && call.completion_tokens > 0)
+                        .then(|| call.completion_tokens as f64 * 1000.0 / generation_ms as f64)
+                })
+                .collect();
+
+            let costs: Vec<f64> = calls.iter().filter_map(|call| call.cost).collect();
+
+            ModelDayMetrics {
+                day,
+                model,
+                calls: calls.len() as u32,
+                failures: (calls.len() - succeeded.len()) as u32,
+                prompt_tokens: calls.iter().map(|call| call.prompt_tokens as u64).sum(),
+                completion_tokens: calls.iter().map(|call| call.completion_tokens as u64).sum(),
+                avg_time_to_first_token_ms: average(&ttfts),
+                avg_latency_ms: average(&succeeded.iter().map(|call| call.latency_ms as f64).collect::<Vec<_>>())
+                    .unwrap_or(0.0),
+                avg_tokens_per_second: average(&speeds),
+                cost: (!costs.is_empty()).then(|| costs.iter().sum()),
+            }
+        })
+        .collect();
+
+    aggregated.sort_by(|a, b| b.day.cmp(&a.day).then_with(|| a.model.cmp(&b.model)));
+    aggregated
+}
+
+fn average(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn call(model: &str, day: u32, success: bool, ttft_ms: Option<u64>, latency_ms: u64, cost: Option<f64>) -> AICallMetrics {
+        AICallMetrics {
+            id: uuid::Uuid::new_v4().to_string(),
+            provider: "Ollama".to_string(),
+            model: model.to_string(),
+            prompt_tokens: 100,
+            completion_tokens: if success { 50 } else { 0 },
+            usage_estimated: false,
+            time_to_first_token_ms: ttft_ms,
+            latency_ms,
+            cost,
+            success,
+            error: (!success).then(|| "timed out".to_string()),
+            created_at: Local.with_ymd_and_hms(2024, 5, day, 12, 0, 0).unwrap().with_timezone(&Utc),
+        }
+    }
+
+    #[test]
+    fn groups_by_model_and_day_newest_first() {
+        let calls = vec![
+            call("small", 1, true, Some(100), 600, None),
+            call("large", 2, true, Some(100), 600, None),
+            call("small", 2, true, Some(100), 600, None),
+        ];
+
+        let keys: Vec<_> = aggregate_by_model_and_day(&calls)
+            .into_iter()
+            .map(|metrics| (metrics.day, metrics.model))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("2024-05-02".to_string(), "large".to_string()),
+                ("2024-05-02".to_string(), "small".to_string()),
+                ("2024-05-01".to_string(), "small".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_calls_count_but_do_not_skew_timings() {
+        let calls = vec![
+            call("small", 1, true, Some(200), 1200, Some(0.01)),
+            call("small", 1, true, Some(400), 900, Some(0.02)),
+            // Failed before any token arrived
+            call("small", 1, false, None, 30_000, None),
+        ];
+
+        let metrics = &aggregate_by_model_and_day(&calls)[0];
+        assert_eq!((metrics.calls, metrics.failures), (3, 1));
+        assert_eq!((metrics.prompt_tokens, metrics.completion_tokens), (300, 100));
+        assert_eq!(metrics.avg_time_to_first_token_ms, Some(300.0));
+        assert_eq!(metrics.avg_latency_ms, 1050.0);
+        // 50 tokens in 1000 ms and 50 tokens in 500 ms
+        assert_eq!(metrics.avg_tokens_per_second, Some(75.0));
+        assert!((metrics.cost.unwrap() - 0.03).abs() < 1e-9);
+    }
+
+    #[test]
+    fn missing_measurements_stay_empty() {
+        let calls = vec![
+            call("small", 1, true, None, 800, None),
+            call("small", 1, false, None, 100, None),
+        ];
+
+        let metrics = &aggregate_by_model_and_day(&calls)[0];
+        assert_eq!(metrics.avg_time_to_first_token_ms, None);
+        assert_eq!(metrics.avg_tokens_per_second, None);
+        assert_eq!(metrics.cost, None);
+        assert_eq!(metrics.avg_latency_ms, 800.0);
+    }
+
+    #[test]
+    fn only_failures_have_zero_latency() {
+        let metrics = &aggregate_by_model_and_day(&[call("small", 1, false, None, 5000, None)])[0];
+        assert_eq!((metrics.calls, metrics.failures), (1, 1));
+        assert_eq!(metrics.avg_latency_ms, 0.0);
+        assert!(aggregate_by_model_and_day(&[]).is_empty());
+    }
+}
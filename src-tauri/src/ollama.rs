@@ -0,0 +1,536 @@
+/**
+ * Ollama model management for Wurdump
+ *
+ * Lists installed models, pulls missing ones through the Ollama HTTP API
+ * (reporting download progress as it streams in), diagnoses the health
+ * of the local server, and loads or unloads models ahead of use.
+ */
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use crate::ai::{HTTP_CLIENT, OLLAMA_BASE_URL};
+use crate::embedded;
+use crate::providers::{self, ProviderConfig, ProviderKind};
+use crate::AppSettings;
+
+/// How long a pull may go without receiving any data before it is abandoned
+const PULL_STALL_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Upper bound for a whole pull; large models can take hours on slow links
+const PULL_TOTAL_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Timeout for each health check request (generous for a busy server)
+const STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Loading a large model from disk can take minutes
+const WARM_UP_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Opening the panel again within this time does not trigger another warm-up
+const WARM_UP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// When the last warm-up started
+static LAST_WARM_UP: Mutex<Option<Instant>> = Mutex::new(None);
+
+/**
+ * Classified reason why the AI service could not be used
+ */
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AIStatusError {
+    /// Nothing is listening on the endpoint
+    ConnectionRefused { message: String },
+    /// The server accepted the connection but did not answer in time
+    Timeout { message: String },
+    /// The server answered with a non-success status code
+    HttpStatus { status: u16, message: String },
+    /// The server answered, but not with the JSON Ollama returns
+    InvalidJson { message: String },
+    /// Any other transport failure
+    Network { message: String },
+}
+
+/**
+ * Detailed health of the local AI service
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct AIStatus {
+    pub ollama_running: bool,
+    /// Endpoint that was checked
+    pub endpoint: String,
+    pub server_version: Option<String>,
+    /// Configured model name
+    pub model: String,
+    /// Whether the configured model is installed
+    pub has_model: bool,
+    /// Whether the configured model is currently loaded in memory
+    pub model_loaded: bool,
+    /// Round-trip time of the version request
+    pub latency_ms: Option<u64>,
+    pub error: Option<AIStatusError>,
+    /// Human-readable summary with the suggested fix
+    pub message: String,
+}
+
+/**
+ * An installed local model
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AIModelInfo {
+    pub name: String,
+    /// Size on disk in bytes
+    pub size: u64,
+    pub family: Option<String>,
+    #[serde(rename = "parameterSize")]
+    pub parameter_size: Option<String>,
+    #[serde(rename = "quantizationLevel")]
+    pub quantization_level: Option<String>,
+    #[serde(rename = "modifiedAt")]
+    pub modified_at: Option<String>,
+    pub digest: Option<String>,
+    /// Whether this is the model selected in settings
+    #[serde(rename = "isActive")]
+    pub is_active: bool,
+}
+
+/**
+ * Progress update emitted while a model is being pulled
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelPullProgress {
+    pub model: String,
+    pub status: String,
+    pub digest: Option<String>,
+    /// Total bytes of the layer currently downloading
+    pub total: Option<u64>,
+    /// Bytes of the current layer downloaded so far
+    pub completed: Option<u64>,
+    /// Download progress of the current layer (0-100)
+    pub percent: Option<f64>,
+    pub done: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<TagsModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsModel {
+    name: String,
+    #[serde(default)]
+    size: u64,
+    modified_at: Option<String>,
+    digest: Option<String>,
+    #[serde(default)]
+    details: TagsModelDetails,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TagsModelDetails {
+    family: Option<String>,
+    parameter_size: Option<String>,
+    quantization_level: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullStatusLine {
+    #[serde(default)]
+    status: String,
+    digest: Option<String>,
+    total: Option<u64>,
+    completed: Option<u64>,
+    error: Option<String>,
+}
+
+/**
+ * List the models installed in the local Ollama server
+ *
+ * Parameters:
+ * - active_model: The configured model name, used to flag the active entry
+ */
+pub async fn list_models(active_model: &str) -> Result<Vec<AIModelInfo>, String> {
+    let response = HTTP_CLIENT
+        .get(format!("{}/api/tags", OLLAMA_BASE_URL))
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| {
+            if e.is_connect() {
+                "Cannot connect to AI service. Please start Ollama: ollama serve".to_string()
+            } else {
+                format!("Failed to list models: {}", e)
+            }
+        })?;
+
+    if !response.status().is_success() {
+        return Err(format!("AI service error: {}", response.status()));
+    }
+
+    let tags: TagsResponse = response.json().await
+        .map_err(|e| format!("Failed to parse model list: {}", e))?;
+
+    let models = tags.models
+        .into_iter()
+        .map(|model| AIModelInfo {
+            is_active: model_matches(&model.name, active_model),
+            name: model.name,
+            size: model.size,
+            family: model.details.family,
+            parameter_size: model.details.parameter_size,
+            quantization_level: model.details.quantization_level,
+            modified_at: model.modified_at,
+            digest: model.digest,
+        })
+        .collect();
+
+    Ok(models)
+}
+
+/**
+ * Check the health of the local Ollama server and the configured model
+ *
+ * Never fails: problems are reported through AIStatus.error and a message
+ * describing how to fix them.
+ */
+pub async fn check_status(model: &str) -> AIStatus {
+    let mut status = AIStatus {
+        ollama_running: false,
+        endpoint: OLLAMA_BASE_URL.to_string(),
+        server_version: None,
+        model: model.to_string(),
+        has_model: false,
+        model_loaded: false,
+        latency_ms: None,
+        error: None,
+        message: String::new(),
+    };
+
+    // Reachability, version and round-trip latency
+    let started_at = Instant::now();
+    let version = match get_json(&format!("{}/api/version", OLLAMA_BASE_URL)).await {
+        Ok(version) => version,
+        Err(error) => {
+            status.message = status_error_message(&error);
+            status.error = Some(error);
+            return status;
+        }
+    };
+    status.latency_ms = Some(started_at.elapsed().as_millis() as u64);
+    status.ollama_running = true;
+    status.server_version = version["version"].as_str().map(str::to_string);
+
+    // Installed models
+    match get_json(&format!("{}/api/tags", OLLAMA_BASE_URL)).await {
+        Ok(tags) => {
+            status.has_model = model_names(&tags).iter().any(|name| model_matches(name, model));
+        }
+        Err(error) => {
+            status.message = status_error_message(&error);
+            status.error = Some(error);
+            return status;
+        }
+    }
+
+    // Models currently loaded in memory (older servers lack this endpoint)
+    match get_json(&format!("{}/api/ps", OLLAMA_BASE_URL)).await {
+        Ok(running) => {
+            status.model_loaded = model_names(&running).iter().any(|name| model_matches(name, model));
+        }
+        Err(e) => log::debug!("Could not query loaded models: {:?}", e),
+    }
+
+    status.message = if !status.has_model {
+        format!(
+            "Ollama is running but {} is not installed. Download it from the model list or run: ollama pull {}",
+            model, model
+        )
+    } else if status.model_loaded {
+        "AI features are ready!".to_string()
+    } else {
+        format!("AI features are ready. {} will be loaded on first use, which can take a while.", model)
+    };
+
+    status
+}
+
+/**
+ * GET a JSON document, classifying any failure
+ */
+async fn get_json(url: &str) -> Result<serde_json::Value, AIStatusError> {
+    let response = HTTP_CLIENT
+        .get(url)
+        .timeout(STATUS_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| {
+            if e.is_timeout() {
+                AIStatusError::Timeout { message: e.to_string() }
+            } else if e.is_connect() {
+                AIStatusError::ConnectionRefused { message: e.to_string() }
+            } else {
+                AIStatusError::Network { message: e.to_string() }
+            }
+        })?;
+
+    let status_code = response.status();
+    if !status_code.is_success() {
+        return Err(AIStatusError::HttpStatus {
+            status: status_code.as_u16(),
+            message: response.text().await.unwrap_or_default(),
+        });
+    }
+
+    response.json::<serde_json::Value>().await.map_err(|e| {
+        if e.is_timeout() {
+            AIStatusError::Timeout { message: e.to_string() }
+        } else {
+            AIStatusError::InvalidJson { message: e.to_string() }
+        }
+    })
+}
+
+/**
+ * Extract model names from a /api/tags or /api/ps response
+ */
+fn model_names(response: &serde_json::Value) -> Vec<String> {
+    response["models"]
+        .as_array()
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|model| model["name"].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/**
+ * Describe a status error together with the most likely fix
+ */
+fn status_error_message(error: &AIStatusError) -> String {
+    match error {
+        AIStatusError::ConnectionRefused { .. } => {
+            "Ollama not running. Please start it with: ollama serve".to_string()
+        }
+        AIStatusError::Timeout { .. } => format!(
+            "Ollama did not respond within {}s. It may be busy loading a model; wait a moment or restart it.",
+            STATUS_TIMEOUT.as_secs()
+        ),
+        AIStatusError::HttpStatus { status, .. } => format!(
+            "Ollama returned HTTP {}. Make sure port 11434 is served by Ollama, then restart it.",
+            status
+        ),
+        AIStatusError::InvalidJson { .. } => format!(
+            "Something answered on {} but it does not look like Ollama. Stop the other service or update Ollama.",
+            OLLAMA_BASE_URL
+        ),
+        AIStatusError::Network { message } => format!("Network error while contacting Ollama: {}", message),
+    }
+}
+
+/**
+ * Check whether an installed model name refers to the requested model
+ *
+ * Ollama reports untagged models with an implicit ":latest" suffix.
+ */
+pub fn model_matches(installed: &str, requested: &str) -> bool {
+    installed == requested || installed == format!("{}:latest", requested)
+}
+
+/**
+ * Pull a model, reporting progress for every status line Ollama streams back
+ *
+ * Returns Err("Request cancelled by user") if a cancellation signal arrives.
+ */
+pub async fn pull_model(
+    model: &str,
+    cancel_rx: &mut broadcast::Receiver<()>,
+    mut on_progress: impl FnMut(ModelPullProgress),
+) -> Result<(), String> {
+    let mut response = HTTP_CLIENT
+        .post(format!("{}/api/pull", OLLAMA_BASE_URL))
+        .timeout(PULL_TOTAL_TIMEOUT)
+        .json(&serde_json::json!({ "model": model, "stream": true }))
+        .send()
+        .await
+        .map_err(|e| {
+            if e.is_connect() {
+                "Cannot connect to AI service. Please start Ollama: ollama serve".to_string()
+            } else {
+                format!("Failed to start model pull: {}", e)
+            }
+        })?;
+
+    if !response.status().is_success() {
+        return Err(format!("AI service error: {} - {}",
+                          response.status(),
+                          response.text().await.unwrap_or_else(|_| "Unknown error".to_string())));
+    }
+
+    // The body is newline-delimited JSON; lines may be split across chunks
+    let mut buffer: Vec<u8> = Vec::new();
+    loop {
+        let chunk = tokio::select! {
+            chunk = tokio::time::timeout(PULL_STALL_TIMEOUT, response.chunk()) => chunk
+                .map_err(|_| format!("Model pull stalled: no data for {}s", PULL_STALL_TIMEOUT.as_secs()))?
+                .map_err(|e| format!("Model pull interrupted: {}", e))?,
+            _ = cancel_rx.recv() => {
+                return Err("Request cancelled by user".to_string());
+            }
+        };
+
+        let Some(chunk) = chunk else {
+            break;
+        };
+        buffer.extend_from_slice(&chunk);
+
+        while let Some(newline) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=newline).collect();
+            if handle_pull_line(model, &line, &mut on_progress)? {
+                return Ok(());
+            }
+        }
+    }
+
+    if handle_pull_line(model, &buffer, &mut on_progress)? {
+        return Ok(());
+    }
+
+    Err("Model pull ended before Ollama reported success".to_string())
+}
+
+/**
+ * Parse one NDJSON status line; returns true once the pull has succeeded
+ */
+fn handle_pull_line(
+    model: &str,
+    line: &[u8],
+    on_progress: &mut impl FnMut(ModelPullProgress),
+) -> Result<bool, String> {
+    let text = String::from_utf8_lossy(line);
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(false);
+    }
+
+    let status: PullStatusLine = serde_json::from_str(text)
+        .map_err(|e| format!("Unexpected pull response: {}", e))?;
+
+    if let Some(error) = status.error {
+        return Err(format!("Model pull failed: {}", error));
+    }
+
+    let done = status.status == "success";
+    let percent = match (status.completed, status.total) {
+        (Some(completed), Some(total)) if total > 0 => Some(completed as f64 / total as f64 * 100.0),
+        _ => None,
+    };
+
+    on_progress(ModelPullProgress {
+        model: model.to_string(),
+        status: status.status,
+        digest: status.digest,
+        total: status.total,
+        completed: status.completed,
+        percent,
+        done,
+    });
+
+    Ok(done)
+}
+
+/**
+ * Load a model into memory so the next request does not wait for it
+ *
+ * A generate request without a prompt only loads the model.
+ */
+pub async fn warm_up(provider: &ProviderConfig) -> Result<(), String> {
+    let mut body = serde_json::json!({ "model": provider.model });
+    if let Some(keep_alive) = &provider.keep_alive {
+        body["keep_alive"] = providers::keep_alive_value(keep_alive);
+    }
+
+    let started_at = Instant::now();
+    post_generate(provider, &body, WARM_UP_TIMEOUT).await?;
+    log::info!("🔥 Warmed up {} in {}ms", provider.model, started_at.elapsed().as_millis());
+    Ok(())
+}
+
+/**
+ * Unload a model to free the memory it uses
+ */
+pub async fn unload(provider: &ProviderConfig) -> Result<(), String> {
+    let body = serde_json::json!({ "model": provider.model, "keep_alive": 0 });
+    post_generate(provider, &body, STATUS_TIMEOUT).await?;
+    log::info!("💤 Unloaded {}", provider.model);
+    Ok(())
+}
+
+/**
+ * Warm up the first provider of the chain in the background
+ *
+ * Embedded models are loaded in-process. Does nothing when AI is disabled,
+ * the first provider is an OpenAI-compatible service, or a warm-up
+ * started within the last minute.
+ */
+pub fn spawn_warm_up(settings: &AppSettings) {
+    if !settings.ai_enabled {
+        return;
+    }
+
+    let Some(provider) = providers::provider_chain(settings, None).into_iter().next() else {
+        return;
+    };
+    if provider.kind == ProviderKind::OpenAI {
+        return;
+    }
+
+    {
+        let mut last = LAST_WARM_UP.lock().unwrap();
+        if last.is_some_and(|started| started.elapsed() < WARM_UP_INTERVAL) {
+            return;
+        }
+        *last = Some(Instant::now());
+    }
+
+    // May be called from non-async contexts such as the global shortcut handler
+    tauri::async_runtime::spawn(async move {
+        let result = match provider.kind {
+            ProviderKind::Embedded => embedded::warm_up(&provider).await,
+            _ => warm_up(&provider).await,
+        };
+        if let Err(e) = result {
+            log::warn!("⚠️ Model warm-up failed: {}", e);
+        }
+    });
+}
+
+/**
+ * POST to /api/generate on an Ollama provider
+ */
+async fn post_generate(provider: &ProviderConfig, body: &serde_json::Value, timeout: Duration) -> Result<(), String> {
+    let response = HTTP_CLIENT
+        .post(format!("{}/api/generate", provider.base_url.trim_end_matches('/')))
+        .timeout(timeout)
+        .json(body)
+        .send()
+        .await
+        .map_err(|e| {
+            if e.is_connect() {
+                "Cannot connect to AI service. Please start Ollama: ollama serve".to_string()
+            } else {
+                format!("Network error: {}", e)
+            }
+        })?;
+
+    if !response.status().is_success() {
+        return Err(format!("AI service error: {} - {}",
+                          response.status(),
+                          response.text().await.unwrap_or_else(|_| "Unknown error".to_string())));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,429 @@
+/**
+ * Validation and repair of AI output for Wurdump
+ *
+ * Works out which format the answer should be in (JSON, YAML, code in a
+ * given language, or free text), strips markdown fences and chatter around
+ * it, and checks that it parses. The outcome of that check determines the
+ * confidence reported for the transformation.
+ */
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// "to JSON", "into yaml", "as Python", "in TypeScript", ...
+static TARGET_FORMAT_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:to|into|as|in)\s+(?:valid\s+|a\s+|an\s+)?(json|ya?ml|rust|python|javascript|js|typescript|ts|golang|go)\b").unwrap()
+});
+
+/// Conversions to a format that cannot be validated (CSV, Markdown, ...)
+static CONVERSION_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:convert|translate|transform|rewrite|port)\b").unwrap()
+});
+
+/// Requests that ask for prose about the content rather than new content
+static PROSE_REQUEST_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\b(?:explain|summari[sz]e|summary|describe|review|why|what|how|document|analy[sz]e|comment)\b").unwrap()
+});
+
+static FENCE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)```[\w+#.-]*[ \t]*\r?\n(.*?)\r?\n?[ \t]*```").unwrap());
+
+static LEADING_CHATTER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^\s*(?:here(?:'s| is| are)|sure|certainly|below|the following|okay|ok)\b[^\n]*\n+").unwrap()
+});
+
+static TRAILING_CHATTER_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)\n+\s*(?:let me know|hope this|feel free|note:|i(?:'ve| have) )[^\n]*\s*$").unwrap()
+});
+
+static YAML_KEY_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?m)^\s*(?:- )?["']?[\w.-]+["']?:(?:\s|$)"#).unwrap());
+
+/**
+ * Format the model's answer is expected to have
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Free text; not validated
+    Text,
+    Json,
+    Yaml,
+    /// Source code in the given language
+    Code(String),
+}
+
+impl OutputFormat {
+    /**
+     * Human-readable name used in prompts and messages
+     */
+    pub fn name(&self) -> String {
+        match self {
+            OutputFormat::Text => "text".to_string(),
+            OutputFormat::Json => "JSON".to_string(),
+            OutputFormat::Yaml => "YAML".to_string(),
+            OutputFormat::Code(language) => format!("{} code", language),
+        }
+    }
+}
+
+/**
+ * How validation of an answer went
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationOutcome {
+    /// Free text, nothing to validate
+    Unchecked,
+    /// Valid as returned
+    Valid,
+    /// Valid after removing fences or chatter
+    Cleaned,
+    /// Valid after asking the model to fix its answer
+    Repaired,
+    /// Still invalid after the repair attempt
+    Invalid,
+}
+
+impl ValidationOutcome {
+    /**
+     * Confidence reported for an answer with this outcome
+     */
+    pub fn confidence(&self) -> f64 {
+        match self {
+            ValidationOutcome::Valid => 0.95,
+            ValidationOutcome::Cleaned => 0.9,
+            ValidationOutcome::Unchecked => 0.8,
+            ValidationOutcome::Repaired => 0.75,
+            ValidationOutcome::Invalid => 0.3,
+        }
+    }
+}
+
+/**
+ * Work out the expected output format from the instruction and the source
+ *
+ * Requests for explanations produce text. Otherwise an explicit target
+ * ("convert to JSON", "now in TypeScript") wins, and conversions to other
+ * formats produce text. Anything else is expected in the same format as
+ * the source content. When unsure this leans towards text, since an answer
+ * wrongly treated as text is merely not validated.
+ */
+pub fn expected_format(instruction: &str, content_type: &str, code_language: Option<&str>, content: &str) -> OutputFormat {
+    explicit_format(instruction).unwrap_or_else(|| source_format(content_type, code_language, content))
+}
+
+/**
+ * Work out the expected output format for a follow-up in a conversation
+ *
+ * "Shorter" after "now in TypeScript" should still produce TypeScript, so
+ * the most recent instruction that implies a format decides. Instructions
+ * are given oldest first, ending with the follow-up itself.
+ */
+pub fn expected_follow_up_format(instructions: &[&str], content_type: &str, code_language: Option<&str>, content: &str) -> OutputFormat {
+    instructions
+        .iter()
+        .rev()
+        .find_map(|instruction| explicit_format(instruction))
+        .unwrap_or_else(|| source_format(content_type, code_language, content))
+}
+
+/**
+ * Format implied by the wording of an instruction, if any
+ */
+fn explicit_format(instruction: &str) -> Option<OutputFormat> {
+    if PROSE_REQUEST_REGEX.is_match(instruction) {
+        return Some(OutputFormat::Text);
+    }
+
+    if let Some(caps) = TARGET_FORMAT_REGEX.captures(instruction) {
+        return Some(match caps[1].to_lowercase().as_str() {
+            "json" => OutputFormat::Json,
+            "yaml" | "yml" => OutputFormat::Yaml,
+            "js" | "javascript" => OutputFormat::Code("javascript".to_string()),
+            "ts" | "typescript" => OutputFormat::Code("typescript".to_string()),
+            "go" | "golang" => OutputFormat::Code("go".to_string()),
+            language => OutputFormat::Code(language.to_string()),
+        });
+    }
+
+    if CONVERSION_REGEX.is_match(instruction) {
+        return Some(OutputFormat::Text);
+    }
+
+    None
+}
+
+/**
+ * Format of the source content itself
+ */
+fn source_format(content_type: &str, code_language: Option<&str>, content: &str) -> OutputFormat {
+    match (content_type, code_language) {
+        ("json", _) => OutputFormat::Json,
+        ("code", Some(language)) => OutputFormat::Code(language.to_string()),
+        _ if looks_like_yaml(content) => OutputFormat::Yaml,
+        _ => OutputFormat::Text,
+    }
+}
+
+/**
+ * Whether text is a YAML document (several `key:` lines that parse as YAML)
+ */
+fn looks_like_yaml(content: &str) -> bool {
+    YAML_KEY_REGEX.find_iter(content).count() >= 2
+        && matches!(
+            serde_yaml::from_str::<serde_yaml::Value>(content),
+            Ok(serde_yaml::Value::Mapping(_)) | Ok(serde_yaml::Value::Sequence(_))
+        )
+}
+
+/**
+ * Strip markdown fences and conversational text around a structured answer
+ */
+pub fn clean_output(output: &str, format: &OutputFormat) -> String {
+    if *format == OutputFormat::Text {
+        return output.to_string();
+    }
+
+    // Prefer the largest fenced block; small ones are usually examples in the chatter
+    let fenced = FENCE_REGEX
+        .captures_iter(output)
+        .filter_map(|caps| caps.get(1))
+        .max_by_key(|block| block.as_str().len())
+        .map(|block| block.as_str().to_string());
+
+    let mut cleaned = fenced.unwrap_or_else(|| {
+        let without_leading = LEADING_CHATTER_REGEX.replace(output, "");
+        TRAILING_CHATTER_REGEX.replace(&without_leading, "").into_owned()
+    });
+
+    if *format == OutputFormat::Json {
+        if let Some(json) = extract_json(&cleaned) {
+            cleaned = json;
+        }
+    }
+
+    cleaned.trim().to_string()
+}
+
+/**
+ * Cut the outermost JSON object or array out of surrounding text
+ */
+fn extract_json(text: &str) -> Option<String> {
+    let start = text.find(['{', '['])?;
+    let closing = if text[start..].starts_with('{') { '}' } else { ']' };
+    let end = text.rfind(closing)?;
+    (end > start).then(|| text[start..=end].to_string())
+}
+
+/**
+ * Check that an answer parses in the expected format
+ *
+ * Returns the parser's error message when it does not.
+ */
+pub fn validate_output(output: &str, format: &OutputFormat) -> Result<(), String> {
+    match format {
+        OutputFormat::Text => Ok(()),
+        OutputFormat::Json => serde_json::from_str::<serde_json::Value>(output)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        OutputFormat::Yaml => serde_yaml::from_str::<serde_yaml::Value>(output)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        OutputFormat::Code(language) if language == "rust" => validate_rust(output),
+        OutputFormat::Code(language) if language == "json" => validate_output(output, &OutputFormat::Json),
+        OutputFormat::Code(language) => check_brackets(output, language),
+    }
+}
+
+/**
+ * Build the prompt asking the model to fix an invalid answer
+ */
+pub fn repair_prompt(output: &str, format: &OutputFormat, error: &str) -> String {
+    format!(
+        "The following output should be valid {name}, but parsing it failed with: {error}\n\nReturn only the corrected {name}, with no explanation and no markdown fences.\n\n{output}",
+        name = format.name(),
+        error = error,
+        output = output
+    )
+}
+
+/**
+ * Parse Rust as a file, falling back to a block of statements or an expression
+ */
+fn validate_rust(code: &str) -> Result<(), String> {
+    let file_error = match syn::parse_file(code) {
+        Ok(_) => return Ok(()),
+        Err(e) => e,
+    };
+
+    // Snippets are often function bodies rather than whole files
+    if syn::parse_str::<syn::Block>(&format!("{{\n{}\n}}", code)).is_ok() {
+        return Ok(());
+    }
+
+    Err(file_error.to_string())
+}
+
+/**
+ * Check that brackets are balanced, ignoring string literals and comments
+ */
+fn check_brackets(code: &str, language: &str) -> Result<(), String> {
+    let hash_comments = matches!(language, "python" | "ruby" | "shell" | "bash");
+    let mut stack: Vec<(char, usize)> = Vec::new();
+    let mut string_quote: Option<char> = None;
+    let mut escaped = false;
+    let mut in_line_comment = false;
+    let mut in_block_comment = false;
+    let mut line = 1;
+    let mut chars = code.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+            in_line_comment = false;
+        }
+
+        if in_line_comment {
+            continue;
+        }
+        if in_block_comment {
+            if c == '*' && chars.peek() == Some(&'/') {
+                chars.next();
+                in_block_comment = false;
+            }
+            continue;
+        }
+        if let Some(quote) = string_quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == quote {
+                string_quote = None;
+            }
+            continue;
+        }
+
+        match c {
+            '"' | '\'' | '`' => string_quote = Some(c),
+            '#' if hash_comments => in_line_comment = true,
+            '/' if !hash_comments && chars.peek() == Some(&'/') => in_line_comment = true,
+            '/' if !hash_comments && chars.peek() == Some(&'*') => {
+                chars.next();
+                in_block_comment = true;
+            }
+            '(' | '[' | '{' => stack.push((c, line)),
+            ')' | ']' | '}' => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                match stack.pop() {
+                    Some((open, _)) if open == expected => {}
+                    Some((open, open_line)) => {
+                        return Err(format!("'{}' on line {} does not match '{}' opened on line {}", c, line, open, open_line));
+                    }
+                    None => return Err(format!("Unexpected '{}' on line {}", c, line)),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(quote) = string_quote {
+        return Err(format!("Unterminated string starting with {}", quote));
+    }
+    if let Some((open, open_line)) = stack.pop() {
+        return Err(format!("'{}' opened on line {} is never closed", open, open_line));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explicit_targets_win_over_the_source() {
+        assert_eq!(expected_format("Convert to JSON", "text", None, "a,b"), OutputFormat::Json);
+        assert_eq!(expected_format("rewrite as TS", "code", Some("python"), "x = 1"), OutputFormat::Code("typescript".to_string()));
+        assert_eq!(expected_format("convert into yml", "json", None, "{}"), OutputFormat::Yaml);
+        assert_eq!(expected_format("Convert to CSV", "json", None, "{}"), OutputFormat::Text);
+    }
+
+    #[test]
+    fn prose_requests_are_text() {
+        assert_eq!(expected_format("Explain this in Python terms", "code", Some("rust"), "fn main() {}"), OutputFormat::Text);
+        assert_eq!(expected_format("Summarize", "json", None, "{}"), OutputFormat::Text);
+    }
+
+    #[test]
+    fn source_format_is_the_fallback() {
+        assert_eq!(expected_format("Make it shorter", "json", None, "{}"), OutputFormat::Json);
+        assert_eq!(expected_format("Fix the bug", "code", Some("rust"), "fn main() {}"), OutputFormat::Code("rust".to_string()));
+        assert_eq!(expected_format("Fix typos", "text", None, "name: app\nversion: 2\n"), OutputFormat::Yaml);
+        assert_eq!(expected_format("Fix typos", "text", None, "Thanks for the notes.\nSee you tomorrow."), OutputFormat::Text);
+    }
+
+    #[test]
+    fn follow_ups_keep_the_latest_explicit_format() {
+        let instructions = ["Convert to JSON", "now in TypeScript", "shorter"];
+        assert_eq!(
+            expected_follow_up_format(&instructions, "text", None, "a,b"),
+            OutputFormat::Code("typescript".to_string())
+        );
+        assert_eq!(expected_follow_up_format(&["shorter"], "json", None, "{}"), OutputFormat::Json);
+    }
+
+    #[test]
+    fn clean_output_strips_fences_and_chatter() {
+        let fenced = "Here is the JSON:\n\n```json\n{\"a\": 1}\n```\n\nLet me know if you need more.";
+        assert_eq!(clean_output(fenced, &OutputFormat::Json), "{\"a\": 1}");
+
+        let chatty = "Sure! Here you go:\n{\"a\": [1, 2]}\nHope this helps!";
+        assert_eq!(clean_output(chatty, &OutputFormat::Json), "{\"a\": [1, 2]}");
+
+        let code = "Certainly, here's the code:\nfn main() {}\nNote: untested.";
+        assert_eq!(clean_output(code, &OutputFormat::Code("rust".to_string())), "fn main() {}");
+
+        let text = "Sure, ```not code```";
+        assert_eq!(clean_output(text, &OutputFormat::Text), text);
+    }
+
+    #[test]
+    fn validates_structured_formats() {
+        assert!(validate_output("{\"a\": 1}", &OutputFormat::Json).is_ok());
+        assert!(validate_output("{\"a\": 1,}", &OutputFormat::Json).is_err());
+        assert!(validate_output("a: 1\nb: [2, 3]\n", &OutputFormat::Yaml).is_ok());
+        assert!(validate_output("a: [1, 2\n", &OutputFormat::Yaml).is_err());
+        assert!(validate_output("anything", &OutputFormat::Text).is_ok());
+    }
+
+    #[test]
+    fn validates_rust_files_and_snippets() {
+        let rust = OutputFormat::Code("rust".to_string());
+        assert!(validate_output("fn main() { println!(\"hi\"); }", &rust).is_ok());
+        assert!(validate_output("let x = 1;\nx + 1", &rust).is_ok());
+        assert!(validate_output("fn main( {", &rust).is_err());
+    }
+
+    #[test]
+    fn bracket_check_ignores_strings_and_comments() {
+        let js = OutputFormat::Code("javascript".to_string());
+        assert!(validate_output("const s = \"(\"; // )\n/* { */ f([1, 2]);", &js).is_ok());
+        assert_eq!(
+            validate_output("f(\n  [1, 2\n);", &js).unwrap_err(),
+            "')' on line 3 does not match '[' opened on line 2"
+        );
+        assert!(validate_output("if (x) {", &js).unwrap_err().contains("never closed"));
+        assert!(validate_output("s = 'abc", &js).unwrap_err().contains("Unterminated"));
+
+        let python = OutputFormat::Code("python".to_string());
+        assert!(validate_output("x = [1, 2]  # ]\nprint(x)", &python).is_ok());
+    }
+
+    #[test]
+    fn confidence_follows_the_outcome() {
+        assert!(ValidationOutcome::Valid.confidence() > ValidationOutcome::Cleaned.confidence());
+        assert!(ValidationOutcome::Repaired.confidence() > ValidationOutcome::Invalid.confidence());
+    }
+}
@@ -0,0 +1,214 @@
+/**
+ * Prompt template support for Wurdump
+ *
+ * Renders reusable prompt templates with placeholders such as {{content}},
+ * {{language}}, {{content_type}} and {{date}}, and converts template sets
+ * to and from TOML so curated prompts can be shared.
+ */
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use crate::PromptTemplate;
+
+static PLACEHOLDER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*(\w+)\s*\}\}").unwrap());
+
+/**
+ * Values substituted into template placeholders
+ */
+#[derive(Debug, Clone)]
+pub struct TemplateVariables {
+    pub content: String,
+    pub language: Option<String>,
+    pub content_type: String,
+    pub date: String,
+}
+
+/**
+ * Render a template body, replacing known placeholders
+ *
+ * Unknown placeholders are left untouched so typos stay visible in the prompt.
+ */
+pub fn render_template(body: &str, variables: &TemplateVariables) -> String {
+    PLACEHOLDER_REGEX
+        .replace_all(body, |caps: &Captures| match &caps[1] {
+            "content" => variables.content.clone(),
+            "language" => variables.language.clone().unwrap_or_else(|| "plain text".to_string()),
+            "content_type" => variables.content_type.clone(),
+            "date" => variables.date.clone(),
+            _ => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+/**
+ * Template fields sent by the frontend when creating or updating a template
+ */
+#[derive(Debug, Clone, Deserialize)]
+pub struct PromptTemplateInput {
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub body: String,
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    #[serde(rename = "maxTokens")]
+    pub max_tokens: Option<u32>,
+}
+
+/**
+ * TOML document holding a shareable set of templates
+ */
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TemplateFile {
+    #[serde(default)]
+    pub templates: Vec<TemplateEntry>,
+}
+
+/**
+ * A template as written in TOML (no ids or timestamps)
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateEntry {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+impl From<&PromptTemplate> for TemplateEntry {
+    fn from(template: &PromptTemplate) -> Self {
+        Self {
+            name: template.name.clone(),
+            description: template.description.clone(),
+            body: template.body.clone(),
+            model: template.model.clone(),
+            temperature: template.temperature,
+            max_tokens: template.max_tokens,
+        }
+    }
+}
+
+/**
+ * Serialize templates to a TOML document
+ */
+pub fn export_templates(templates: &[PromptTemplate]) -> Result<String, String> {
+    let file = TemplateFile {
+        templates: templates.iter().map(TemplateEntry::from).collect(),
+    };
+    toml::to_string_pretty(&file).map_err(|e| format!("Failed to export templates: {}", e))
+}
+
+/**
+ * Parse a TOML document into template entries
+ */
+pub fn parse_templates(toml_text: &str) -> Result<Vec<TemplateEntry>, String> {
+    let file: TemplateFile = toml::from_str(toml_text)
+        .map_err(|e| format!("Invalid template file: {}", e))?;
+
+    for entry in &file.templates {
+        if entry.name.trim().is_empty() {
+            return Err("Every template needs a name".to_string());
+        }
+        if entry.body.trim().is_empty() {
+            return Err(format!("Template '{}' has an empty body", entry.name));
+        }
+    }
+
+    Ok(file.templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn variables(content: &str) -> TemplateVariables {
+        TemplateVariables {
+            content: content.to_string(),
+            language: None,
+            content_type: "code".to_string(),
+            date: "2024-05-01".to_string(),
+        }
+    }
+
+    fn template(name: &str, body: &str) -> PromptTemplate {
+        PromptTemplate {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            description: String::new(),
+            body: body.to_string(),
+            model: None,
+            temperature: None,
+            max_tokens: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn renders_known_placeholders() {
+        let rendered = render_template(
+            "Explain this {{ content_type }} in {{language}} ({{date}}):\n{{content}}",
+            &variables("x = 1"),
+        );
+        assert_eq!(rendered, "Explain this code in plain text (2024-05-01):\nx = 1");
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders() {
+        let rendered = render_template("{{contnet}} and {{content}}", &variables("text"));
+        assert_eq!(rendered, "{{contnet}} and text");
+    }
+
+    #[test]
+    fn does_not_expand_placeholders_inside_values() {
+        // Clipboard content that looks like a template must come through verbatim
+        let rendered = render_template("Summarize: {{content}}", &variables("{{date}} {{content}}"));
+        assert_eq!(rendered, "Summarize: {{date}} {{content}}");
+    }
+
+    #[test]
+    fn rejects_invalid_template_files() {
+        assert!(parse_templates("templates = [").unwrap_err().starts_with("Invalid template file"));
+        assert!(parse_templates("[[templates]]\nname = \"No body\"").is_err());
+        assert_eq!(
+            parse_templates("[[templates]]\nname = \" \"\nbody = \"{{content}}\"").unwrap_err(),
+            "Every template needs a name"
+        );
+        assert_eq!(
+            parse_templates("[[templates]]\nname = \"Empty\"\nbody = \"  \"").unwrap_err(),
+            "Template 'Empty' has an empty body"
+        );
+        assert!(parse_templates("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn export_and_import_round_trip() {
+        let mut tuned = template("Review", "Review this {{language}} code:\n\n{{content}}\n");
+        tuned.description = "Line-by-line review".to_string();
+        tuned.model = Some("llama3.2".to_string());
+        tuned.temperature = Some(0.2);
+        tuned.max_tokens = Some(512);
+        let templates = vec![tuned, template("Quote \"it\"", "Say {{content}}")];
+
+        let imported = parse_templates(&export_templates(&templates).unwrap()).unwrap();
+
+        assert_eq!(imported.len(), templates.len());
+        for (entry, original) in imported.iter().zip(&templates) {
+            assert_eq!(entry.name, original.name);
+            assert_eq!(entry.description, original.description);
+            assert_eq!(entry.body, original.body);
+            assert_eq!(entry.model, original.model);
+            assert_eq!(entry.temperature, original.temperature);
+            assert_eq!(entry.max_tokens, original.max_tokens);
+        }
+    }
+}
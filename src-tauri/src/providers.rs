@@ -0,0 +1,544 @@
+/**
+ * AI provider chains for Wurdump
+ *
+ * A request is sent to an ordered list of providers, each with its own
+ * model, for example a small local model first and a larger one after it.
+ * Providers are either an Ollama server, spoken to through its native chat
+ * API so keep_alive can be set, any OpenAI-compatible chat completions
+ * endpoint, or a GGUF model run in-process. Remote providers are only used
+ * when online fallback is enabled.
+ */
+
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::ai::{HTTP_CLIENT, OLLAMA_BASE_URL};
+use crate::embedded;
+use crate::redaction::redact_for_log;
+use crate::AppSettings;
+
+/// Seconds a provider may take to answer before the next one is tried
+pub const DEFAULT_MAX_PROCESSING_TIME: u32 = 120;
+
+/// Stands in for a saved API key in settings sent to the frontend
+pub const API_KEY_MASK: &str = "********";
+
+/**
+ * API spoken by a provider
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// Ollama server; chat requests go to the native <baseUrl>/api/chat
+    Ollama,
+    /// OpenAI-compatible server; chat requests go to <baseUrl>/chat/completions
+    OpenAI,
+    /// GGUF model run on the CPU inside the app (needs the embedded-model feature)
+    Embedded,
+}
+
+/**
+ * One entry in the provider chain
+ *
+ * Debug output masks the API key so settings can be logged safely.
+ */
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    /// Name reported on results, e.g. "Local Ollama"
+    pub name: String,
+    pub kind: ProviderKind,
+    /// Unused by embedded providers
+    #[serde(rename = "baseUrl", default)]
+    pub base_url: String,
+    pub model: String,
+    /// Bearer token for OpenAI-compatible providers
+    #[serde(rename = "apiKey", default)]
+    pub api_key: Option<String>,
+    /// Price per million prompt tokens, used for cost metrics
+    #[serde(rename = "inputCostPerMillion", default)]
+    pub input_cost_per_million: Option<f64>,
+    /// Price per million completion tokens, used for cost metrics
+    #[serde(rename = "outputCostPerMillion", default)]
+    pub output_cost_per_million: Option<f64>,
+    /// How long Ollama keeps the model loaded after a request (e.g. "10m", "-1")
+    #[serde(rename = "keepAlive", default)]
+    pub keep_alive: Option<String>,
+    /// GGUF file run by an embedded provider
+    #[serde(rename = "modelPath", default)]
+    pub model_path: Option<String>,
+}
+
+impl std::fmt::Debug for ProviderConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProviderConfig")
+            .field("name", &self.name)
+            .field("kind", &self.kind)
+            .field("base_url", &self.base_url)
+            .field("model", &self.model)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("input_cost_per_million", &self.input_cost_per_million)
+            .field("output_cost_per_million", &self.output_cost_per_million)
+            .field("keep_alive", &self.keep_alive)
+            .field("model_path", &self.model_path)
+            .finish()
+    }
+}
+
+impl ProviderConfig {
+    /**
+     * The local Ollama server running the given model
+     */
+    pub fn local_ollama(model: &str) -> Self {
+        Self {
+            name: "Ollama".to_string(),
+            kind: ProviderKind::Ollama,
+            base_url: OLLAMA_BASE_URL.to_string(),
+            model: model.to_string(),
+            api_key: None,
+            input_cost_per_million: None,
+            output_cost_per_million: None,
+            keep_alive: None,
+            model_path: None,
+        }
+    }
+
+    /**
+     * The GGUF model at the given path, run in-process
+     */
+    pub fn embedded(model_path: &str) -> Self {
+        let model = std::path::Path::new(model_path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| model_path.to_string());
+
+        Self {
+            name: "Embedded".to_string(),
+            kind: ProviderKind::Embedded,
+            base_url: String::new(),
+            model,
+            api_key: None,
+            input_cost_per_million: None,
+            output_cost_per_million: None,
+            keep_alive: None,
+            model_path: Some(model_path.to_string()),
+        }
+    }
+
+    /**
+     * Whether the provider runs on this machine
+     */
+    pub fn is_local(&self) -> bool {
+        if self.kind == ProviderKind::Embedded {
+            return true;
+        }
+
+        let host = reqwest::Url::parse(&self.base_url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host.to_string()));
+        matches!(host.as_deref(), Some("localhost" | "127.0.0.1" | "[::1]" | "::1"))
+    }
+
+    /**
+     * Cost of a call, when prices are configured
+     */
+    pub fn cost(&self, prompt_tokens: u32, completion_tokens: u32) -> Option<f64> {
+        if self.input_cost_per_million.is_none() && self.output_cost_per_million.is_none() {
+            return None;
+        }
+        let input = self.input_cost_per_million.unwrap_or(0.0) * prompt_tokens as f64;
+        let output = self.output_cost_per_million.unwrap_or(0.0) * completion_tokens as f64;
+        Some((input + output) / 1_000_000.0)
+    }
+
+    fn chat_url(&self) -> String {
+        let base_url = self.base_url.trim_end_matches('/');
+        match self.kind {
+            ProviderKind::Ollama => format!("{}/api/chat", base_url),
+            ProviderKind::OpenAI => format!("{}/chat/completions", base_url),
+            ProviderKind::Embedded => unreachable!("embedded providers are not reached over HTTP"),
+        }
+    }
+}
+
+/**
+ * Replace saved API keys with API_KEY_MASK so they never leave the backend
+ */
+pub fn mask_api_keys(providers: &mut [ProviderConfig]) {
+    for provider in providers {
+        if provider.api_key.as_deref().is_some_and(|key| !key.is_empty()) {
+            provider.api_key = Some(API_KEY_MASK.to_string());
+        }
+    }
+}
+
+/**
+ * Put the saved API keys back where an update still carries the mask
+ *
+ * Providers are matched by name. A masked key whose provider no longer
+ * exists is dropped rather than saved as the mask.
+ */
+pub fn restore_masked_api_keys(providers: &mut [ProviderConfig], saved: &[ProviderConfig]) {
+    for provider in providers {
+        if provider.api_key.as_deref() != Some(API_KEY_MASK) {
+            continue;
+        }
+        provider.api_key = saved
+            .iter()
+            .find(|saved| saved.name == provider.name)
+            .and_then(|saved| saved.api_key.clone());
+    }
+}
+
+/**
+ * Providers to try for a request, in order; never empty
+ *
+ * Without a configured chain the local Ollama server runs the active model,
+ * preceded by the embedded model when this build supports it and a model
+ * path is set. A model requested explicitly (e.g. by a prompt template)
+ * replaces the model of the first provider that is not embedded. Remote
+ * providers are dropped unless online fallback is enabled. Ollama providers
+ * without their own keep_alive use the one from the settings, and embedded
+ * providers without their own file use the settings' model path.
+ */
+pub fn provider_chain(settings: &AppSettings, model_override: Option<&str>) -> Vec<ProviderConfig> {
+    let mut chain: Vec<ProviderConfig> = settings
+        .ai_providers
+        .iter()
+        .filter(|provider| settings.use_online_fallback || provider.is_local())
+        .cloned()
+        .collect();
+
+    if chain.is_empty() {
+        if embedded::AVAILABLE && !settings.model_path.trim().is_empty() {
+            chain.push(ProviderConfig::embedded(settings.model_path.trim()));
+        }
+        chain.push(ProviderConfig::local_ollama(&settings.ai_model));
+    }
+
+    if let Some(model) = model_override {
+        if let Some(provider) = chain.iter_mut().find(|provider| provider.kind != ProviderKind::Embedded) {
+            provider.model = model.to_string();
+        }
+    }
+
+    for provider in chain.iter_mut() {
+        match provider.kind {
+            ProviderKind::Ollama if provider.keep_alive.is_none() && !settings.ai_keep_alive.trim().is_empty() => {
+                provider.keep_alive = Some(settings.ai_keep_alive.trim().to_string());
+            }
+            ProviderKind::Embedded if provider.model_path.is_none() && !settings.model_path.trim().is_empty() => {
+                provider.model_path = Some(settings.model_path.trim().to_string());
+            }
+            _ => {}
+        }
+    }
+
+    chain
+}
+
+/**
+ * keep_alive as Ollama expects it: plain numbers are seconds, anything else a duration string
+ */
+pub fn keep_alive_value(keep_alive: &str) -> Value {
+    match keep_alive.trim().parse::<i64>() {
+        Ok(seconds) => Value::from(seconds),
+        Err(_) => Value::String(keep_alive.trim().to_string()),
+    }
+}
+
+/**
+ * Time each provider gets to answer a request
+ */
+pub fn request_timeout(settings: &AppSettings) -> Duration {
+    Duration::from_secs(settings.max_processing_time.max(1) as u64)
+}
+
+/**
+ * Whether an error means the next provider in the chain should be tried
+ */
+pub fn is_failover_error(error: &str) -> bool {
+    error.contains("timed out") || error.contains("Cannot connect") || error.contains("Cannot load")
+}
+
+/**
+ * Answer from a provider, with the usage it reported
+ */
+#[derive(Debug, Clone)]
+pub struct ChatCompletion {
+    pub content: String,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    /// Time until the first piece of the answer arrived
+    pub time_to_first_token: Option<Duration>,
+}
+
+/**
+ * Send a chat completion request to a provider and return the answer
+ *
+ * The answer is streamed so the time to the first token can be measured;
+ * OpenAI-compatible providers that ignore streaming and answer with a single
+ * JSON body are handled too. Embedded providers answer in-process. The
+ * request body is in chat completions form; its model is replaced with the
+ * provider's model.
+ */
+pub async fn chat_completion(provider: &ProviderConfig, request_body: &Value, timeout: Duration) -> Result<ChatCompletion, String> {
+    let body = match provider.kind {
+        ProviderKind::Ollama => ollama_chat_body(provider, request_body),
+        ProviderKind::OpenAI => {
+            let mut body = request_body.clone();
+            body["model"] = Value::String(provider.model.clone());
+            body["stream"] = Value::Bool(true);
+            body["stream_options"] = serde_json::json!({ "include_usage": true });
+            body
+        }
+        ProviderKind::Embedded => return embedded::chat_completion(provider, request_body, timeout).await,
+    };
+
+    let started_at = Instant::now();
+    let mut builder = HTTP_CLIENT
+        .post(provider.chat_url())
+        .header("Content-Type", "application/json")
+        .header("Accept", "text/event-stream, application/json")
+        .timeout(timeout)
+        .json(&body);
+    if let Some(api_key) = &provider.api_key {
+        builder = builder.bearer_auth(api_key);
+    }
+
+    let timed_out = || format!("AI request to {} timed out ({}s). The model might be busy or still loading.", provider.name, timeout.as_secs());
+
+    let mut response = builder.send().await.map_err(|e| {
+        if e.is_timeout() {
+            timed_out()
+        } else if e.is_connect() && provider.kind == ProviderKind::Ollama {
+            "Cannot connect to AI service. Please start Ollama: ollama serve".to_string()
+        } else if e.is_connect() {
+            format!("Cannot connect to AI service {} at {}", provider.name, provider.base_url)
+        } else {
+            format!("Network error: {}", e)
+        }
+    })?;
+
+    if !response.status().is_success() {
+        return Err(format!("AI service error: {} - {}",
+                          response.status(),
+                          response.text().await.unwrap_or_else(|_| "Unknown error".to_string())));
+    }
+
+    let mut completion = ChatCompletion {
+        content: String::new(),
+        prompt_tokens: None,
+        completion_tokens: None,
+        time_to_first_token: None,
+    };
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut raw_body = String::new();
+    let mut streamed = false;
+
+    loop {
+        let chunk = response.chunk().await.map_err(|e| {
+            if e.is_timeout() { timed_out() } else { format!("Network error: {}", e) }
+        })?;
+        let Some(chunk) = chunk else {
+            break;
+        };
+        buffer.extend_from_slice(&chunk);
+
+        // Ollama streams one JSON object per line, OpenAI-compatible servers one
+        // "data: {...}" event per line; lines are split on bytes so characters
+        // cut between chunks stay intact
+        while let Some(newline) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim();
+
+            if provider.kind == ProviderKind::Ollama {
+                if !line.is_empty() {
+                    streamed = true;
+                    read_ollama_event(line, started_at, &mut completion)?;
+                }
+                continue;
+            }
+
+            let Some(data) = line.strip_prefix("data:") else {
+                raw_body.push_str(line);
+                raw_body.push('\n');
+                continue;
+            };
+            streamed = true;
+
+            let data = data.trim();
+            if data == "[DONE]" {
+                continue;
+            }
+            let event: Value = serde_json::from_str(data)
+                .map_err(|e| format!("Failed to parse AI response as JSON: {}", e))?;
+            if let Some(error) = event.get("error") {
+                return Err(format!("AI service error: {}", error));
+            }
+
+            if let Some(delta) = event
+                .get("choices")
+                .and_then(|choices| choices.get(0))
+                .and_then(|choice| choice.get("delta"))
+                .and_then(|delta| delta.get("content"))
+                .and_then(|content| content.as_str())
+            {
+                record_delta(delta, started_at, &mut completion);
+            }
+            read_usage(&event, &mut completion);
+        }
+    }
+
+    let rest = String::from_utf8_lossy(&buffer).trim().to_string();
+    if provider.kind == ProviderKind::Ollama && !rest.is_empty() {
+        streamed = true;
+        read_ollama_event(&rest, started_at, &mut completion)?;
+    }
+
+    if !streamed {
+        // The provider answered with a single JSON body
+        raw_body.push_str(&rest);
+        let json: Value = serde_json::from_str(&raw_body)
+            .map_err(|e| format!("Failed to parse AI response as JSON: {}", e))?;
+
+        completion.content = json
+            .get("choices")
+            .and_then(|choices| choices.get(0))
+            .and_then(|choice| choice.get("message"))
+            .and_then(|message| message.get("content"))
+            .and_then(|content| content.as_str())
+            .ok_or_else(|| {
+                log::error!("Invalid AI response structure: {}", redact_for_log(&json.to_string()));
+                "Invalid response format from AI service".to_string()
+            })?
+            .to_string();
+        read_usage(&json, &mut completion);
+    }
+
+    if completion.content.trim().is_empty() {
+        return Err("AI returned empty response".to_string());
+    }
+
+    Ok(completion)
+}
+
+/**
+ * Build a native Ollama chat request from a chat completions body
+ */
+fn ollama_chat_body(provider: &ProviderConfig, request_body: &Value) -> Value {
+    let mut options = serde_json::Map::new();
+    if let Some(temperature) = request_body.get("temperature") {
+        options.insert("temperature".to_string(), temperature.clone());
+    }
+    if let Some(max_tokens) = request_body.get("max_tokens") {
+        options.insert("num_predict".to_string(), max_tokens.clone());
+    }
+
+    let mut body = serde_json::json!({
+        "model": provider.model,
+        "messages": request_body["messages"],
+        "stream": true,
+        "options": options,
+    });
+    if let Some(keep_alive) = &provider.keep_alive {
+        body["keep_alive"] = keep_alive_value(keep_alive);
+    }
+    body
+}
+
+/**
+ * Handle one line of a native Ollama chat stream
+ */
+fn read_ollama_event(line: &str, started_at: Instant, completion: &mut ChatCompletion) -> Result<(), String> {
+    let event: Value = serde_json::from_str(line)
+        .map_err(|e| format!("Failed to parse AI response as JSON: {}", e))?;
+    if let Some(error) = event.get("error").and_then(|error| error.as_str()) {
+        return Err(format!("AI service error: {}", error));
+    }
+
+    if let Some(delta) = event["message"]["content"].as_str() {
+        record_delta(delta, started_at, completion);
+    }
+
+    // The final event carries the token counts
+    if event["done"].as_bool() == Some(true) {
+        completion.prompt_tokens = event["prompt_eval_count"].as_u64().map(|count| count as u32);
+        completion.completion_tokens = event["eval_count"].as_u64().map(|count| count as u32);
+    }
+    Ok(())
+}
+
+/**
+ * Append a piece of the streamed answer, noting when the first one arrived
+ */
+fn record_delta(delta: &str, started_at: Instant, completion: &mut ChatCompletion) {
+    if completion.time_to_first_token.is_none() && !delta.is_empty() {
+        completion.time_to_first_token = Some(started_at.elapsed());
+    }
+    completion.content.push_str(delta);
+}
+
+/**
+ * Take token counts from the "usage" object of a response, if present
+ */
+fn read_usage(json: &Value, completion: &mut ChatCompletion) {
+    let Some(usage) = json.get("usage").filter(|usage| usage.is_object()) else {
+        return;
+    };
+
+    let count = |key: &str| usage.get(key).and_then(|value| value.as_u64()).map(|value| value as u32);
+    if let Some(prompt_tokens) = count("prompt_tokens") {
+        completion.prompt_tokens = Some(prompt_tokens);
+    }
+    if let Some(completion_tokens) = count("completion_tokens") {
+        completion.completion_tokens = Some(completion_tokens);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_output_masks_the_api_key() {
+        let mut provider = ProviderConfig::local_ollama("llama3");
+        provider.api_key = Some("sk-very-secret-key".to_string());
+        let debug = format!("{:?}", provider);
+        assert!(!debug.contains("sk-very-secret-key"));
+        assert!(debug.contains("<redacted>"));
+        assert!(debug.contains("llama3"));
+    }
+
+    #[test]
+    fn masked_api_keys_are_restored_from_the_saved_providers() {
+        let provider = |name: &str, api_key: Option<&str>| ProviderConfig {
+            name: name.to_string(),
+            kind: ProviderKind::OpenAI,
+            base_url: "https://api.example.com/v1".to_string(),
+            model: "gpt".to_string(),
+            api_key: api_key.map(str::to_string),
+            input_cost_per_million: None,
+            output_cost_per_million: None,
+            keep_alive: None,
+            model_path: None,
+        };
+        let saved = vec![provider("Cloud", Some("sk-cloud")), provider("Backup", Some("sk-backup")), provider("Local", None)];
+
+        let mut sent = saved.clone();
+        mask_api_keys(&mut sent);
+        assert_eq!(sent[0].api_key.as_deref(), Some(API_KEY_MASK));
+        assert_eq!(sent[2].api_key, None);
+
+        // Reordered, one key replaced, and a provider that was never saved
+        let mut updated = vec![
+            sent[1].clone(),
+            provider("Cloud", Some("sk-new")),
+            provider("Other", Some(API_KEY_MASK)),
+            sent[2].clone(),
+        ];
+        restore_masked_api_keys(&mut updated, &saved);
+        let keys: Vec<_> = updated.iter().map(|provider| provider.api_key.as_deref()).collect();
+        assert_eq!(keys, vec![Some("sk-backup"), Some("sk-new"), None, None]);
+    }
+}
@@ -0,0 +1,376 @@
+/**
+ * Splitting of AI answers into segments for Wurdump
+ *
+ * Answers that walk through several pieces of code come back as prose with
+ * fenced code blocks in between. Each block becomes a transformation of its
+ * own that can be copied separately, with the language taken from the fence
+ * or detected, and the surrounding prose as its description. The full answer
+ * stays the first transformation so history and conversations keep the
+ * whole reply.
+ */
+
+use once_cell::sync::Lazy;
+use uuid::Uuid;
+use crate::content_detection::ContentDetector;
+use crate::AITransformation;
+
+/// Answers with fewer code blocks are left whole
+const MIN_CODE_BLOCKS: usize = 2;
+
+/// Characters of prose kept in a description
+const DESCRIPTION_CHARS: usize = 300;
+
+static DETECTOR: Lazy<ContentDetector> = Lazy::new(ContentDetector::new);
+
+/**
+ * A part of an answer
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Prose(String),
+    Code { language: Option<String>, code: String },
+}
+
+/**
+ * A line made of a code fence (``` or ~~~, at least three) and its info string
+ */
+struct Fence<'a> {
+    marker: char,
+    len: usize,
+    info: &'a str,
+}
+
+/**
+ * A fence that has been opened but not closed yet
+ */
+struct OpenBlock<'a> {
+    fence: Fence<'a>,
+    /// Byte offset of the opening fence line
+    start: usize,
+    /// Byte offset of the first code line
+    code_start: usize,
+    /// Fences of the same length opened inside the block (e.g. a ```bash
+    /// example inside a ```markdown block) that still need their closing fence
+    nested: usize,
+}
+
+/**
+ * Split an answer into prose and fenced code blocks, in order
+ *
+ * A block ends at a bare fence of the same character that is at least as
+ * long as the opening one, so longer fences can wrap shorter ones. Empty
+ * prose between blocks is dropped. An unclosed fence is left as prose.
+ */
+pub fn parse_segments(answer: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut prose_start = 0;
+    let mut open: Option<OpenBlock> = None;
+    let mut offset = 0;
+
+    for line in answer.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let Some(fence) = parse_fence(line) else {
+            continue;
+        };
+
+        let Some(block) = open.as_mut() else {
+            open = Some(OpenBlock { fence, start: line_start, code_start: offset, nested: 0 });
+            continue;
+        };
+        if fence.marker != block.fence.marker || fence.len < block.fence.len {
+            continue;
+        }
+        if !fence.info.is_empty() {
+            if fence.len == block.fence.len {
+                block.nested += 1;
+            }
+            continue;
+        }
+        if block.nested > 0 {
+            block.nested -= 1;
+            continue;
+        }
+
+        push_prose(&mut segments, &answer[prose_start..block.start]);
+        let code = answer[block.code_start..line_start].trim_end().to_string();
+        let language = fence_language(block.fence.info)
+            .or_else(|| DETECTOR.detect_content(&code).code_language);
+        segments.push(Segment::Code { language, code });
+        prose_start = offset;
+        open = None;
+    }
+
+    push_prose(&mut segments, &answer[prose_start..]);
+    segments
+}
+
+/**
+ * The transformation followed by one transformation per code block
+ *
+ * Only answers with several code blocks are split; others are returned as is.
+ */
+pub fn split_transformation(transformation: AITransformation) -> Vec<AITransformation> {
+    let segments = parse_segments(&transformation.result);
+    let total = segments.iter().filter(|segment| matches!(segment, Segment::Code { .. })).count();
+    if total < MIN_CODE_BLOCKS {
+        return vec![transformation];
+    }
+
+    let prose: Vec<&str> = segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Prose(text) => Some(text.as_str()),
+            Segment::Code { .. } => None,
+        })
+        .collect();
+    let overall_description = (!prose.is_empty()).then(|| summarize(&prose.join(" ")));
+
+    let mut parts = Vec::with_capacity(total + 1);
+    let mut preceding_prose: Option<&str> = None;
+    let mut index = 0;
+
+    for segment in &segments {
+        match segment {
+            Segment::Prose(text) => preceding_prose = Some(text.as_str()),
+            Segment::Code { language, code } => {
+                index += 1;
+                let title = match language {
+                    Some(language) => format!("{} code ({} of {})", display_language(language), index, total),
+                    None => format!("Code ({} of {})", index, total),
+                };
+                // The paragraph right before a block usually says what it is
+                let description = preceding_prose
+                    .take()
+                    .and_then(|text| text.split("\n\n").last())
+                    .map(summarize)
+                    .or_else(|| overall_description.clone())
+                    .unwrap_or_else(|| format!("Code block {} of the answer", index));
+
+                parts.push(AITransformation {
+                    id: format!("ai-{}", Uuid::new_v4()),
+                    title,
+                    description,
+                    result: code.clone(),
+                    confidence: transformation.confidence,
+                    is_applied: false,
+                    transformation_type: "code_block".to_string(),
+                    is_cached: transformation.is_cached,
+                    provider: transformation.provider.clone(),
+                    model: transformation.model.clone(),
+                    context_sources: transformation.context_sources.clone(),
+                    language: language.clone(),
+                });
+            }
+        }
+    }
+
+    let mut full = transformation;
+    if let Some(description) = overall_description {
+        full.description = description;
+    }
+
+    log::info!("🧱 Split AI answer into {} code blocks", total);
+    let mut transformations = vec![full];
+    transformations.extend(parts);
+    transformations
+}
+
+/**
+ * Parse a line as a code fence, allowing up to three spaces of indentation
+ */
+fn parse_fence(line: &str) -> Option<Fence<'_>> {
+    let line = line.trim_end();
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let line = &line[indent..];
+    let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.chars().take_while(|c| *c == marker).count();
+    if len < 3 {
+        return None;
+    }
+    let info = line[len..].trim();
+    // Backticks in the info string mean this is inline code, not a fence
+    if marker == '`' && info.contains('`') {
+        return None;
+    }
+    Some(Fence { marker, len, info })
+}
+
+/**
+ * Language named by a fence info string such as "rust,ignore" or "py title=x"
+ */
+fn fence_language(info: &str) -> Option<String> {
+    let language = info
+        .trim_start_matches(['{', '.'])
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '}')
+        .next()
+        .unwrap_or_default();
+    normalize_language(language)
+}
+
+fn push_prose(segments: &mut Vec<Segment>, text: &str) {
+    let text = text.trim();
+    if !text.is_empty() {
+        segments.push(Segment::Prose(text.to_string()));
+    }
+}
+
+/**
+ * Canonical name for a fence info string ("rs" -> "rust"); None when empty
+ */
+fn normalize_language(info: &str) -> Option<String> {
+    let info = info.trim().to_lowercase();
+    let language = match info.as_str() {
+        "" => return None,
+        "rs" => "rust",
+        "py" | "python3" => "python",
+        "js" | "jsx" | "node" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "golang" => "go",
+        "sh" | "shell" | "zsh" | "console" => "bash",
+        "yml" => "yaml",
+        "c++" | "cc" | "hpp" => "cpp",
+        "cs" | "c#" => "csharp",
+        other => other,
+    };
+    Some(language.to_string())
+}
+
+fn display_language(language: &str) -> String {
+    match language {
+        "javascript" => "JavaScript".to_string(),
+        "typescript" => "TypeScript".to_string(),
+        "json" | "yaml" | "html" | "css" | "sql" | "toml" | "xml" => language.to_uppercase(),
+        "cpp" => "C++".to_string(),
+        "csharp" => "C#".to_string(),
+        _ => {
+            let mut chars = language.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+    }
+}
+
+/**
+ * Prose on a single line, shortened for a description
+ */
+fn summarize(text: &str) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let collapsed = collapsed.trim_end_matches(':').to_string();
+    if collapsed.chars().count() > DESCRIPTION_CHARS {
+        format!("{}…", collapsed.chars().take(DESCRIPTION_CHARS).collect::<String>())
+    } else {
+        collapsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(language: Option<&str>, code: &str) -> Segment {
+        Segment::Code { language: language.map(str::to_string), code: code.to_string() }
+    }
+
+    #[test]
+    fn splits_prose_and_code_in_order() {
+        let answer = "First:\n```py\nprint(1)\n```\nThen:\n```rs\nfn main() {}\n```\nDone.";
+        assert_eq!(
+            parse_segments(answer),
+            vec![
+                Segment::Prose("First:".to_string()),
+                code(Some("python"), "print(1)"),
+                Segment::Prose("Then:".to_string()),
+                code(Some("rust"), "fn main() {}"),
+                Segment::Prose("Done.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn longer_fences_wrap_shorter_ones() {
+        let answer = "Put this in the README:\n````markdown\n# Setup\n```bash\nnpm install\n```\n````\nThat's it.";
+        assert_eq!(
+            parse_segments(answer),
+            vec![
+                Segment::Prose("Put this in the README:".to_string()),
+                code(Some("markdown"), "# Setup\n```bash\nnpm install\n```"),
+                Segment::Prose("That's it.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn same_length_fences_with_info_strings_nest() {
+        let answer = "```markdown\nRun:\n```bash\nmake\n```\nThen test.\n```\nAfter.";
+        assert_eq!(
+            parse_segments(answer),
+            vec![
+                code(Some("markdown"), "Run:\n```bash\nmake\n```\nThen test."),
+                Segment::Prose("After.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_fence_stays_prose() {
+        let answer = "```rust\nfn main() {}\n```\nAnd then:\n```python\nprint(1)";
+        assert_eq!(
+            parse_segments(answer),
+            vec![
+                code(Some("rust"), "fn main() {}"),
+                Segment::Prose("And then:\n```python\nprint(1)".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn language_comes_from_the_first_word_of_the_info_string() {
+        let answer = "```rust,ignore\nlet x = 1;\n```\n~~~ yml title=config\na: 1\n~~~\n```{.python}\nx = 1\n```";
+        assert_eq!(
+            parse_segments(answer),
+            vec![
+                code(Some("rust"), "let x = 1;"),
+                code(Some("yaml"), "a: 1"),
+                code(Some("python"), "x = 1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn closing_fence_must_match_the_marker() {
+        let answer = "~~~text\n```\nstill inside\n~~~";
+        assert_eq!(parse_segments(answer), vec![code(Some("text"), "```\nstill inside")]);
+    }
+
+    #[test]
+    fn split_needs_several_code_blocks() {
+        let transformation = |result: &str| AITransformation {
+            id: "ai-1".to_string(),
+            title: "Answer".to_string(),
+            description: String::new(),
+            result: result.to_string(),
+            confidence: 0.8,
+            is_applied: false,
+            transformation_type: "custom".to_string(),
+            is_cached: false,
+            provider: None,
+            model: None,
+            context_sources: Vec::new(),
+            language: None,
+        };
+
+        assert_eq!(split_transformation(transformation("Only one:\n```rust\nfn a() {}\n```")).len(), 1);
+
+        let parts = split_transformation(transformation("The API:\n```ts\nexport {}\n```\nThe test:\n```ts\ntest()\n```"));
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[1].title, "TypeScript code (1 of 2)");
+        assert_eq!(parts[1].description, "The API");
+        assert_eq!(parts[2].result, "test()");
+    }
+}
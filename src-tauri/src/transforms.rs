@@ -0,0 +1,572 @@
+/**
+ * Built-in transformation engine for Wurdump
+ *
+ * Provides deterministic, offline transformations (formatting, encoding,
+ * case conversion, line utilities and hashing) that run instantly without
+ * calling the AI model. Results use the same AITransformation shape as the
+ * AI pipeline so the panel can show them side by side, even when Ollama is down.
+ */
+
+use base64::{engine::general_purpose, Engine as _};
+use md5::Md5;
+use once_cell::sync::Lazy;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use crate::AITransformation;
+
+/**
+ * Characters percent-encoded by url_encode: everything except RFC 3986 unreserved characters
+ */
+const URL_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+/**
+ * Shortest text base64_decode treats as Base64
+ */
+const MIN_BASE64_CHARS: usize = 8;
+
+/**
+ * Shared registry of built-in transforms
+ */
+pub static TRANSFORM_REGISTRY: Lazy<TransformRegistry> = Lazy::new(TransformRegistry::new);
+
+/**
+ * A single deterministic transformation
+ */
+#[derive(Debug, Clone, Serialize)]
+pub struct BuiltinTransform {
+    pub id: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    #[serde(rename = "transformationType")]
+    pub transformation_type: &'static str,
+    #[serde(skip)]
+    apply: fn(&str) -> Result<String, String>,
+}
+
+/**
+ * Registry of all built-in transforms, in display order
+ */
+pub struct TransformRegistry {
+    transforms: Vec<BuiltinTransform>,
+}
+
+impl TransformRegistry {
+    /**
+     * Create the registry with every built-in transform registered
+     */
+    pub fn new() -> Self {
+        let transforms = vec![
+            BuiltinTransform {
+                id: "json_pretty",
+                title: "Pretty-print JSON",
+                description: "Format JSON with two-space indentation",
+                transformation_type: "format_conversion",
+                apply: json_pretty,
+            },
+            BuiltinTransform {
+                id: "json_minify",
+                title: "Minify JSON",
+                description: "Remove all insignificant whitespace from JSON",
+                transformation_type: "format_conversion",
+                apply: json_minify,
+            },
+            BuiltinTransform {
+                id: "base64_encode",
+                title: "Base64 encode",
+                description: "Encode the text as standard Base64",
+                transformation_type: "format_conversion",
+                apply: base64_encode,
+            },
+            BuiltinTransform {
+                id: "base64_decode",
+                title: "Base64 decode",
+                description: "Decode standard or URL-safe Base64 into text",
+                transformation_type: "format_conversion",
+                apply: base64_decode,
+            },
+            BuiltinTransform {
+                id: "url_encode",
+                title: "URL encode",
+                description: "Percent-encode the text for use in a URL",
+                transformation_type: "format_conversion",
+                apply: url_encode,
+            },
+            BuiltinTransform {
+                id: "url_decode",
+                title: "URL decode",
+                description: "Decode percent-encoded text",
+                transformation_type: "format_conversion",
+                apply: url_decode,
+            },
+            BuiltinTransform {
+                id: "camel_case",
+                title: "camelCase",
+                description: "Convert each line to camelCase",
+                transformation_type: "format_conversion",
+                apply: camel_case,
+            },
+            BuiltinTransform {
+                id: "snake_case",
+                title: "snake_case",
+                description: "Convert each line to snake_case",
+                transformation_type: "format_conversion",
+                apply: snake_case,
+            },
+            BuiltinTransform {
+                id: "kebab_case",
+                title: "kebab-case",
+                description: "Convert each line to kebab-case",
+                transformation_type: "format_conversion",
+                apply: kebab_case,
+            },
+            BuiltinTransform {
+                id: "trim_whitespace",
+                title: "Trim whitespace",
+                description: "Remove trailing whitespace on every line and surrounding blank lines",
+                transformation_type: "cleanup",
+                apply: trim_whitespace,
+            },
+            BuiltinTransform {
+                id: "sort_lines",
+                title: "Sort lines",
+                description: "Sort lines alphabetically",
+                transformation_type: "cleanup",
+                apply: sort_lines,
+            },
+            BuiltinTransform {
+                id: "dedupe_lines",
+                title: "Remove duplicate lines",
+                description: "Keep the first occurrence of each line",
+                transformation_type: "cleanup",
+                apply: dedupe_lines,
+            },
+            BuiltinTransform {
+                id: "normalize_line_endings",
+                title: "Normalize line endings",
+                description: "Convert CRLF and CR line endings to LF",
+                transformation_type: "cleanup",
+                apply: normalize_line_endings,
+            },
+            BuiltinTransform {
+                id: "sha256",
+                title: "SHA-256 hash",
+                description: "Hex-encoded SHA-256 digest of the text",
+                transformation_type: "validation",
+                apply: sha256_hash,
+            },
+            BuiltinTransform {
+                id: "md5",
+                title: "MD5 hash",
+                description: "Hex-encoded MD5 digest of the text",
+                transformation_type: "validation",
+                apply: md5_hash,
+            },
+        ];
+
+        Self { transforms }
+    }
+
+    /**
+     * List every registered transform
+     */
+    pub fn transforms(&self) -> &[BuiltinTransform] {
+        &self.transforms
+    }
+
+    /**
+     * Look up a transform by id
+     */
+    pub fn get(&self, transform_id: &str) -> Option<&BuiltinTransform> {
+        self.transforms.iter().find(|t| t.id == transform_id)
+    }
+
+    /**
+     * Run a single transform and wrap the output as an AITransformation
+     */
+    pub fn apply(&self, transform_id: &str, content: &str) -> Result<AITransformation, String> {
+        let transform = self
+            .get(transform_id)
+            .ok_or_else(|| format!("Unknown transform: {}", transform_id))?;
+
+        let result = (transform.apply)(content)?;
+        Ok(transform.to_transformation(result))
+    }
+
+    /**
+     * Run every transform that applies to the content
+     *
+     * Transforms that fail (e.g. JSON formatting on non-JSON text) or that
+     * would leave the content unchanged are skipped. Line transforms keep the
+     * content's line endings, so unchanged text compares equal.
+     */
+    pub fn applicable(&self, content: &str) -> Vec<AITransformation> {
+        self.transforms
+            .iter()
+            .filter_map(|transform| {
+                let result = (transform.apply)(content).ok()?;
+                if result == content {
+                    return None;
+                }
+                Some(transform.to_transformation(result))
+            })
+            .collect()
+    }
+}
+
+impl Default for TransformRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuiltinTransform {
+    fn to_transformation(&self, result: String) -> AITransformation {
+        AITransformation {
+            // Unique per run so feedback on one result does not overwrite another's
+            id: format!("builtin-{}-{}", self.id, uuid::Uuid::new_v4()),
+            title: self.title.to_string(),
+            description: self.description.to_string(),
+            result,
+            confidence: 1.0,
+            is_applied: false,
+            transformation_type: self.transformation_type.to_string(),
+            is_cached: false,
+            provider: None,
+            model: None,
+            context_sources: Vec::new(),
+            language: None,
+        }
+    }
+}
+
+fn json_pretty(content: &str) -> Result<String, String> {
+    let value: serde_json::Value = serde_json::from_str(content.trim())
+        .map_err(|e| format!("Invalid JSON: {}", e))?;
+    serde_json::to_string_pretty(&value).map_err(|e| e.to_string())
+}
+
+fn json_minify(content: &str) -> Result<String, String> {
+    let value: serde_json::Value = serde_json::from_str(content.trim())
+        .map_err(|e| format!("Invalid JSON: {}", e))?;
+    serde_json::to_string(&value).map_err(|e| e.to_string())
+}
+
+fn base64_encode(content: &str) -> Result<String, String> {
+    Ok(general_purpose::STANDARD.encode(content.as_bytes()))
+}
+
+fn base64_decode(content: &str) -> Result<String, String> {
+    let compact: String = content.chars().filter(|c| !c.is_whitespace()).collect();
+    if compact.is_empty() {
+        return Err("Nothing to decode".to_string());
+    }
+    if !looks_like_base64(&compact) {
+        return Err("Content does not look like Base64".to_string());
+    }
+
+    let bytes = general_purpose::STANDARD
+        .decode(&compact)
+        .or_else(|_| general_purpose::URL_SAFE.decode(&compact))
+        .or_else(|_| general_purpose::STANDARD_NO_PAD.decode(&compact))
+        .or_else(|_| general_purpose::URL_SAFE_NO_PAD.decode(&compact))
+        .map_err(|e| format!("Invalid Base64: {}", e))?;
+
+    let decoded = String::from_utf8(bytes).map_err(|_| "Decoded Base64 is not valid UTF-8 text".to_string())?;
+    if decoded.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        return Err("Decoded Base64 is binary data".to_string());
+    }
+    Ok(decoded)
+}
+
+/**
+ * Whether text is plausibly Base64 rather than an ordinary word
+ *
+ * Short words like "test" decode to valid text too, so Base64 needs a
+ * minimum length and padding or characters that words rarely mix: digits,
+ * symbols from the Base64 alphabet, or both upper and lower case letters.
+ */
+fn looks_like_base64(compact: &str) -> bool {
+    if compact.len() < MIN_BASE64_CHARS {
+        return false;
+    }
+
+    let has = |predicate: fn(&char) -> bool| compact.chars().any(|c| predicate(&c));
+    compact.ends_with('=')
+        || has(|c| c.is_ascii_digit() || matches!(c, '+' | '/' | '-' | '_'))
+        || (has(char::is_ascii_uppercase) && has(char::is_ascii_lowercase))
+}
+
+fn url_encode(content: &str) -> Result<String, String> {
+    Ok(utf8_percent_encode(content, URL_ENCODE_SET).to_string())
+}
+
+fn url_decode(content: &str) -> Result<String, String> {
+    if !content.contains('%') && !content.contains('+') {
+        return Err("Content is not URL encoded".to_string());
+    }
+
+    let with_spaces = content.replace('+', " ");
+    percent_decode_str(&with_spaces)
+        .decode_utf8()
+        .map(|decoded| decoded.into_owned())
+        .map_err(|_| "Decoded URL is not valid UTF-8 text".to_string())
+}
+
+/**
+ * Split an identifier or phrase into lowercase words
+ *
+ * Handles camelCase, PascalCase, snake_case, kebab-case, dotted and
+ * space-separated input, keeping acronym runs like "HTTPServer" together.
+ */
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let chars: Vec<char> = line.chars().collect();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        if c.is_uppercase() && !current.is_empty() {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).map(|n| n.is_lowercase()).unwrap_or(false);
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_is_lower) {
+                words.push(std::mem::take(&mut current));
+            }
+        }
+
+        current.extend(c.to_lowercase());
+    }
+
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn convert_lines(content: &str, convert: impl Fn(Vec<String>) -> String) -> Result<String, String> {
+    // Only identifiers and short phrases make sense to re-case; skip code, JSON and prose with punctuation
+    let is_identifier_like = content
+        .chars()
+        .all(|c| c.is_alphanumeric() || c.is_whitespace() || matches!(c, '_' | '-' | '.'));
+    if !is_identifier_like {
+        return Err("Content is not a list of identifiers or phrases".to_string());
+    }
+
+    let converted: Vec<String> = content
+        .lines()
+        .map(|line| {
+            let words = split_words(line);
+            if words.is_empty() {
+                line.trim().to_string()
+            } else {
+                convert(words)
+            }
+        })
+        .collect();
+
+    if converted.iter().all(|line| line.is_empty()) {
+        return Err("No words to convert".to_string());
+    }
+
+    Ok(join_lines(&converted, content))
+}
+
+fn camel_case(content: &str) -> Result<String, String> {
+    convert_lines(content, |words| {
+        words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| if i == 0 { word.clone() } else { capitalize(word) })
+            .collect()
+    })
+}
+
+fn snake_case(content: &str) -> Result<String, String> {
+    convert_lines(content, |words| words.join("_"))
+}
+
+fn kebab_case(content: &str) -> Result<String, String> {
+    convert_lines(content, |words| words.join("-"))
+}
+
+fn trim_whitespace(content: &str) -> Result<String, String> {
+    let lines: Vec<&str> = content.lines().map(|line| line.trim_end()).collect();
+    let start = lines.iter().position(|line| !line.is_empty()).unwrap_or(lines.len());
+    let end = lines.iter().rposition(|line| !line.is_empty()).map(|i| i + 1).unwrap_or(start);
+    if start == end {
+        return Ok(String::new());
+    }
+    Ok(join_lines(&lines[start..end], content))
+}
+
+fn sort_lines(content: &str) -> Result<String, String> {
+    let mut lines: Vec<&str> = content.lines().collect();
+    if lines.len() < 2 {
+        return Err("Need at least two lines to sort".to_string());
+    }
+    lines.sort_unstable();
+    Ok(join_lines(&lines, content))
+}
+
+fn dedupe_lines(content: &str) -> Result<String, String> {
+    let mut seen = std::collections::HashSet::new();
+    let lines: Vec<&str> = content.lines().filter(|line| seen.insert(*line)).collect();
+    Ok(join_lines(&lines, content))
+}
+
+/**
+ * Join lines with the original content's line ending, keeping a final newline if it had one
+ */
+fn join_lines<S: AsRef<str>>(lines: &[S], original: &str) -> String {
+    let ending = if original.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut joined = lines.iter().map(|line| line.as_ref()).collect::<Vec<_>>().join(ending);
+    if original.ends_with('\n') {
+        joined.push_str(ending);
+    }
+    joined
+}
+
+fn normalize_line_endings(content: &str) -> Result<String, String> {
+    Ok(content.replace("\r\n", "\n").replace('\r', "\n"))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn sha256_hash(content: &str) -> Result<String, String> {
+    Ok(to_hex(&Sha256::digest(content.as_bytes())))
+}
+
+fn md5_hash(content: &str) -> Result<String, String> {
+    Ok(to_hex(&Md5::digest(content.as_bytes())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applicable_ids(content: &str) -> Vec<String> {
+        TRANSFORM_REGISTRY
+            .applicable(content)
+            .into_iter()
+            .filter_map(|t| crate::feedback::builtin_preset(&t.id).map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn json_formatting_round_trips() {
+        let pretty = json_pretty(r#"{"a":[1,2],"b":null}"#).unwrap();
+        assert_eq!(pretty, "{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": null\n}");
+        assert_eq!(json_minify(&pretty).unwrap(), r#"{"a":[1,2],"b":null}"#);
+        assert!(json_pretty("not json").is_err());
+    }
+
+    #[test]
+    fn base64_round_trips() {
+        let encoded = base64_encode("hello world").unwrap();
+        assert_eq!(encoded, "aGVsbG8gd29ybGQ=");
+        assert_eq!(base64_decode(&encoded).unwrap(), "hello world");
+        assert_eq!(base64_decode("aGVsbG8gd29ybGQ").unwrap(), "hello world");
+        assert_eq!(base64_decode("aGVsbG8g\nd29ybGQ=").unwrap(), "hello world");
+    }
+
+    #[test]
+    fn base64_decode_ignores_plain_words() {
+        assert!(base64_decode("test").is_err());
+        assert!(base64_decode("abcd").is_err());
+        assert!(base64_decode("username").is_err());
+        assert!(!applicable_ids("test").contains(&"base64_decode".to_string()));
+    }
+
+    #[test]
+    fn base64_decode_rejects_binary() {
+        assert!(base64_decode("AAECAwQFBgc=").is_err());
+    }
+
+    #[test]
+    fn url_encoding_round_trips() {
+        let encoded = url_encode("a b&c=d/é").unwrap();
+        assert_eq!(encoded, "a%20b%26c%3Dd%2F%C3%A9");
+        assert_eq!(url_decode(&encoded).unwrap(), "a b&c=d/é");
+        assert_eq!(url_decode("a+b").unwrap(), "a b");
+        assert!(url_decode("plain").is_err());
+    }
+
+    #[test]
+    fn splits_words_across_conventions() {
+        assert_eq!(split_words("parseHTTPServer"), vec!["parse", "http", "server"]);
+        assert_eq!(split_words("user_id-value.name"), vec!["user", "id", "value", "name"]);
+        assert_eq!(split_words("Version2Update"), vec!["version2", "update"]);
+    }
+
+    #[test]
+    fn converts_case_per_line() {
+        assert_eq!(camel_case("user name\nHTTP server").unwrap(), "userName\nhttpServer");
+        assert_eq!(snake_case("userName").unwrap(), "user_name");
+        assert_eq!(kebab_case("UserName\r\n").unwrap(), "user-name\r\n");
+        assert!(snake_case("fn main() {}").is_err());
+    }
+
+    #[test]
+    fn trims_whitespace() {
+        assert_eq!(trim_whitespace("\n\n  a  \nb\t\n\n").unwrap(), "  a\nb\n");
+        assert_eq!(trim_whitespace("a \r\nb\r\n").unwrap(), "a\r\nb\r\n");
+        assert_eq!(trim_whitespace(" \n ").unwrap(), "");
+    }
+
+    #[test]
+    fn line_transforms_keep_line_endings() {
+        assert_eq!(sort_lines("b\na\n").unwrap(), "a\nb\n");
+        assert_eq!(sort_lines("b\r\na\r\n").unwrap(), "a\r\nb\r\n");
+        assert_eq!(dedupe_lines("a\r\nb\r\na").unwrap(), "a\r\nb");
+        assert!(sort_lines("single").is_err());
+    }
+
+    #[test]
+    fn unchanged_line_transforms_are_not_offered() {
+        for content in ["a\nb\n", "a\r\nb\r\n"] {
+            let ids = applicable_ids(content);
+            assert!(!ids.contains(&"sort_lines".to_string()), "{:?}", content);
+            assert!(!ids.contains(&"dedupe_lines".to_string()), "{:?}", content);
+            assert!(!ids.contains(&"trim_whitespace".to_string()), "{:?}", content);
+        }
+        assert!(applicable_ids("a\r\nb\r\n").contains(&"normalize_line_endings".to_string()));
+    }
+
+    #[test]
+    fn normalizes_line_endings() {
+        assert_eq!(normalize_line_endings("a\r\nb\rc\n").unwrap(), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn hashes_match_known_digests() {
+        assert_eq!(sha256_hash("abc").unwrap(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(md5_hash("abc").unwrap(), "900150983cd24fb0d6963f7d28e17f72");
+    }
+
+    #[test]
+    fn apply_rejects_unknown_transforms() {
+        assert!(TRANSFORM_REGISTRY.apply("nope", "text").is_err());
+        let result = TRANSFORM_REGISTRY.apply("snake_case", "fooBar").unwrap();
+        assert_eq!(crate::feedback::builtin_preset(&result.id), Some("snake_case"));
+        let again = TRANSFORM_REGISTRY.apply("snake_case", "fooBar").unwrap();
+        assert_ne!(result.id, again.id);
+        assert_eq!(result.result, "foo_bar");
+    }
+}
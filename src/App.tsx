@@ -6,7 +6,7 @@
 import { useState, useEffect } from 'react';
 import { ClipboardPanel } from './components/ClipboardPanel';
 import { ErrorBoundary } from './components/ErrorBoundary';
-// import { SettingsService } from './services/tauri-commands';
+import { SettingsService } from './services/tauri-commands';
 import type { AppSettings } from './types/settings';
 import { DEFAULT_SETTINGS } from './types/settings';
 import './App.css';
@@ -22,7 +22,7 @@ function App() {
   // Application state
   const [isPanelVisible, setIsPanelVisible] = useState(true); // Start visible for development
   const [isSettingsVisible, setIsSettingsVisible] = useState(false);
-  const [_settings, setSettings] = useState<AppSettings>(DEFAULT_SETTINGS);
+  const [settings, setSettings] = useState<AppSettings>(DEFAULT_SETTINGS);
   const [isLoading, setIsLoading] = useState(true);
 
   /**
@@ -31,11 +31,8 @@ function App() {
   useEffect(() => {
     const loadSettings = async () => {
       try {
-        // Note: This will fail until we implement the backend properly
-        // For now, we'll use default settings
-        // const loadedSettings = await SettingsService.getSettings();
-        // setSettings(loadedSettings);
-        setSettings(DEFAULT_SETTINGS);
+        // Outside Tauri (plain browser dev server) there is no backend to ask
+        setSettings(isTauriContext() ? await SettingsService.getSettings() : DEFAULT_SETTINGS);
       } catch (error) {
         console.warn('Failed to load settings, using defaults:', error);
         setSettings(DEFAULT_SETTINGS);
@@ -87,6 +84,7 @@ function App() {
           isVisible={isPanelVisible}
           onClose={handleClosePanel}
           onOpenSettings={handleOpenSettings}
+          aiSettings={settings.ai}
         />
 
         {/* Settings Panel - TODO: Implement */}
@@ -1,15 +1,31 @@
 /**
  * AI Status Indicator component
- * Shows whether Ollama is running and gpt-oss model is available
+ * Shows whether Ollama is running and the configured model is available
  */
 
 import React, { useState, useEffect } from 'react';
 import { Sparkles, AlertCircle, CheckCircle, ExternalLink, RefreshCw } from 'lucide-react';
 import { invoke } from '@tauri-apps/api/core';
 
+/**
+ * Classified reason why the AI service could not be used
+ */
+type AIStatusError =
+  | { kind: 'connection_refused'; message: string }
+  | { kind: 'timeout'; message: string }
+  | { kind: 'http_status'; status: number; message: string }
+  | { kind: 'invalid_json'; message: string }
+  | { kind: 'network'; message: string };
+
 interface AIStatus {
   ollama_running: boolean;
-  has_gpt_oss: boolean;
+  endpoint: string;
+  server_version?: string;
+  model: string;
+  has_model: boolean;
+  model_loaded: boolean;
+  latency_ms?: number;
+  error?: AIStatusError;
   message: string;
 }
 
@@ -43,7 +59,10 @@ export const AIStatusIndicator: React.FC<AIStatusIndicatorProps> = ({
       console.error('Failed to check AI status:', error);
       setStatus({
         ollama_running: false,
-        has_gpt_oss: false,
+        endpoint: '',
+        model: '',
+        has_model: false,
+        model_loaded: false,
         message: 'Failed to check AI status',
       });
     } finally {
@@ -72,18 +91,18 @@ export const AIStatusIndicator: React.FC<AIStatusIndicatorProps> = ({
     return null;
   }
 
-  const isReady = status.ollama_running && status.has_gpt_oss;
-  const isPartiallyReady = status.ollama_running && !status.has_gpt_oss;
+  const isReady = status.ollama_running && status.has_model;
+  const isPartiallyReady = status.ollama_running && !status.has_model;
 
   // Compact view - just show an icon
   if (compact) {
     const getTooltipText = () => {
       if (isReady) {
-        return "AI Ready: Ollama running with gpt-oss model available";
+        return `AI Ready: Ollama running with ${status.model} available`;
       } else if (isPartiallyReady) {
-        return "AI Partially Ready: Ollama running but gpt-oss model not found";
+        return `AI Partially Ready: Ollama running but ${status.model} not found`;
       } else {
-        return "AI Not Available: Ollama not running";
+        return `AI Not Available: ${status.message}`;
       }
     };
 
@@ -137,18 +156,20 @@ export const AIStatusIndicator: React.FC<AIStatusIndicatorProps> = ({
           )}
           <span className="text-sm text-gray-700 dark:text-gray-300">
             Ollama: {status.ollama_running ? 'Running' : 'Not Running'}
+            {status.server_version && ` (v${status.server_version})`}
+            {status.latency_ms !== undefined && ` · ${status.latency_ms}ms`}
           </span>
         </div>
 
         {/* Model Status */}
         <div className="flex items-center gap-2">
-          {status.has_gpt_oss ? (
+          {status.has_model ? (
             <CheckCircle className="w-4 h-4 text-green-500" />
           ) : (
             <AlertCircle className="w-4 h-4 text-yellow-500" />
           )}
           <span className="text-sm text-gray-700 dark:text-gray-300">
-            gpt-oss model: {status.has_gpt_oss ? 'Available' : 'Not Found'}
+            {status.model || 'Model'}: {status.has_model ? (status.model_loaded ? 'Loaded' : 'Available') : 'Not Found'}
           </span>
         </div>
       </div>
@@ -168,7 +189,16 @@ export const AIStatusIndicator: React.FC<AIStatusIndicatorProps> = ({
           </h4>
           
           <div className="space-y-1 text-xs text-gray-600 dark:text-gray-400">
-            {!status.ollama_running && (
+            {!status.ollama_running && status.error?.kind !== 'connection_refused' && (
+              <div className="flex items-start gap-2">
+                <span className="font-mono bg-gray-100 dark:bg-gray-800 px-2 py-1 rounded">
+                  !
+                </span>
+                <p>{status.message}</p>
+              </div>
+            )}
+
+            {!status.ollama_running && status.error?.kind === 'connection_refused' && (
               <div className="flex items-start gap-2">
                 <span className="font-mono bg-gray-100 dark:bg-gray-800 px-2 py-1 rounded">
                   1.
@@ -187,16 +217,16 @@ export const AIStatusIndicator: React.FC<AIStatusIndicatorProps> = ({
               </div>
             )}
             
-            {status.ollama_running && !status.has_gpt_oss && (
+            {status.ollama_running && !status.has_model && (
               <div className="flex items-start gap-2">
                 <span className="font-mono bg-gray-100 dark:bg-gray-800 px-2 py-1 rounded">
                   2.
                 </span>
                 <div>
-                  <p>Download the gpt-oss model:</p>
+                  <p>Download the {status.model} model:</p>
                   <p className="mt-1">
                     <code className="bg-gray-100 dark:bg-gray-800 px-1 rounded">
-                      ollama pull gpt-oss:20b
+                      ollama pull {status.model}
                     </code>
                   </p>
                 </div>
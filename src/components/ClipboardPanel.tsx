@@ -9,6 +9,7 @@ import { useHotkeys } from 'react-hotkeys-hook';
 import { ClipboardService, AIService, PanelService } from '../services/tauri-commands';
 import { getPlatformShortcuts } from '../utils/platform';
 import type { ClipboardItem, AITransformation } from '../types/clipboard';
+import type { AISettings } from '../types/settings';
 import { ClipboardItemComponent } from './ClipboardItem';
 import { AITransformationList } from './AITransformationList';
 import { SearchInput } from './SearchInput';
@@ -23,6 +24,8 @@ interface ClipboardPanelProps {
   onClose: () => void;
   /** Callback when settings should be opened */
   onOpenSettings: () => void;
+  /** AI settings applied to requests from the panel */
+  aiSettings: AISettings;
 }
 
 /**
@@ -32,6 +35,7 @@ export const ClipboardPanel: React.FC<ClipboardPanelProps> = ({
   isVisible,
   onClose,
   onOpenSettings,
+  aiSettings,
 }) => {
   // State management
   const [currentContent, setCurrentContent] = useState<string>('');
@@ -124,7 +128,8 @@ export const ClipboardPanel: React.FC<ClipboardPanelProps> = ({
       const transformations = await AIService.processWithAI(
         currentContent, 
         customPrompt,
-        contextStrings.length > 0 ? contextStrings : undefined
+        contextStrings.length > 0 ? contextStrings : undefined,
+        { maxTokens: aiSettings.maxTokens, temperature: aiSettings.temperature }
       );
       
       // Check if request was cancelled
@@ -146,7 +151,7 @@ export const ClipboardPanel: React.FC<ClipboardPanelProps> = ({
       setIsProcessingAI(false);
       setAbortController(null);
     }
-  }, [currentContent, customPrompt, contextItems]);
+  }, [currentContent, customPrompt, contextItems, aiSettings]);
 
   /**
    * Cancel the current AI request
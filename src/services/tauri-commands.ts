@@ -4,8 +4,9 @@
  */
 
 import { invoke } from '@tauri-apps/api/core';
-import type { ClipboardItem, AITransformation } from '../types/clipboard';
-import type { AppSettings } from '../types/settings';
+import type { ClipboardItem, AITransformation, AITransformationRecord, AIModelInfo, ConversationMessage, AIProcessOptions, BuiltinTransform, PromptTemplate, PromptTemplateInput, SemanticSearchResult, BatchAIRequest, BatchAIResult, ModelDayMetrics, FeedbackAction, PresetScore } from '../types/clipboard';
+import type { AppSettings, BackendSettings } from '../types/settings';
+import { fromBackendSettings, toBackendSettings } from '../types/settings';
 
 /**
  * Clipboard operations
@@ -70,6 +71,20 @@ export class ClipboardService {
       throw new Error('Failed to clear clipboard history');
     }
   }
+
+  /**
+   * Search history by meaning using local embeddings
+   *
+   * Hybrid mode blends in keyword matches, which also finds items not indexed yet.
+   */
+  static async semanticSearch(query: string, limit = 20, hybrid = false): Promise<SemanticSearchResult[]> {
+    try {
+      return await invoke<SemanticSearchResult[]>('semantic_search', { query, limit, hybrid });
+    } catch (error) {
+      console.error('Failed to run semantic search:', error);
+      throw new Error('Failed to run semantic search');
+    }
+  }
 }
 
 /**
@@ -78,17 +93,22 @@ export class ClipboardService {
 export class AIService {
   /**
    * Process content with AI using custom prompt and optional context
+   *
+   * Requests wait in a bounded queue; listen to 'ai-queue-position' events
+   * (QueuePositionEvent) to show progress while waiting.
    */
   static async processWithAI(
     content: string, 
     customPrompt?: string,
-    contextItems?: string[]
+    contextItems?: string[],
+    options?: AIProcessOptions
   ): Promise<AITransformation[]> {
     try {
       return await invoke<AITransformation[]>('process_with_ai', { 
         content, 
         customPrompt,
-        contextItems
+        contextItems,
+        options
       });
     } catch (error) {
       console.error('Failed to process content with AI:', error);
@@ -96,7 +116,317 @@ export class AIService {
     }
   }
 
+  /**
+   * Apply one prompt to several clipboard items
+   *
+   * Emits 'ai-batch-progress' after each item. Items that fail are reported
+   * in the result rather than failing the whole batch.
+   */
+  static async batchProcess(request: BatchAIRequest): Promise<BatchAIResult> {
+    try {
+      return await invoke<BatchAIResult>('batch_process_with_ai', { request });
+    } catch (error) {
+      console.error('Failed to run AI batch:', error);
+      throw new Error('AI batch processing failed');
+    }
+  }
+
+  /**
+   * Generate the clipboard digest for a day (YYYY-MM-DD, default today)
+   *
+   * The digest is stored as a clipboard item and also announced with a
+   * 'clipboard-digest-created' event.
+   */
+  static async generateDigest(date?: string): Promise<ClipboardItem> {
+    try {
+      return await invoke<ClipboardItem>('generate_digest', { date });
+    } catch (error) {
+      console.error('Failed to generate digest:', error);
+      throw new Error('Failed to generate digest');
+    }
+  }
+
+  /**
+   * Get the AI transformations recorded for a clipboard item
+   */
+  static async getItemTransformations(itemId: string): Promise<AITransformationRecord[]> {
+    try {
+      return await invoke<AITransformationRecord[]>('get_item_transformations', { itemId });
+    } catch (error) {
+      console.error('Failed to get AI transformation history:', error);
+      throw new Error('Failed to fetch AI transformation history');
+    }
+  }
+
+  /**
+   * Copy an earlier AI result back to the clipboard without re-running the model
+   */
+  static async reapplyTransformation(transformationId: string): Promise<AITransformationRecord> {
+    try {
+      return await invoke<AITransformationRecord>('reapply_transformation', { transformationId });
+    } catch (error) {
+      console.error('Failed to re-apply AI transformation:', error);
+      throw new Error('Failed to re-apply AI transformation');
+    }
+  }
+
+  /**
+   * Record what the user did with a result; built-in results ("builtin-<id>-<uuid>") need the content type
+   */
+  static async recordFeedback(transformationId: string, action: FeedbackAction, contentType?: string): Promise<void> {
+    try {
+      await invoke('record_transformation_feedback', { transformationId, action, contentType });
+    } catch (error) {
+      console.error('Failed to record feedback:', error);
+      throw new Error('Failed to record feedback');
+    }
+  }
+
+  /**
+   * Get how templates and built-in transforms have done on a content type, best first
+   */
+  static async getPresetRankings(contentType: string): Promise<PresetScore[]> {
+    try {
+      return await invoke<PresetScore[]>('get_preset_rankings', { contentType });
+    } catch (error) {
+      console.error('Failed to get preset rankings:', error);
+      throw new Error('Failed to fetch preset rankings');
+    }
+  }
+
+  /**
+   * Get the refinement conversation about a clipboard item, oldest message first
+   */
+  static async getConversation(itemId: string): Promise<ConversationMessage[]> {
+    try {
+      return await invoke<ConversationMessage[]>('get_conversation', { itemId });
+    } catch (error) {
+      console.error('Failed to get conversation:', error);
+      throw new Error('Failed to get conversation');
+    }
+  }
+
+  /**
+   * Refine the latest result for an item ("shorter", "now in TypeScript", ...)
+   *
+   * Earlier turns of the item's conversation are sent along with the new
+   * instruction. Returns the assistant's reply.
+   */
+  static async sendFollowUp(
+    itemId: string,
+    instruction: string,
+    options?: AIProcessOptions
+  ): Promise<ConversationMessage> {
+    try {
+      return await invoke<ConversationMessage>('send_follow_up', { itemId, instruction, options });
+    } catch (error) {
+      console.error('Failed to send follow-up:', error);
+      throw new Error('Failed to send follow-up');
+    }
+  }
+
+  /**
+   * Copy the content of any conversation turn to the clipboard
+   */
+  static async copyConversationTurn(messageId: string): Promise<void> {
+    try {
+      await invoke('copy_conversation_turn', { messageId });
+    } catch (error) {
+      console.error('Failed to copy conversation turn:', error);
+      throw new Error('Failed to copy conversation turn');
+    }
+  }
+
+  /**
+   * Delete the refinement conversation about a clipboard item
+   */
+  static async clearConversation(itemId: string): Promise<void> {
+    try {
+      await invoke('clear_conversation', { itemId });
+    } catch (error) {
+      console.error('Failed to clear conversation:', error);
+      throw new Error('Failed to clear conversation');
+    }
+  }
+
+  /**
+   * Get token usage, latency and cost of AI calls per model and day
+   */
+  static async getMetrics(days = 30): Promise<ModelDayMetrics[]> {
+    try {
+      return await invoke<ModelDayMetrics[]>('get_ai_metrics', { days });
+    } catch (error) {
+      console.error('Failed to get AI metrics:', error);
+      throw new Error('Failed to get AI metrics');
+    }
+  }
+
+  /**
+   * Unload the local model to free memory until the next request
+   */
+  static async unloadModel(): Promise<void> {
+    try {
+      await invoke('unload_ai_model');
+    } catch (error) {
+      console.error('Failed to unload AI model:', error);
+      throw new Error('Failed to unload AI model');
+    }
+  }
+
+  /**
+   * List the models installed in the local Ollama server
+   */
+  static async listModels(): Promise<AIModelInfo[]> {
+    try {
+      return await invoke<AIModelInfo[]>('list_ai_models');
+    } catch (error) {
+      console.error('Failed to list AI models:', error);
+      throw new Error('Failed to list AI models');
+    }
+  }
+
+  /**
+   * Select the installed model used for AI requests
+   */
+  static async setActiveModel(model: string): Promise<void> {
+    try {
+      await invoke('set_active_model', { model });
+    } catch (error) {
+      console.error('Failed to set active AI model:', error);
+      throw new Error('Failed to set active AI model');
+    }
+  }
+
+  /**
+   * Download a model; listen to 'ai-model-pull-progress' events for ModelPullProgress updates
+   */
+  static async pullModel(model: string): Promise<void> {
+    try {
+      await invoke('pull_ai_model', { model });
+    } catch (error) {
+      console.error('Failed to pull AI model:', error);
+      throw new Error('Failed to pull AI model');
+    }
+  }
+
+  /**
+   * Remove every cached AI result
+   */
+  static async clearCache(): Promise<void> {
+    try {
+      await invoke('clear_ai_cache');
+    } catch (error) {
+      console.error('Failed to clear AI cache:', error);
+      throw new Error('Failed to clear AI cache');
+    }
+  }
+
+}
+
+/**
+ * Built-in (non-AI) transform operations
+ */
+export class TransformService {
+  /**
+   * List every built-in transform
+   */
+  static async listTransforms(): Promise<BuiltinTransform[]> {
+    try {
+      return await invoke<BuiltinTransform[]>('list_builtin_transforms');
+    } catch (error) {
+      console.error('Failed to list built-in transforms:', error);
+      throw new Error('Failed to list transforms');
+    }
+  }
+
+  /**
+   * Apply a single built-in transform to content
+   */
+  static async applyTransform(content: string, transformId: string): Promise<AITransformation> {
+    try {
+      return await invoke<AITransformation>('apply_builtin_transform', { content, transformId });
+    } catch (error) {
+      console.error('Failed to apply built-in transform:', error);
+      throw new Error('Transform failed');
+    }
+  }
+
+  /**
+   * Run every built-in transform that applies to the content, best rated first
+   */
+  static async runApplicable(content: string): Promise<AITransformation[]> {
+    try {
+      return await invoke<AITransformation[]>('run_builtin_transforms', { content });
+    } catch (error) {
+      console.error('Failed to run built-in transforms:', error);
+      throw new Error('Transforms failed');
+    }
+  }
+}
 
+/**
+ * Prompt template operations
+ */
+export class TemplateService {
+  /**
+   * List all prompt templates; with a content type, those that worked well on it come first
+   */
+  static async listTemplates(contentType?: string): Promise<PromptTemplate[]> {
+    try {
+      return await invoke<PromptTemplate[]>('list_prompt_templates', { contentType });
+    } catch (error) {
+      console.error('Failed to list prompt templates:', error);
+      throw new Error('Failed to fetch prompt templates');
+    }
+  }
+
+  /**
+   * Create a template, or update it when an id is given
+   */
+  static async saveTemplate(template: PromptTemplateInput): Promise<PromptTemplate> {
+    try {
+      return await invoke<PromptTemplate>('save_prompt_template', { template });
+    } catch (error) {
+      console.error('Failed to save prompt template:', error);
+      throw new Error('Failed to save prompt template');
+    }
+  }
+
+  /**
+   * Delete a prompt template
+   */
+  static async deleteTemplate(templateId: string): Promise<void> {
+    try {
+      await invoke('delete_prompt_template', { templateId });
+    } catch (error) {
+      console.error('Failed to delete prompt template:', error);
+      throw new Error('Failed to delete prompt template');
+    }
+  }
+
+  /**
+   * Export all templates as a TOML document
+   */
+  static async exportTemplates(): Promise<string> {
+    try {
+      return await invoke<string>('export_prompt_templates');
+    } catch (error) {
+      console.error('Failed to export prompt templates:', error);
+      throw new Error('Failed to export prompt templates');
+    }
+  }
+
+  /**
+   * Import templates from a TOML document, returning how many were imported
+   */
+  static async importTemplates(toml: string): Promise<number> {
+    try {
+      return await invoke<number>('import_prompt_templates', { toml });
+    } catch (error) {
+      console.error('Failed to import prompt templates:', error);
+      throw new Error('Failed to import prompt templates');
+    }
+  }
 }
 
 /**
@@ -108,7 +438,7 @@ export class SettingsService {
    */
   static async getSettings(): Promise<AppSettings> {
     try {
-      return await invoke<AppSettings>('get_settings');
+      return fromBackendSettings(await invoke<BackendSettings>('get_settings'));
     } catch (error) {
       console.error('Failed to get settings:', error);
       throw new Error('Failed to load settings');
@@ -117,10 +447,13 @@ export class SettingsService {
 
   /**
    * Update application settings
+   *
+   * Settings the panel does not edit keep their current backend values.
    */
   static async updateSettings(settings: AppSettings): Promise<void> {
     try {
-      await invoke('update_settings', { settings });
+      const current = await invoke<BackendSettings>('get_settings');
+      await invoke('update_settings', { settings: toBackendSettings(settings, current) });
     } catch (error) {
       console.error('Failed to update settings:', error);
       throw new Error('Failed to save settings');
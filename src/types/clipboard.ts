@@ -80,6 +80,18 @@ export interface ClipboardItem {
   tags: string[];
   /** Preview text (truncated version for UI) */
   preview: string;
+  /** Short title generated by AI enrichment */
+  title?: string;
+}
+
+/**
+ * Payload of 'clipboard-item-enriched' events
+ */
+export interface ItemEnrichedEvent {
+  itemId: string;
+  title: string;
+  /** Tags added by enrichment */
+  tags: string[];
 }
 
 /**
@@ -100,6 +112,244 @@ export interface AITransformation {
   isApplied: boolean;
   /** Type of transformation performed */
   transformationType: TransformationType;
+  /** Whether this result was served from the AI result cache */
+  isCached?: boolean;
+  /** Provider that produced the result, for AI results */
+  provider?: string;
+  /** Model that produced the result, for AI results */
+  model?: string;
+  /** Clipboard items that were given to the model as context */
+  contextSources?: ContextSource[];
+  /** Language of the result, when it is a piece of code */
+  language?: string;
+}
+
+/**
+ * One entry in the ordered AI provider chain
+ */
+export interface ProviderConfig {
+  /** Name reported on results */
+  name: string;
+  /** 'ollama' for an Ollama server, 'openai' for any OpenAI-compatible endpoint, 'embedded' for an in-process GGUF model */
+  kind: 'ollama' | 'openai' | 'embedded';
+  baseUrl: string;
+  model: string;
+  /** Bearer token for OpenAI-compatible providers; saved keys are returned as '********', send that back to keep them */
+  apiKey?: string;
+  /** Price per million prompt tokens, used for cost metrics */
+  inputCostPerMillion?: number;
+  /** Price per million completion tokens, used for cost metrics */
+  outputCostPerMillion?: number;
+  /** How long Ollama keeps the model loaded after a request ("10m", "-1", "0") */
+  keepAlive?: string;
+  /** GGUF file run by an embedded provider; defaults to the model path setting */
+  modelPath?: string;
+}
+
+/**
+ * AI call metrics aggregated for one model on one day
+ */
+export interface ModelDayMetrics {
+  /** Local calendar day (YYYY-MM-DD) */
+  day: string;
+  model: string;
+  calls: number;
+  failures: number;
+  promptTokens: number;
+  completionTokens: number;
+  avgTimeToFirstTokenMs?: number;
+  avgLatencyMs: number;
+  /** Completion tokens per second of generation, after the first token */
+  avgTokensPerSecond?: number;
+  /** Cost in the provider's currency, when prices are configured */
+  cost?: number;
+}
+
+/**
+ * Optional parameters for AI processing
+ */
+export interface AIProcessOptions {
+  /** Skip the result cache and always ask the model */
+  bypassCache?: boolean;
+  /** Source clipboard item, used to link results in history */
+  itemId?: string;
+  /** Prompt template to use */
+  templateId?: string;
+  /** Response token limit (defaults to the template, then AISettings.maxTokens) */
+  maxTokens?: number;
+  /** Sampling temperature (defaults to the template, then AISettings.temperature) */
+  temperature?: number;
+  /** Queue priority; interactive requests run before background work */
+  priority?: AIJobPriority;
+  /** Id reported as jobId in 'ai-queue-position' events */
+  requestId?: string;
+  /** Clipboard history items to include as context (instead of sending their contents) */
+  contextItemIds?: string[];
+  /**
+   * Add related recent items as context (defaults to the setting). To drop an
+   * automatically picked item, resend with the items to keep as contextItemIds
+   * and this set to false.
+   */
+  autoContext?: boolean;
+}
+
+/**
+ * Scheduling priority of an AI request
+ */
+export type AIJobPriority = 'interactive' | 'background';
+
+/**
+ * Payload of 'ai-queue-position' events
+ */
+export interface QueuePositionEvent {
+  /** Request id (AIProcessOptions.requestId when provided) */
+  jobId: string;
+  /** 0 when the request is running, otherwise its place in line */
+  position: number;
+  priority: AIJobPriority;
+}
+
+/**
+ * An AI transformation stored in history
+ */
+export interface AITransformationRecord {
+  /** Unique identifier (same as the transformation id) */
+  id: string;
+  /** Clipboard item the transformation was produced from, if any */
+  itemId?: string;
+  /** Prompt that produced the result */
+  prompt: string;
+  /** Model that produced the result */
+  model: string;
+  /** The stored transformation */
+  transformation: AITransformation;
+  /** Time the model took to respond in milliseconds */
+  latencyMs: number;
+  /** Prompt template used, if any */
+  templateId?: string;
+  /** Clipboard items that were given to the model as context */
+  contextSources: ContextSource[];
+  /** When the transformation was produced */
+  createdAt: string;
+}
+
+export type ConversationRole = 'user' | 'assistant';
+
+export interface ConversationMessage {
+  /** Unique identifier */
+  id: string;
+  /** Clipboard item the conversation is about */
+  itemId: string;
+  /** 'user' for instructions, 'assistant' for results */
+  role: ConversationRole;
+  /** Instruction or result text */
+  content: string;
+  /** Recorded transformation behind an assistant message */
+  transformationId?: string;
+  /** When the message was added */
+  createdAt: string;
+}
+
+/**
+ * Clipboard history item used as context for an AI transformation
+ */
+export interface ContextSource {
+  itemId: string;
+  contentType: string;
+  codeLanguage?: string;
+  /** Tokens of the item included in the prompt */
+  tokens: number;
+  /** Whether the item was cut to fit the context budget */
+  truncated: boolean;
+  /** Why the item was picked, when it was selected automatically */
+  reason?: string;
+}
+
+/**
+ * A model installed in the local Ollama server
+ */
+export interface AIModelInfo {
+  /** Model name including tag, e.g. "gpt-oss:20b" */
+  name: string;
+  /** Size on disk in bytes */
+  size: number;
+  /** Model family, e.g. "llama" */
+  family?: string;
+  /** Parameter count, e.g. "20.9B" */
+  parameterSize?: string;
+  /** Quantization, e.g. "Q4_K_M" */
+  quantizationLevel?: string;
+  /** When the model was last modified */
+  modifiedAt?: string;
+  /** Model digest */
+  digest?: string;
+  /** Whether this is the model selected for AI requests */
+  isActive: boolean;
+}
+
+/**
+ * Payload of 'ai-model-pull-progress' events
+ */
+export interface ModelPullProgress {
+  /** Model being pulled */
+  model: string;
+  /** Status reported by Ollama, e.g. "pulling manifest" or "success" */
+  status: string;
+  /** Digest of the layer currently downloading */
+  digest?: string;
+  /** Total bytes of the current layer */
+  total?: number;
+  /** Bytes of the current layer downloaded so far */
+  completed?: number;
+  /** Download progress of the current layer (0-100) */
+  percent?: number;
+  /** Whether the pull has finished successfully */
+  done: boolean;
+}
+
+/**
+ * Reusable prompt template
+ *
+ * The body may use {{content}}, {{language}}, {{content_type}} and {{date}} placeholders.
+ */
+export interface PromptTemplate {
+  /** Unique identifier */
+  id: string;
+  /** Unique display name */
+  name: string;
+  /** What the template is for */
+  description: string;
+  /** Prompt text with placeholders */
+  body: string;
+  /** Default model for this template */
+  model?: string;
+  /** Default temperature for this template */
+  temperature?: number;
+  /** Default response token limit for this template */
+  maxTokens?: number;
+  /** When the template was created */
+  createdAt: string;
+  /** When the template was last changed */
+  updatedAt: string;
+}
+
+/**
+ * Fields used to create or update a prompt template
+ */
+export type PromptTemplateInput = Omit<PromptTemplate, 'id' | 'createdAt' | 'updatedAt'> & { id?: string };
+
+/**
+ * A deterministic transform that runs locally without the AI model
+ */
+export interface BuiltinTransform {
+  /** Stable identifier used to apply the transform */
+  id: string;
+  /** Human-readable title */
+  title: string;
+  /** What the transform does */
+  description: string;
+  /** Type of transformation performed */
+  transformationType: TransformationType;
 }
 
 /**
@@ -113,7 +363,9 @@ export enum TransformationType {
   TRANSLATION = 'translation',
   CLEANUP = 'cleanup',
   ENHANCEMENT = 'enhancement',
-  VALIDATION = 'validation'
+  VALIDATION = 'validation',
+  /** One code block split out of a longer answer */
+  CODE_BLOCK = 'code_block'
 }
 
 /**
@@ -196,3 +448,99 @@ export interface SearchResult {
   /** Total time taken for the search in milliseconds */
   searchTime: number;
 }
+
+/**
+ * A clipboard item matched by semantic search
+ */
+export interface SemanticSearchResult {
+  item: ClipboardItem;
+  /** Final ranking score (0-1) */
+  score: number;
+  /** Cosine similarity between the query and the item */
+  semanticScore: number;
+  /** Share of query terms found in the item (hybrid mode only) */
+  keywordScore?: number;
+}
+
+/**
+ * Criteria for selecting clipboard history items
+ */
+export interface ClipboardFilter {
+  /** Text that the content, title or tags must contain (case-insensitive) */
+  query?: string;
+  contentType?: string;
+  codeLanguage?: string;
+  /** Only items captured at or after this time (ISO 8601) */
+  since?: string;
+  /** Only items captured before this time (ISO 8601) */
+  until?: string;
+  favoritesOnly?: boolean;
+}
+
+/**
+ * A prompt to apply to several clipboard items
+ */
+export interface BatchAIRequest {
+  /** Items to process (at most 50); takes precedence over the filter */
+  itemIds?: string[];
+  /** Selects the items when no ids are given */
+  filter?: ClipboardFilter;
+  /** Instruction applied to every item; a template can be chosen in the options instead */
+  prompt?: string;
+  /** Options applied to every item's request */
+  options?: AIProcessOptions;
+  /** Also merge the per-item results into one output */
+  combine?: boolean;
+  /** Ask the model to merge the results with this instruction instead of concatenating them */
+  combinePrompt?: string;
+  /** Caller-chosen id reported in progress events */
+  batchId?: string;
+}
+
+export interface BatchItemResult {
+  itemId: string;
+  transformations: AITransformation[];
+  /** Why the item failed, if it did */
+  error?: string;
+}
+
+export interface BatchAIResult {
+  batchId: string;
+  results: BatchItemResult[];
+  succeeded: number;
+  failed: number;
+  /** Merged output, when requested and at least one item succeeded */
+  combined?: AITransformation;
+}
+
+/**
+ * Payload of 'ai-batch-progress' events, sent after each item
+ */
+export interface BatchProgressEvent {
+  batchId: string;
+  itemId: string;
+  /** Items finished so far, including this one */
+  completed: number;
+  total: number;
+  error?: string;
+}
+
+/**
+ * What the user did with a transformation result
+ */
+export type FeedbackAction = 'applied' | 'copied' | 'rejected' | 'thumbs_up' | 'thumbs_down';
+
+/**
+ * How a prompt template or built-in transform has done on one content type
+ */
+export interface PresetScore {
+  kind: 'template' | 'builtin';
+  id: string;
+  /** Between -1 and 1; 0 without feedback */
+  score: number;
+  applied: number;
+  copied: number;
+  rejected: number;
+  thumbsUp: number;
+  thumbsDown: number;
+}
@@ -60,7 +60,7 @@ export interface PrivacySettings {
 export interface AISettings {
   /** Whether AI processing is enabled */
   enabled: boolean;
-  /** Local model path for gpt-oss */
+  /** GGUF model run in-process (builds with the embedded-model feature) */
   modelPath: string;
   /** Whether to use online fallback if local model fails */
   useOnlineFallback: boolean;
@@ -112,6 +112,31 @@ export interface AppSettings {
   lastUpdated: Date;
 }
 
+/**
+ * Settings as the backend stores them (flat, snake_case)
+ *
+ * The backend rejects unknown fields, so only AppSettings fields that exist
+ * here reach it; the rest are frontend preferences. Fields the panel does not
+ * edit are sent back unchanged.
+ */
+export interface BackendSettings {
+  hotkey_enabled: boolean;
+  hotkey_modifiers: string[];
+  hotkey_key: string;
+  theme: string;
+  panel_opacity: number;
+  store_history: boolean;
+  max_history_items: number;
+  ai_enabled: boolean;
+  model_path: string;
+  use_online_fallback: boolean;
+  max_processing_time: number;
+  cache_ai_results: boolean;
+  ai_temperature: number;
+  ai_max_tokens: number;
+  [key: string]: unknown;
+}
+
 /**
  * Default settings for the application
  */
@@ -140,7 +165,7 @@ export const DEFAULT_SETTINGS: AppSettings = {
     enabled: true,
     modelPath: '',
     useOnlineFallback: false,
-    maxProcessingTime: 30,
+    maxProcessingTime: 120,
     temperature: 0.7,
     maxTokens: 1000,
     cacheResults: true,
@@ -156,3 +181,61 @@ export const DEFAULT_SETTINGS: AppSettings = {
   version: '1.0.0',
   lastUpdated: new Date()
 };
+
+/**
+ * Build the panel's settings from the backend's, with defaults for frontend-only preferences
+ */
+export function fromBackendSettings(backend: BackendSettings): AppSettings {
+  return {
+    ...DEFAULT_SETTINGS,
+    hotkey: {
+      enabled: backend.hotkey_enabled,
+      modifiers: backend.hotkey_modifiers,
+      key: backend.hotkey_key,
+      displayName: [...backend.hotkey_modifiers, backend.hotkey_key].join('+')
+    },
+    ui: {
+      ...DEFAULT_SETTINGS.ui,
+      theme: backend.theme as UISettings['theme'],
+      panelOpacity: backend.panel_opacity
+    },
+    privacy: {
+      ...DEFAULT_SETTINGS.privacy,
+      storeHistory: backend.store_history,
+      maxHistoryItems: backend.max_history_items
+    },
+    ai: {
+      ...DEFAULT_SETTINGS.ai,
+      enabled: backend.ai_enabled,
+      modelPath: backend.model_path,
+      useOnlineFallback: backend.use_online_fallback,
+      maxProcessingTime: backend.max_processing_time,
+      cacheResults: backend.cache_ai_results,
+      temperature: backend.ai_temperature,
+      maxTokens: backend.ai_max_tokens
+    }
+  };
+}
+
+/**
+ * Apply the panel's settings to the backend's current settings
+ */
+export function toBackendSettings(settings: AppSettings, current: BackendSettings): BackendSettings {
+  return {
+    ...current,
+    hotkey_enabled: settings.hotkey.enabled,
+    hotkey_modifiers: settings.hotkey.modifiers,
+    hotkey_key: settings.hotkey.key,
+    theme: settings.ui.theme,
+    panel_opacity: settings.ui.panelOpacity,
+    store_history: settings.privacy.storeHistory,
+    max_history_items: settings.privacy.maxHistoryItems,
+    ai_enabled: settings.ai.enabled,
+    model_path: settings.ai.modelPath,
+    use_online_fallback: settings.ai.useOnlineFallback,
+    max_processing_time: settings.ai.maxProcessingTime,
+    cache_ai_results: settings.ai.cacheResults,
+    ai_temperature: settings.ai.temperature,
+    ai_max_tokens: settings.ai.maxTokens
+  };
+}